@@ -0,0 +1,217 @@
+use criterion::{BatchSize, Criterion, criterion_group, criterion_main};
+use json_deref::{
+    CompiledTemplate, SharedResolver, SourceBuilder, resolve_json, resolve_json_in_place,
+    resolve_json_shared, resolve_json_sparse, resolve_template_with_source,
+};
+use serde_json::{Value, json};
+use std::hint::black_box;
+
+/// A large value referenced from 1000 places
+fn repeated_references() -> Value {
+    let catalog: Vec<Value> = (0..200)
+        .map(|i| json!({ "id": i, "name": format!("item {i}"), "tags": ["a", "b", "c"] }))
+        .collect();
+    let references: Vec<Value> = (0..1000).map(|_| json!("{/catalog}")).collect();
+
+    json!({ "catalog": catalog, "references": references })
+}
+
+fn bench_repeated_references(c: &mut Criterion) {
+    let input = repeated_references();
+
+    let mut group = c.benchmark_group("value referenced 1000 times");
+    group.bench_function("resolve_json", |b| {
+        b.iter(|| resolve_json(black_box(&input)))
+    });
+    group.bench_function("resolve_json_shared", |b| {
+        b.iter(|| resolve_json_shared(black_box(&input)))
+    });
+    group.finish();
+}
+
+/// A source with tens of thousands of paths
+fn large_source() -> Value {
+    let users: Vec<Value> = (0..10_000)
+        .map(|i| json!({ "name": format!("user {i}"), "address": { "city": "Berlin", "zip": i } }))
+        .collect();
+    json!({ "users": users })
+}
+
+fn bench_large_source(c: &mut Criterion) {
+    let resolver = SourceBuilder::new().merge(&large_source()).build();
+    let exact = json!({ "name": "{/users/5000/name}", "zip": "{/users/9999/address/zip}" });
+    let wildcard = json!({ "names": "{/users/*/name}", "zips": "{/users/*/address/zip}" });
+
+    let mut group = c.benchmark_group("large source");
+    group.bench_function("exact lookups", |b| {
+        b.iter(|| resolver.resolve(black_box(&exact)))
+    });
+    group.bench_function("wildcard queries", |b| {
+        b.iter(|| resolver.resolve(black_box(&wildcard)))
+    });
+    group.finish();
+}
+
+fn bench_shared_source(c: &mut Criterion) {
+    let source = large_source();
+    let resolver = SourceBuilder::new().merge(&source).build();
+    let shared = SharedResolver::new(&source);
+    let template = json!({ "name": "{/users/5000/name}", "address": "{/users/9999/address}" });
+
+    // Every request takes its own handle on the source, as a server handler would
+    let mut group = c.benchmark_group("source handle per request");
+    group.sample_size(10);
+    group.bench_function("Resolver", |b| {
+        b.iter(|| resolver.clone().resolve(black_box(&template)))
+    });
+    group.bench_function("SharedResolver", |b| {
+        b.iter(|| shared.clone().resolve(black_box(&template)))
+    });
+    group.finish();
+}
+
+/// Nested arrays whose elements refer to each other
+fn nested_arrays() -> Value {
+    let grid: Vec<Value> = (0..200)
+        .map(|row| {
+            let cells: Vec<Value> = (0..50)
+                .map(|column| match column % 2 {
+                    0 => json!([row, column]),
+                    _ => json!("{../0/0}"),
+                })
+                .collect();
+            json!(cells)
+        })
+        .collect();
+    json!({ "grid": grid, "corner": "{/grid/199/48}" })
+}
+
+fn bench_nested_arrays(c: &mut Criterion) {
+    let input = nested_arrays();
+
+    c.bench_function("nested arrays", |b| {
+        b.iter(|| resolve_json(black_box(&input)))
+    });
+}
+
+/// A large document with a handful of placeholders
+fn mostly_static() -> Value {
+    let records: Vec<Value> = (0..5_000)
+        .map(|i| json!({ "id": i, "name": format!("record {i}"), "flags": [true, false] }))
+        .collect();
+    json!({
+        "records": records,
+        "summary": { "first": "{/records/0/name}", "last": "{/records/4999/name}" }
+    })
+}
+
+fn bench_mostly_static(c: &mut Criterion) {
+    let input = mostly_static();
+
+    let mut group = c.benchmark_group("mostly static document");
+    group.bench_function("resolve_json", |b| {
+        b.iter(|| resolve_json(black_box(&input)))
+    });
+    group.bench_function("resolve_json_sparse", |b| {
+        b.iter(|| resolve_json_sparse(black_box(&input)))
+    });
+    group.finish();
+}
+
+fn bench_in_place(c: &mut Criterion) {
+    let input = mostly_static();
+
+    let mut group = c.benchmark_group("document resolved in place");
+    group.bench_function("resolve_json", |b| {
+        b.iter(|| resolve_json(black_box(&input)))
+    });
+    // The copy the document is resolved in is made outside of the measurement
+    group.bench_function("resolve_json_in_place", |b| {
+        b.iter_batched(
+            || input.clone(),
+            |mut document| {
+                resolve_json_in_place(black_box(&mut document));
+                document
+            },
+            BatchSize::LargeInput,
+        )
+    });
+    group.finish();
+}
+
+/// An object with many keys, half of them referring to the other half
+fn wide_object() -> Value {
+    let map: serde_json::Map<String, Value> = (0..20_000)
+        .map(|i| match i % 2 {
+            0 => (format!("key{i}"), json!(i)),
+            _ => (format!("key{i}"), json!(format!("{{/key{}}}", i - 1))),
+        })
+        .collect();
+    Value::Object(map)
+}
+
+fn bench_wide_object(c: &mut Criterion) {
+    let input = wide_object();
+
+    c.bench_function("wide object", |b| {
+        b.iter(|| resolve_json(black_box(&input)))
+    });
+}
+
+/// One template filled from many small sources
+fn template_and_sources() -> (Value, Vec<Value>) {
+    let template = json!({
+        "greeting": "Hello, {/user/name}!",
+        "contact": { "email": "{/user/email}", "city": "{/user/address/city}" },
+        "static": { "version": 2, "features": ["a", "b", "c"], "limits": { "rps": 100 } }
+    });
+    let sources = (0..10_000)
+        .map(|i| {
+            json!({
+                "user": {
+                    "name": format!("user {i}"),
+                    "email": format!("user{i}@example.com"),
+                    "address": { "city": "Berlin", "zip": i }
+                },
+                "history": [1, 2, 3, 4, 5]
+            })
+        })
+        .collect();
+    (template, sources)
+}
+
+fn bench_compiled_template(c: &mut Criterion) {
+    let (template, sources) = template_and_sources();
+    let compiled = CompiledTemplate::new(&template);
+
+    let mut group = c.benchmark_group("template applied to 10k sources");
+    group.sample_size(10);
+    group.bench_function("resolve_template_with_source", |b| {
+        b.iter(|| {
+            for source in &sources {
+                black_box(resolve_template_with_source(&template, black_box(source)));
+            }
+        })
+    });
+    group.bench_function("CompiledTemplate::apply", |b| {
+        b.iter(|| {
+            for source in &sources {
+                black_box(compiled.apply(black_box(source)));
+            }
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_repeated_references,
+    bench_large_source,
+    bench_shared_source,
+    bench_nested_arrays,
+    bench_mostly_static,
+    bench_in_place,
+    bench_compiled_template,
+    bench_wide_object
+);
+criterion_main!(benches);
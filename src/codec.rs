@@ -0,0 +1,68 @@
+use serde_json::Value;
+use std::fmt;
+
+/// A transformation applied to the resolved value at one output path,
+/// e.g. to decrypt `/db/password` wherever its content came from.
+///
+/// Unlike filters, which belong to a placeholder, a codec belongs to a location in the output
+/// and also applies to values that were never placeholders. See [`crate::resolve_json_with_codecs`].
+pub struct Codec {
+    f: Box<dyn Fn(Value) -> Value>,
+}
+
+impl Codec {
+    pub fn new<F>(f: F) -> Self
+    where
+        F: Fn(Value) -> Value + 'static,
+    {
+        Codec { f: Box::new(f) }
+    }
+
+    pub fn apply(&self, value: Value) -> Value {
+        (self.f)(value)
+    }
+}
+
+impl fmt::Debug for Codec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Codec").finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AbsolutePath;
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_codec_applies_to_its_path_only() {
+        let input = json!({
+            "secrets": { "password": "terces" },
+            "db": { "password": "{/secrets/password}", "user": "{/secrets/password}" },
+            "plain": "static"
+        });
+        let reverse = Codec::new(|value| match value {
+            Value::String(text) => Value::String(text.chars().rev().collect()),
+            other => other,
+        });
+        let codecs = HashMap::from([
+            (AbsolutePath::new("/db/password"), reverse),
+            (
+                AbsolutePath::new("/missing"),
+                Codec::new(|_| json!("never")),
+            ),
+        ]);
+
+        let resolved = crate::resolve_json_with_codecs(&input, &codecs);
+        assert_eq!(
+            resolved,
+            json!({
+                "secrets": { "password": "terces" },
+                "db": { "password": "secret", "user": "terces" },
+                "plain": "static"
+            })
+        );
+    }
+}
@@ -0,0 +1,110 @@
+use serde_json::{Number, Value};
+
+/// Converts a value of the `config` crate to JSON.
+///
+/// Integers that don't fit a JSON number are kept as strings, and non-finite floats become `null`.
+pub fn config_to_json(value: &config::Value) -> Value {
+    match &value.kind {
+        config::ValueKind::Nil => Value::Null,
+        config::ValueKind::Boolean(b) => Value::Bool(*b),
+        config::ValueKind::I64(i) => Value::from(*i),
+        config::ValueKind::U64(u) => Value::from(*u),
+        config::ValueKind::I128(i) => {
+            Number::from_i128(*i).map_or_else(|| Value::String(i.to_string()), Value::Number)
+        }
+        config::ValueKind::U128(u) => {
+            Number::from_u128(*u).map_or_else(|| Value::String(u.to_string()), Value::Number)
+        }
+        config::ValueKind::Float(f) => Number::from_f64(*f).map_or(Value::Null, Value::Number),
+        config::ValueKind::String(s) => Value::String(s.clone()),
+        config::ValueKind::Table(table) => Value::Object(
+            table
+                .iter()
+                .map(|(key, value)| (key.clone(), config_to_json(value)))
+                .collect(),
+        ),
+        config::ValueKind::Array(array) => Value::Array(array.iter().map(config_to_json).collect()),
+    }
+}
+
+/// Converts JSON to a value of the `config` crate, without an origin
+pub fn json_to_config(value: &Value) -> config::Value {
+    let kind = match value {
+        Value::Null => config::ValueKind::Nil,
+        Value::Bool(b) => config::ValueKind::Boolean(*b),
+        Value::Number(n) => match (n.as_i64(), n.as_u64()) {
+            (Some(i), _) => config::ValueKind::I64(i),
+            (None, Some(u)) => config::ValueKind::U64(u),
+            _ => config::ValueKind::Float(n.as_f64().unwrap_or(f64::NAN)),
+        },
+        Value::String(s) => config::ValueKind::String(s.clone()),
+        Value::Array(array) => config::ValueKind::Array(array.iter().map(json_to_config).collect()),
+        Value::Object(map) => config::ValueKind::Table(
+            map.iter()
+                .map(|(key, value)| (key.clone(), json_to_config(value)))
+                .collect(),
+        ),
+    };
+    config::Value::new(None, kind)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_resolve_config_value_round_trip() {
+        let settings = config::Config::builder()
+            .set_default("host", "localhost")
+            .unwrap()
+            .set_default("port", 8080)
+            .unwrap()
+            .set_override("server.url", "http://{/host}/")
+            .unwrap()
+            .set_override("server.port", "{/port}")
+            .unwrap()
+            .build()
+            .unwrap();
+        let value: config::Value = settings.try_deserialize().unwrap();
+
+        let resolved = crate::resolve_config_value(&value);
+
+        assert_eq!(
+            config_to_json(&resolved),
+            json!({
+                "host": "localhost",
+                "port": 8080,
+                "server": { "url": "http://localhost/", "port": 8080 }
+            })
+        );
+        // The whole placeholder keeps the type, so the value reads back as a number
+        let port = resolved
+            .into_table()
+            .unwrap()
+            .remove("server")
+            .unwrap()
+            .into_table()
+            .unwrap()
+            .remove("port")
+            .unwrap()
+            .into_uint()
+            .unwrap();
+        assert_eq!(port, 8080);
+    }
+
+    #[test]
+    fn test_config_conversions() {
+        let json = json!({ "a": [1, -2, 1.5, true, null, "x"], "b": {} });
+        assert_eq!(config_to_json(&json_to_config(&json)), json);
+
+        // Integers beyond 64 bits keep their digits with `arbitrary_precision`
+        #[cfg(not(feature = "arbitrary_precision"))]
+        {
+            let huge = config::Value::new(None, config::ValueKind::U128(u128::MAX));
+            assert_eq!(config_to_json(&huge), json!(u128::MAX.to_string()));
+        }
+        let nan = config::Value::new(None, config::ValueKind::Float(f64::NAN));
+        assert_eq!(config_to_json(&nan), Value::Null);
+    }
+}
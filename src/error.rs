@@ -0,0 +1,199 @@
+use crate::{kind::ValueKind, parsing::path::AbsolutePath};
+use std::{fmt, io};
+
+/// A problem found while resolving placeholders.
+///
+/// These are only reported by the strict entry points; the default functions
+/// leave problematic placeholders unchanged instead.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResolveError {
+    /// The placeholder target does not exist
+    Unresolved {
+        /// Path of the field containing the placeholder
+        at: AbsolutePath,
+        /// Placeholder body, without braces
+        reference: String,
+    },
+    /// The placeholder target exists but can't be converted to the requested type
+    InvalidCast {
+        /// Path of the field containing the placeholder
+        at: AbsolutePath,
+        /// Placeholder body, without braces
+        reference: String,
+    },
+    /// The placeholder target exists but one of the filters can't be applied to it
+    InvalidFilter {
+        /// Path of the field containing the placeholder
+        at: AbsolutePath,
+        /// Placeholder body, without braces
+        reference: String,
+    },
+    /// A relative placeholder climbs above the document root
+    EscapesRoot {
+        /// Path of the field containing the placeholder
+        at: AbsolutePath,
+        /// The relative path
+        reference: String,
+    },
+    /// A placeholder marked with `!` refers to a missing, null or empty value
+    RequiredEmpty {
+        /// Path of the field containing the placeholder
+        at: AbsolutePath,
+        /// Placeholder body, without braces
+        reference: String,
+    },
+    /// A resolved field doesn't have the expected JSON type
+    KindMismatch {
+        /// Path of the field in the resolved document
+        at: AbsolutePath,
+        /// The type the field should have
+        expected: ValueKind,
+        /// The type the field has, None if the field is missing
+        found: Option<ValueKind>,
+    },
+    /// A value pulled from the source refers back to itself, directly or through other values.
+    /// Also reported for placeholders of the document leading back to their own field,
+    /// see [`crate::find_reference_cycles`].
+    Cycle {
+        /// Path of the field containing the placeholder
+        at: AbsolutePath,
+        /// Placeholder body, without braces
+        reference: String,
+        /// The paths of the cycle, each referring to the next one and the last one back to
+        /// the first. Fields of the document start with `at`, `[/a, /b]` for `{/b}` at `/a`
+        /// and `{/a}` at `/b`, while values of the source start with the one pulled again.
+        chain: Vec<AbsolutePath>,
+    },
+    /// The resolved document would be larger than allowed
+    OutputTooLarge {
+        /// Path of the field whose resolution crossed the limit
+        at: AbsolutePath,
+        /// The maximum size of the serialized output, in bytes
+        limit: usize,
+    },
+    /// A placeholder body is longer than [`crate::ResolveOptions::max_reference_len`].
+    /// The placeholder is kept as literal text.
+    ReferenceTooLong {
+        /// Path of the field containing the placeholder
+        at: AbsolutePath,
+        /// The maximum length of a placeholder body, in bytes
+        limit: usize,
+    },
+}
+
+impl fmt::Display for ResolveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ResolveError::Unresolved { at, reference } => {
+                write!(f, "unresolved reference {{{reference}}} at {at}")
+            }
+            ResolveError::InvalidCast { at, reference } => {
+                write!(f, "can't cast {{{reference}}} at {at}")
+            }
+            ResolveError::InvalidFilter { at, reference } => {
+                write!(f, "can't apply filters of {{{reference}}} at {at}")
+            }
+            ResolveError::EscapesRoot { at, reference } => {
+                write!(f, "reference {{{reference}}} at {at} climbs above the root")
+            }
+            ResolveError::RequiredEmpty { at, reference } => {
+                write!(
+                    f,
+                    "required reference {{{reference}}} at {at} is missing or empty"
+                )
+            }
+            ResolveError::KindMismatch {
+                at,
+                expected,
+                found: Some(found),
+            } => write!(f, "expected {expected} at {at}, found {found}"),
+            ResolveError::KindMismatch {
+                at,
+                expected,
+                found: None,
+            } => write!(f, "expected {expected} at {at}, found nothing"),
+            ResolveError::Cycle {
+                at,
+                reference,
+                chain,
+            } => {
+                write!(f, "reference {{{reference}}} at {at} is part of a cycle: ")?;
+                for path in chain {
+                    write!(f, "{path} -> ")?;
+                }
+                match chain.first() {
+                    Some(first) => write!(f, "{first}"),
+                    None => write!(f, "{at}"),
+                }
+            }
+            ResolveError::OutputTooLarge { at, limit } => {
+                write!(f, "output exceeds {limit} bytes when resolving {at}")
+            }
+            ResolveError::ReferenceTooLong { at, limit } => {
+                write!(f, "reference at {at} is longer than {limit} bytes")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ResolveError {}
+
+/// A failure of the helpers reading and deserializing a document,
+/// such as [`crate::resolve_json_reader_to_object`], by cause.
+#[derive(Debug)]
+pub enum ResolveReadError {
+    /// The document, or a file it includes, couldn't be read
+    Io(io::Error),
+    /// The document isn't valid JSON (or JSON5)
+    Parse(serde_json::Error),
+    /// A placeholder couldn't be resolved, only reported in strict mode
+    Resolve(ResolveError),
+    /// The resolved document doesn't match the requested type
+    Deserialize(serde_json::Error),
+}
+
+impl ResolveReadError {
+    /// Sorts an error of reading and parsing JSON into [`ResolveReadError::Io`]
+    /// and [`ResolveReadError::Parse`]
+    pub(crate) fn from_read(error: serde_json::Error) -> Self {
+        match error.is_io() {
+            true => ResolveReadError::Io(error.into()),
+            false => ResolveReadError::Parse(error),
+        }
+    }
+}
+
+impl fmt::Display for ResolveReadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ResolveReadError::Io(error) => write!(f, "can't read the document: {error}"),
+            ResolveReadError::Parse(error) => write!(f, "can't parse the document: {error}"),
+            ResolveReadError::Resolve(error) => write!(f, "can't resolve the document: {error}"),
+            ResolveReadError::Deserialize(error) => {
+                write!(f, "can't deserialize the resolved document: {error}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ResolveReadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ResolveReadError::Io(error) => Some(error),
+            ResolveReadError::Parse(error) | ResolveReadError::Deserialize(error) => Some(error),
+            ResolveReadError::Resolve(error) => Some(error),
+        }
+    }
+}
+
+impl From<io::Error> for ResolveReadError {
+    fn from(error: io::Error) -> Self {
+        ResolveReadError::Io(error)
+    }
+}
+
+impl From<ResolveError> for ResolveReadError {
+    fn from(error: ResolveError) -> Self {
+        ResolveReadError::Resolve(error)
+    }
+}
@@ -0,0 +1,40 @@
+use serde_json::Value;
+use std::fmt;
+
+/// The JSON type of a value, without its content.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ValueKind {
+    String,
+    Number,
+    Bool,
+    Array,
+    Object,
+    Null,
+}
+
+impl ValueKind {
+    pub fn of(value: &Value) -> Self {
+        match value {
+            Value::String(_) => ValueKind::String,
+            Value::Number(_) => ValueKind::Number,
+            Value::Bool(_) => ValueKind::Bool,
+            Value::Array(_) => ValueKind::Array,
+            Value::Object(_) => ValueKind::Object,
+            Value::Null => ValueKind::Null,
+        }
+    }
+}
+
+impl fmt::Display for ValueKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ValueKind::String => "string",
+            ValueKind::Number => "number",
+            ValueKind::Bool => "bool",
+            ValueKind::Array => "array",
+            ValueKind::Object => "object",
+            ValueKind::Null => "null",
+        };
+        f.write_str(name)
+    }
+}
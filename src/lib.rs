@@ -1,23 +1,75 @@
+mod codec;
+#[cfg(feature = "config")]
+mod config_value;
+mod error;
+mod kind;
+mod options;
 mod parsing;
+mod report;
+mod resolver;
+mod source;
+mod template;
+
+pub use codec::Codec;
+#[cfg(feature = "config")]
+pub use config_value::{config_to_json, json_to_config};
+pub use error::{ResolveError, ResolveReadError};
+pub use kind::ValueKind;
+pub use options::{IndexStyle, Missing, ResolveOptions};
+pub use parsing::path::AbsolutePath;
+pub use report::{
+    PhaseTimings, ReportFormat, ResolveOutcome, ResolveStats, Unresolved, UnresolvedRef, Warning,
+    write_validation_report,
+};
+pub use resolver::{MultiSourceResolver, Resolver, SharedResolver, SourceBuilder};
+pub use source::{FnSource, Source};
+pub use template::CompiledTemplate;
 
 use parsing::{
-    collect_all_absolute_paths, expand_absolute_paths, extract_values_by_paths, make_deps_path_map,
-    path::{AbsolutePath, RelativePath},
-    values_resolving::{resolve_recursive, resolve_values},
+    collect_all_absolute_paths, collect_leaves, collect_placeholder_paths,
+    collect_replaced_strings, count_placeholders, expand_absolute_paths, extract_values_by_paths,
+    find_cycles, holds_placeholder,
+    index::WILDCARD,
+    make_deps_path_map, map_leaves, merge_arrays_by, nesting_depth, optional_paths,
+    path::RelativePath,
+    reference::Syntax,
+    referenced_source_paths, stringify_scalars, strip_placeholders,
+    suggest::{MAX_DISTANCE, closest_path},
+    value_at, value_at_mut,
+    values_resolving::ValueResolver,
 };
+use report::Problems;
 use serde::de::DeserializeOwned;
 use serde_json::Value;
-use std::collections::{HashMap, HashSet};
+use std::{
+    collections::{HashMap, HashSet},
+    time::Instant,
+};
+#[cfg(feature = "std")]
+use {
+    parsing::INCLUDE_KEY,
+    std::{
+        fs::File,
+        io::{self, BufReader},
+        path::{Component, Path},
+    },
+};
 
 /// A trait to add convenient JSON template and resolution methods for serde_json::Value.
 pub trait JsonResolvableFunctions {
     /// Resolve JSON placeholders within itself.
     fn resolve_internal_dependencies(&self) -> Value;
 
+    /// Resolve JSON placeholders within itself, replacing the original value.
+    fn resolve_internal_dependencies_in_place(&mut self);
+
     /// Resolve the JSON as a template using another JSON as a source.
     ///
     /// - `source`: The source JSON containing the values for placeholders.
     fn resolve_template_with_source(&self, source: &Value) -> Value;
+
+    /// Resolve JSON placeholders within itself and deserialize the result.
+    fn resolve_to<T: DeserializeOwned>(&self) -> Result<T, serde_json::Error>;
 }
 
 impl JsonResolvableFunctions for Value {
@@ -25,10 +77,13 @@ impl JsonResolvableFunctions for Value {
     ///
     /// This uses the JSON itself as the source for resolving placeholders. Placeholders
     /// can reference fields in the JSON using either:
-    /// - **Absolute paths**: `{/path/to/value}`
+    /// - **Absolute paths**: `{/path/to/value}`. A double leading slash, `{//path/to/value}`,
+    ///   is an explicit root anchor and means the same.
     /// - **Relative paths**:
     ///   - `{field_name}`: Refers to sibling fields in the same object.
     ///   - `{../../parent_field}`: Refers to fields higher up in the hierarchy.
+    /// - **Quoted segments**: `{/["  spaced  "]/key}`. A segment written as a JSON string in
+    ///   brackets is taken literally, for keys with surrounding spaces, `:` or `|`.
     ///
     /// If a placeholder cannot be resolved (e.g., nonexistent paths), it is left unchanged.
     ///
@@ -89,12 +144,36 @@ impl JsonResolvableFunctions for Value {
         resolve_json(self)
     }
 
+    /// Resolves internal dependencies within the JSON object in place.
+    ///
+    /// Behaves exactly like [`JsonResolvableFunctions::resolve_internal_dependencies`],
+    /// but stores the result in `self` instead of returning a copy.
+    ///
+    /// ## Example:
+    /// ```
+    /// use serde_json::json;
+    /// use json_deref::JsonResolvableFunctions;
+    ///
+    /// let mut config = json!({ "host": "localhost", "url": "http://{host}/" });
+    /// config["host"] = json!("example.com");
+    ///
+    /// config.resolve_internal_dependencies_in_place();
+    ///
+    /// assert_eq!(config, json!({ "host": "example.com", "url": "http://example.com/" }));
+    /// ```
+    fn resolve_internal_dependencies_in_place(&mut self) {
+        resolve_json_in_place(self)
+    }
+
     /// Resolves the JSON as a template, using another JSON as the source for placeholders.
     ///
     /// The current JSON object acts as a template, and placeholders (e.g., `{/path/to/value}`) are
     /// resolved using the provided source JSON. All placeholders are replaced with their resolved
     /// values if they exist in the source JSON. Invalid or unresolvable placeholders remain unchanged.
     ///
+    /// Relative placeholders (e.g., `{../sibling}`) are resolved against the position of the
+    /// field in the template, including array indices, and then looked up in the source.
+    ///
     /// # Supported JSON Types:
     /// - Strings
     /// - Numbers
@@ -146,15 +225,726 @@ impl JsonResolvableFunctions for Value {
     fn resolve_template_with_source(&self, source: &Value) -> Value {
         resolve_template_with_source(self, source)
     }
+
+    /// Resolves internal dependencies and deserializes the result into `T`,
+    /// like [`resolve_json_to_object`].
+    ///
+    /// ## Example:
+    /// ```
+    /// use serde::Deserialize;
+    /// use serde_json::json;
+    /// use json_deref::JsonResolvableFunctions;
+    ///
+    /// #[derive(Deserialize)]
+    /// struct Config {
+    ///     host: String,
+    ///     url: String,
+    ///     port: u16,
+    /// }
+    ///
+    /// let input = json!({
+    ///     "host": "localhost",
+    ///     "url": "http://{host}/",
+    ///     "env": { "port": "8080" },
+    ///     "port": "{/env/port:int}"
+    /// });
+    ///
+    /// let config: Config = input.resolve_to().unwrap();
+    /// assert_eq!(config.host, "localhost");
+    /// assert_eq!(config.url, "http://localhost/");
+    /// assert_eq!(config.port, 8080);
+    /// ```
+    fn resolve_to<T: DeserializeOwned>(&self) -> Result<T, serde_json::Error> {
+        resolve_json_to_object(self)
+    }
 }
 
 /// Resolves JSON and returns a Value
+///
+/// Placeholders that can't be resolved, and braces that aren't placeholders like `{}` or
+/// `[a-z]{2,3}`, are kept exactly as written. Resolving the result again, or using it as the
+/// source of [`resolve_template_with_source`], therefore leaves them and the resolved values
+/// unchanged, unless a substituted value holds placeholders itself.
+///
+/// Braces written `\{` and `\}` are literal text, so `"Cost is \\{100\\}"` in JSON
+/// resolves to `"Cost is {100}"`. The escapes are consumed, see
+/// [`ResolveOptions::escape_delim`], so such output isn't meant to be resolved twice.
 pub fn resolve_json(input: &Value) -> Value {
+    resolve_json_collecting(input, &Default::default(), &mut Default::default()).0
+}
+
+/// Most passes made by [`resolve_json_recursive`]
+const MAX_RECURSIVE_PASSES: usize = 16;
+
+/// Resolves JSON repeatedly, until substituted values hold no placeholders that can be resolved.
+///
+/// [`resolve_json`] substitutes values as they are written, so `{"a": "{/b}", "b": "{/c}"}`
+/// gives `a` the text `{/c}`. Here the result is resolved again, with the values extracted
+/// anew, until it stops changing. Every pass doubles the length of the chains followed, so the
+/// 16 passes made at most follow chains of up to 65536 references. Placeholders of fields
+/// referring back to themselves are left unresolved, see [`find_reference_cycles`].
+/// A field referring into a cycle keeps changing, and is left as the last pass makes it.
+///
+/// ## Example:
+/// ```
+/// use serde_json::json;
+/// use json_deref::resolve_json_recursive;
+///
+/// let input = json!({
+///     "defaults": { "host": "{/defaults/fallback_host}", "fallback_host": "localhost" },
+///     "url": "http://{/defaults/host}/"
+/// });
+///
+/// let resolved = resolve_json_recursive(&input);
+/// assert_eq!(resolved["url"], json!("http://localhost/"));
+/// ```
+pub fn resolve_json_recursive(input: &Value) -> Value {
+    let mut resolved = resolve_json(input);
+    for _ in 1..MAX_RECURSIVE_PASSES {
+        let next = resolve_json(&resolved);
+        if next == resolved {
+            break;
+        }
+        resolved = next;
+    }
+    resolved
+}
+
+/// Resolves JSON, replacing the input with the result.
+///
+/// The result is the same as that of [`resolve_json`], but the document isn't rebuilt:
+/// strings holding placeholders are replaced where they are, and only the referenced values
+/// are copied. That saves time and memory on large documents.
+///
+/// ## Example:
+/// ```
+/// use serde_json::json;
+/// use json_deref::resolve_json_in_place;
+///
+/// let mut config = json!({ "host": "localhost", "url": "http://{host}/", "port": "{/ports/0}", "ports": [80] });
+/// resolve_json_in_place(&mut config);
+/// assert_eq!(config, json!({ "host": "localhost", "url": "http://localhost/", "port": 80, "ports": [80] }));
+/// ```
+pub fn resolve_json_in_place(value: &mut Value) {
+    let options = ResolveOptions::default();
+    let syntax = Syntax::new(&options);
     let mut path_map = HashMap::new();
-    make_deps_path_map(input, &Default::default(), &mut path_map);
+    make_deps_path_map(value, &Default::default(), &mut path_map, &syntax);
+
+    let paths: HashSet<AbsolutePath> = path_map
+        .values()
+        .flat_map(|dependencies| dependencies.values().cloned())
+        .collect();
+    let mut extracted_values = HashMap::new();
+    extract_values_by_paths(value, &paths, &Default::default(), &mut extracted_values);
+
+    // Referenced values holding placeholders are inserted with their paths expanded,
+    // as `normalize_json` does for the whole document
+    if extracted_values
+        .values()
+        .any(|extracted| holds_placeholder(extracted, &syntax))
+    {
+        for dependencies in path_map.values_mut() {
+            dependencies.retain(|_, absolute_path| extracted_values.contains_key(absolute_path));
+        }
+        for (path, extracted) in extracted_values.iter_mut() {
+            if !holds_placeholder(extracted, &syntax) {
+                continue;
+            }
+            // Elements read through their `$fields` names aren't at the path they're read from
+            if value_at(value, path).is_none() {
+                *value = resolve_json(value);
+                return;
+            }
+            *extracted = expand_absolute_paths(extracted, &path_map, path, &syntax);
+        }
+    }
 
-    let json_with_absolute_paths = expand_absolute_paths(input, &path_map, &Default::default());
+    let mut resolver = document_resolver(value, &path_map, &extracted_values, &options);
+    resolver.resolve_in_place(value, &Default::default());
+}
+
+/// Resolves JSON whose placeholders often refer to the same large values.
+///
+/// `serde_json` values can't share subtrees, so every placeholder still receives its own
+/// clone in the output. The extraction step, however, is deduplicated: each referenced
+/// source value is cloned once per unique path, no matter how many placeholders refer to it.
+/// [`resolve_json`] works the same way; this name states the guarantee for callers relying
+/// on it.
+///
+/// ## Example:
+/// ```
+/// use serde_json::json;
+/// use json_deref::resolve_json_shared;
+///
+/// let input = json!({
+///     "defaults": { "retries": 3, "timeout": 30 },
+///     "services": ["{/defaults}", "{/defaults}", "{/defaults}"]
+/// });
+///
+/// let resolved = resolve_json_shared(&input);
+/// assert_eq!(resolved["services"][2], json!({ "retries": 3, "timeout": 30 }));
+/// ```
+pub fn resolve_json_shared(input: &Value) -> Value {
+    // The source map is keyed by the expanded absolute paths, so repeated references
+    // to the same value are extracted only once
+    resolve_json(input)
+}
+
+/// Resolves JSON and also returns the normalized template, with every relative placeholder
+/// rewritten as an absolute one. Placeholders whose target doesn't exist are kept as written.
+///
+/// The normalized template resolves to the same result and can be stored alongside it,
+/// e.g. to cache both. Returns `(normalized, resolved)`.
+///
+/// ## Example:
+/// ```
+/// use serde_json::json;
+/// use json_deref::resolve_json_with_normalized;
+///
+/// let input = json!({ "user": { "name": "Bob", "greeting": "Hi {name}" } });
+/// let (normalized, resolved) = resolve_json_with_normalized(&input);
+///
+/// assert_eq!(normalized["user"]["greeting"], json!("Hi {/user/name}"));
+/// assert_eq!(resolved["user"]["greeting"], json!("Hi Bob"));
+/// ```
+pub fn resolve_json_with_normalized(input: &Value) -> (Value, Value) {
+    let options = ResolveOptions::default();
+    let normalized = normalize_json(input, &options, &mut Default::default());
+    let resolved = normalized
+        .resolver(input, &options)
+        .resolve(&normalized.json, &Default::default());
+    (normalized.json, resolved)
+}
+
+/// Resolves JSON and splits the result by its top-level keys.
+///
+/// Placeholders may still refer across keys, as the whole document is resolved first.
+/// A root that isn't an object is returned as a single entry under the empty key.
+///
+/// ## Example:
+/// ```
+/// use serde_json::json;
+/// use json_deref::resolve_top_level;
+///
+/// let input = json!({
+///     "db": { "host": "localhost" },
+///     "api": { "url": "http://{/db/host}/" }
+/// });
+///
+/// let sections = resolve_top_level(&input);
+/// assert_eq!(sections["api"], json!({ "url": "http://localhost/" }));
+/// assert_eq!(sections["db"], json!({ "host": "localhost" }));
+/// ```
+pub fn resolve_top_level(input: &Value) -> HashMap<String, Value> {
+    match resolve_json(input) {
+        Value::Object(map) => map.into_iter().collect(),
+        other => HashMap::from([(String::new(), other)]),
+    }
+}
+
+/// Resolves JSON and returns, next to the result, the original string of every field
+/// the resolution changed.
+///
+/// The originals are the strings as written in the input, relative placeholders included,
+/// so putting them back at their paths reconstructs the template. Placeholders that stay
+/// unresolved leave their field unchanged and are not listed.
+///
+/// ## Example:
+/// ```
+/// use serde_json::json;
+/// use std::collections::HashMap;
+/// use json_deref::{resolve_json_with_originals, AbsolutePath};
+///
+/// let input = json!({ "host": "localhost", "url": "http://{host}/", "port": "{/missing}" });
+/// let (resolved, originals) = resolve_json_with_originals(&input);
+///
+/// assert_eq!(resolved["url"], json!("http://localhost/"));
+/// assert_eq!(originals, HashMap::from([
+///     (AbsolutePath::new("/url"), "http://{host}/".to_string()),
+/// ]));
+/// ```
+pub fn resolve_json_with_originals(input: &Value) -> (Value, HashMap<AbsolutePath, String>) {
+    let resolved = resolve_json(input);
+    let mut originals = HashMap::new();
+    collect_replaced_strings(input, &resolved, &Default::default(), &mut originals);
+    (resolved, originals)
+}
 
+/// Resolves JSON and lists every leaf as a JSON Pointer and its value, sorted by pointer.
+///
+/// Gives a stable, line-per-value form of a resolved document, handy for snapshot tests.
+/// Empty objects and arrays are listed as leaves; pointers are compared as plain strings,
+/// so `/items/10` comes before `/items/2`.
+///
+/// ## Example:
+/// ```
+/// use serde_json::json;
+/// use json_deref::resolved_leaves_sorted;
+///
+/// let input = json!({ "name": "db", "url": "{name}:5432", "a/b": [true] });
+///
+/// assert_eq!(resolved_leaves_sorted(&input), vec![
+///     ("/a~1b/0".to_string(), json!(true)),
+///     ("/name".to_string(), json!("db")),
+///     ("/url".to_string(), json!("db:5432")),
+/// ]);
+/// ```
+pub fn resolved_leaves_sorted(input: &Value) -> Vec<(String, Value)> {
+    let mut leaves = Vec::new();
+    collect_leaves(resolve_json(input), String::new(), &mut leaves);
+    leaves.sort_by(|(a, _), (b, _)| a.cmp(b));
+    leaves
+}
+
+/// Resolves JSON, then passes the values at the given output paths through their codecs.
+///
+/// Codecs are keyed by where a value ends up rather than by the placeholder that produced it,
+/// so `/db/password` is decoded whether it was written inline or pulled from elsewhere.
+/// Paths missing from the output are ignored.
+///
+/// ## Example:
+/// ```
+/// use serde_json::{json, Value};
+/// use std::collections::HashMap;
+/// use json_deref::{resolve_json_with_codecs, AbsolutePath, Codec};
+///
+/// let input = json!({ "vault": { "token": "abc" }, "token": "{/vault/token}" });
+/// let upper = Codec::new(|value| match value {
+///     Value::String(text) => Value::String(text.to_uppercase()),
+///     other => other,
+/// });
+///
+/// let codecs = HashMap::from([(AbsolutePath::new("/token"), upper)]);
+/// let resolved = resolve_json_with_codecs(&input, &codecs);
+///
+/// assert_eq!(resolved, json!({ "vault": { "token": "abc" }, "token": "ABC" }));
+/// ```
+pub fn resolve_json_with_codecs(input: &Value, codecs: &HashMap<AbsolutePath, Codec>) -> Value {
+    let mut resolved = resolve_json(input);
+    for (path, codec) in codecs {
+        if let Some(value) = value_at_mut(&mut resolved, path) {
+            *value = codec.apply(value.take());
+        }
+    }
+    resolved
+}
+
+/// Resolves JSON, generating the values of `{random:...}` placeholders from the seed.
+///
+/// `{random:uuid}` becomes a version 4 UUID and `{random:int:1-100}` an integer within
+/// the bounds, both included. The same seed always produces the same document,
+/// which makes this suitable for test fixtures. Other placeholders resolve as in [`resolve_json`].
+///
+/// ## Example:
+/// ```
+/// use serde_json::json;
+/// use json_deref::resolve_json_with_rng;
+///
+/// let input = json!({ "id": "{random:uuid}", "age": "{random:int:18-99}" });
+///
+/// let resolved = resolve_json_with_rng(&input, 42);
+/// assert_eq!(resolved, resolve_json_with_rng(&input, 42));
+/// assert!((18..=99).contains(&resolved["age"].as_i64().unwrap()));
+/// ```
+#[cfg(feature = "random")]
+pub fn resolve_json_with_rng(input: &Value, seed: u64) -> Value {
+    let options = ResolveOptions::default();
+    let normalized = normalize_json(input, &options, &mut Default::default());
+    normalized
+        .resolver(input, &options)
+        .with_rng(seed)
+        .resolve(input, &Default::default())
+}
+
+/// Resolves JSON, rendering `{now:...}` placeholders from the given time.
+///
+/// The format after `now:` is a strftime format as understood by chrono, e.g. `{now:%Y-%m-%d}`.
+/// Every placeholder uses the same time, so the document is consistent and reproducible;
+/// pass `Utc::now()` for the current time. Other placeholders resolve as in [`resolve_json`].
+///
+/// ## Example:
+/// ```
+/// use chrono::{TimeZone, Utc};
+/// use serde_json::json;
+/// use json_deref::resolve_json_with_now;
+///
+/// let input = json!({ "date": "{now:%Y-%m-%d}", "banner": "Built at {now:%H:%M} UTC" });
+/// let now = Utc.with_ymd_and_hms(2024, 3, 5, 14, 7, 0).unwrap();
+///
+/// assert_eq!(
+///     resolve_json_with_now(&input, now),
+///     json!({ "date": "2024-03-05", "banner": "Built at 14:07 UTC" })
+/// );
+/// ```
+#[cfg(feature = "chrono")]
+pub fn resolve_json_with_now<Tz: chrono::TimeZone>(
+    input: &Value,
+    now: chrono::DateTime<Tz>,
+) -> Value {
+    let options = ResolveOptions::default();
+    let normalized = normalize_json(input, &options, &mut Default::default());
+    normalized
+        .resolver(input, &options)
+        .with_now(now.fixed_offset())
+        .resolve(input, &Default::default())
+}
+
+/// Resolves JSON, failing with [`ResolveError::OutputTooLarge`] if the serialized output
+/// would exceed `max_bytes`.
+///
+/// Only the size of the output counts, so an input over the limit is fine as long as its
+/// placeholders shrink it enough. The size is tracked while resolving and resolution stops
+/// as soon as the remaining placeholders couldn't bring it back under the limit, which
+/// protects against small untrusted templates referring to large values many times.
+/// The error is reported at the placeholder that took the output over the limit.
+/// Sizes are those of compact JSON text, as produced by `serde_json::to_string`.
+///
+/// ## Example:
+/// ```
+/// use serde_json::json;
+/// use json_deref::{resolve_json_size_capped, AbsolutePath, ResolveError};
+///
+/// let input = json!({ "items": [1, 2, 3, 4, 5, 6, 7, 8, 9, 10], "copy": "{/items}" });
+/// assert!(resolve_json_size_capped(&input, 100).is_ok());
+///
+/// assert_eq!(resolve_json_size_capped(&input, 60), Err(ResolveError::OutputTooLarge {
+///     at: AbsolutePath::new("/copy"),
+///     limit: 60,
+/// }));
+/// ```
+pub fn resolve_json_size_capped(input: &Value, max_bytes: usize) -> Result<Value, ResolveError> {
+    let options = ResolveOptions::default();
+    let normalized = normalize_json(input, &options, &mut Default::default());
+    let mut resolver = normalized
+        .resolver(input, &options)
+        .with_size_limit(max_bytes, input);
+    let resolved = resolver.resolve(input, &Default::default());
+    let overflow = resolver.size_overflow(&resolved);
+    let aborted = resolver
+        .into_problems()
+        .errors
+        .into_iter()
+        .find(|error| matches!(error, ResolveError::OutputTooLarge { .. }));
+    match aborted.or(overflow) {
+        Some(error) => Err(error),
+        None => Ok(resolved),
+    }
+}
+
+/// Resolves JSON and measures how long each phase of the pipeline took.
+///
+/// Useful to find out which phase dominates on large documents, see [`PhaseTimings`].
+///
+/// ## Example:
+/// ```
+/// use serde_json::json;
+/// use json_deref::resolve_json_timed;
+///
+/// let (resolved, timings) = resolve_json_timed(&json!({ "a": "x", "b": "{/a}" }));
+/// assert_eq!(resolved, json!({ "a": "x", "b": "x" }));
+///
+/// let total = timings.map_build + timings.expand + timings.extract + timings.resolve;
+/// assert!(total >= timings.resolve);
+/// ```
+pub fn resolve_json_timed(input: &Value) -> (Value, PhaseTimings) {
+    let mut timings = PhaseTimings::default();
+    let (resolved, _) = resolve_json_collecting(input, &Default::default(), &mut timings);
+    (resolved, timings)
+}
+
+/// Resolves JSON with the given options, returning everything found on the way.
+///
+/// Besides the resolved value, the [`ResolveOutcome`] lists the placeholders left unresolved,
+/// the warnings along with the typo suggestions of [`suggest_similar_paths`], and
+/// [`ResolveStats`]. Problems are reported there even with [`ResolveOptions::strict`] set.
+///
+/// ## Example:
+/// ```
+/// use serde_json::json;
+/// use json_deref::{resolve_json_full, AbsolutePath, Unresolved};
+///
+/// let input = json!({ "a": "x", "b": "{/a}", "c": "{/missing}" });
+/// let outcome = resolve_json_full(&input, &Default::default());
+///
+/// assert_eq!(outcome.value, json!({ "a": "x", "b": "x", "c": "{/missing}" }));
+/// assert_eq!(outcome.unresolved, vec![Unresolved {
+///     at: AbsolutePath::new("/c"),
+///     reference: "/missing".to_string(),
+///     cycle: None,
+/// }]);
+/// assert_eq!(outcome.stats.placeholders, 2);
+/// ```
+pub fn resolve_json_full(input: &Value, options: &ResolveOptions) -> ResolveOutcome {
+    let mut timings = PhaseTimings::default();
+    let (value, problems) = resolve_json_collecting(input, options, &mut timings);
+    let (fields, placeholders) = count_placeholders(input, &Syntax::new(options));
+
+    let unresolved = problems.unresolved();
+    let mut warnings = problems.warnings;
+    warnings.extend(suggest_similar_paths(input));
+    ResolveOutcome {
+        value: match options.stringify_scalars {
+            true => stringify_scalars(value),
+            false => value,
+        },
+        unresolved,
+        warnings,
+        stats: ResolveStats {
+            fields,
+            placeholders,
+            timings,
+        },
+    }
+}
+
+/// Resolves JSON like [`resolve_json`], failing with every placeholder left unresolved.
+///
+/// Each placeholder of a string is listed on its own, so a partially resolved string like
+/// `"{/host}:{/port}"` gives one entry per missing target. Placeholders leading back to their
+/// own field are listed too, along with the chain of the cycle as in [`ResolveError::Cycle`].
+/// Use it to refuse configs that aren't fully resolved, rather than finding the placeholders
+/// in the output later.
+///
+/// ## Example:
+/// ```
+/// use serde_json::json;
+/// use json_deref::{resolve_json_checked, AbsolutePath, UnresolvedRef};
+///
+/// let input = json!({ "host": "localhost", "url": "{/host}:{/port}" });
+/// assert_eq!(resolve_json_checked(&input), Err(vec![UnresolvedRef {
+///     at: AbsolutePath::new("/url"),
+///     reference: "/port".to_string(),
+///     cycle: None,
+/// }]));
+///
+/// let input = json!({ "a": "{/a}" });
+/// assert_eq!(resolve_json_checked(&input), Err(vec![UnresolvedRef {
+///     at: AbsolutePath::new("/a"),
+///     reference: "/a".to_string(),
+///     cycle: Some(vec![AbsolutePath::new("/a")]),
+/// }]));
+///
+/// let input = json!({ "host": "localhost", "url": "http://{/host}" });
+/// assert_eq!(resolve_json_checked(&input), Ok(json!({ "host": "localhost", "url": "http://localhost" })));
+/// ```
+pub fn resolve_json_checked(input: &Value) -> Result<Value, Vec<UnresolvedRef>> {
+    let (resolved, problems) =
+        resolve_json_collecting(input, &Default::default(), &mut Default::default());
+    match problems.unresolved() {
+        unresolved if unresolved.is_empty() => Ok(resolved),
+        unresolved => Err(unresolved),
+    }
+}
+
+/// Resolves JSON with the given options and returns a Value.
+///
+/// Besides the plain `{/path}` form, a standalone placeholder may request a type
+/// with `{/path:int}`, `{/path:float}`, `{/path:bool}` or `{/path:number}`. The resolved
+/// value is converted to that type when possible, e.g. `"8080"` becomes `8080`.
+/// `:number` keeps the written form: `"3"` becomes an integer, `"3.0"` and `"3e2"` floats.
+/// Placeholders embedded in text insert strings as they are and anything else as JSON,
+/// so `"You have {/count} items"` gives `"You have 5 items"`.
+///
+/// Any placeholder may also pass the resolved value through filters, listed below, like
+/// `{/bio|truncate:20}`. Filters only apply to resolved values: a missing target is handled
+/// like any other, before filtering.
+///
+/// Text after `|` that isn't a list of known filters is a default used when the target is
+/// missing: `{/timeout|30}`, `{/config|{"retries":3}}` or `{/name|anonymous}`. Defaults that
+/// parse as JSON are inserted as such, anything else as a string. Placeholders embedded in
+/// text insert the default as written, and `{/suffix|}` inserts nothing.
+///
+/// A trailing `start:end` segment slices an array, e.g. `{/items/1:3}` resolves to the
+/// elements 1 and 2. Bounds are clamped and inverted ranges give an empty array.
+///
+/// A `!` suffix, like `{/name!}` or `{/port:int!}`, requires the target to exist and be
+/// non-empty: null and empty strings, arrays or objects are reported as
+/// [`ResolveError::RequiredEmpty`]. Outside of strict mode it has no effect.
+///
+/// [`ResolveOptions::open_delim`] and [`ResolveOptions::close_delim`] change the delimiters,
+/// e.g. to `${`…`}`, and [`ResolveOptions::escape_delim`] allows writing them as plain text.
+///
+/// `{$key}` resolves to the key holding the placeholder, or to its index in an array,
+/// so `{ "ports": { "http": "{$key}" } }` gives `"http"`.
+///
+/// `{spread_kv:/params}` renders the entries of an object as `key=value` pairs joined with
+/// [`ResolveOptions::spread_kv_separator`], e.g. `page=2&q=rust` for query strings. Strings are
+/// inserted as-is, other values as JSON, nested objects included. Other targets stay unresolved.
+///
+/// `"base": "{spread:/defaults}"` merges the entries of the `defaults` object into the object
+/// holding the placeholder, dropping the `base` key. Keys written in the object override spread
+/// ones, and later spreads, in document order, override earlier ones. Targets that aren't objects
+/// are reported as [`ResolveError::KindMismatch`] and stay unresolved.
+///
+/// `{concat:/first," ",/last}` joins the referenced values and quoted literals into one
+/// string. Missing parts are left empty, and reported in strict mode.
+/// `{concat_arrays:/defaults,/extra}` likewise joins arrays into one array, leaving out
+/// missing parts and parts that aren't arrays, which are reported as [`ResolveError::KindMismatch`].
+///
+/// `{#each /users}{.name}, {/each}` repeats the text up to `{/each}` for every element of the
+/// `users` array, with `{.name}` reading the `name` of the element and `{.}` the element itself.
+/// Loops can't be nested. A target that isn't an array is reported as
+/// [`ResolveError::KindMismatch`], and the loop stays unresolved.
+///
+/// With [`ResolveOptions::strict`] set, the first placeholder that can't be resolved
+/// or converted is returned as an error. Otherwise such placeholders stay unchanged.
+/// A root `"$optional": ["/a/b", "/c"]` list names paths that may be missing: placeholders
+/// referring to them stay unchanged without an error. The list itself is kept in the output.
+///
+/// A key ending in `?` is conditional: `"port?": "{/port}"` gives `"port"` when the value
+/// resolves, and is dropped along with its errors when it stays unresolved or resolves to
+/// null or an empty string, array or object.
+///
+/// Arrays holding records may name their positions with a `"$fields": ["lat", "lng"]` key
+/// in the enclosing object, so `{/place/point/lat}` is the same as `{/place/point/0}`.
+///
+/// ## Filters:
+/// - `{/bio|truncate:20}` keeps the first 20 characters of a string.
+/// - `{/status|map:active=on;inactive=off}` turns codes into labels.
+/// - `{/amount|group}` writes a number with separated thousands, `1,234,567`, and
+///   `{/amount|group:.}` with another separator.
+/// - `{bytes:/key}` or `{/key|bytes}` decodes a hex string into an array of byte values.
+/// - With the `base64` feature, `{/blob|base64:decode}` and `{/text|base64:encode}` convert
+///   base64 strings.
+/// - With the `hash` feature, `{hash:/content}` or `{/content|hash}` gives the first 8 hex
+///   digits of the SHA-256 of the value, a stable key for cache busting.
+///
+/// ## Example:
+/// ```
+/// use serde_json::json;
+/// use json_deref::{resolve_json_with_options, ResolveOptions};
+///
+/// let input = json!({
+///     "env": { "port": "8080", "debug": "true" },
+///     "port": "{/env/port:int}",
+///     "debug": "{/env/debug:bool}"
+/// });
+///
+/// let resolved = resolve_json_with_options(&input, &ResolveOptions::default()).unwrap();
+/// assert_eq!(resolved["port"], json!(8080));
+/// assert_eq!(resolved["debug"], json!(true));
+///
+/// let invalid = json!({ "name": "abc", "port": "{/name:int}" });
+/// let strict = ResolveOptions { strict: true, ..Default::default() };
+/// assert!(resolve_json_with_options(&invalid, &strict).is_err());
+/// ```
+pub fn resolve_json_with_options(
+    input: &Value,
+    options: &ResolveOptions,
+) -> Result<Value, ResolveError> {
+    let (resolved, problems) = resolve_json_collecting(input, options, &mut Default::default());
+    problems.into_result(resolved, options)
+}
+
+/// Resolves JSON, copying the objects and arrays without placeholders whole.
+///
+/// [`resolve_json`] rebuilds every object and array of the document. Here the branches holding
+/// no placeholder at any depth are found first and cloned in one go, which is faster for
+/// large documents that are mostly static. The result is the same as that of [`resolve_json`].
+///
+/// ## Example:
+/// ```
+/// use serde_json::json;
+/// use json_deref::{resolve_json, resolve_json_sparse};
+///
+/// let input = json!({
+///     "static": { "levels": [1, 2, 3], "names": { "a": "A", "b": "B" } },
+///     "dynamic": { "first": "{/static/levels/0}" }
+/// });
+///
+/// let resolved = resolve_json_sparse(&input);
+/// assert_eq!(resolved["dynamic"], json!({ "first": 1 }));
+/// assert_eq!(resolved, resolve_json(&input));
+/// ```
+pub fn resolve_json_sparse(input: &Value) -> Value {
+    let options = ResolveOptions::default();
+    let normalized = normalize_json(input, &options, &mut Default::default());
+    normalized
+        .resolver(input, &options)
+        .with_static_branches(input)
+        .resolve(input, &Default::default())
+}
+
+fn resolve_json_collecting(
+    input: &Value,
+    options: &ResolveOptions,
+    timings: &mut PhaseTimings,
+) -> (Value, Problems) {
+    let normalized = normalize_json(input, options, timings);
+
+    // Misspelled paths aren't extracted, so the alternatives are looked up in the whole document
+    let mut document_map = HashMap::new();
+    if options.fuzzy {
+        collect_all_absolute_paths(input, &AbsolutePath::new("/"), &mut document_map);
+    }
+
+    let started = Instant::now();
+    let mut resolver = normalized
+        .resolver(input, options)
+        .with_fuzzy_source(&document_map);
+    let resolved = resolver.resolve(input, &Default::default());
+    timings.resolve = started.elapsed();
+
+    (resolved, resolver.into_problems())
+}
+
+/// Resolver of a document against its own values.
+///
+/// Placeholders leading back to the field holding them stay unresolved and are reported as
+/// cycles, see `find_cycles`, and reported placeholders have their paths made absolute.
+fn document_resolver<'a>(
+    input: &Value,
+    path_map: &HashMap<AbsolutePath, HashMap<RelativePath, AbsolutePath>>,
+    extracted_values: &'a HashMap<AbsolutePath, Value>,
+    options: &'a ResolveOptions,
+) -> ValueResolver<'a> {
+    // Fields can only lead back to themselves through referenced values holding placeholders
+    let syntax = Syntax::new(options);
+    let mut cycles = Vec::new();
+    if extracted_values
+        .values()
+        .any(|value| holds_placeholder(value, &syntax))
+    {
+        cycles = find_cycles(path_map);
+    }
+
+    ValueResolver::new(extracted_values, options)
+        .with_optional_paths(optional_paths(input))
+        .with_absolute_reports()
+        .with_cycles(&cycles)
+}
+
+/// A document prepared by `normalize_json`
+struct Normalized {
+    /// The document with the placeholders whose target exists rewritten with absolute paths
+    json: Value,
+    /// Absolute targets of the placeholders of each field, keyed by the paths as written
+    path_map: HashMap<AbsolutePath, HashMap<RelativePath, AbsolutePath>>,
+    /// The values the placeholders refer to, by absolute path
+    extracted_values: HashMap<AbsolutePath, Value>,
+}
+
+impl Normalized {
+    /// Resolver of the document, see `document_resolver`
+    fn resolver<'a>(&'a self, input: &Value, options: &'a ResolveOptions) -> ValueResolver<'a> {
+        document_resolver(input, &self.path_map, &self.extracted_values, options)
+    }
+}
+
+/// Rewrites all placeholders with absolute paths and extracts the values they refer to
+fn normalize_json(
+    input: &Value,
+    options: &ResolveOptions,
+    timings: &mut PhaseTimings,
+) -> Normalized {
+    let started = Instant::now();
+    let mut path_map = HashMap::new();
+    let syntax = Syntax::new(options);
+    make_deps_path_map(input, &Default::default(), &mut path_map, &syntax);
+    timings.map_build = started.elapsed();
+
+    let started = Instant::now();
     let path_maps: Vec<HashMap<RelativePath, AbsolutePath>> = path_map.values().cloned().collect();
     let mut paths = HashSet::new();
     for map in path_maps.iter() {
@@ -162,14 +952,532 @@ pub fn resolve_json(input: &Value) -> Value {
     }
 
     let mut extracted_values = HashMap::new();
-    extract_values_by_paths(
-        &json_with_absolute_paths,
-        &paths,
+    extract_values_by_paths(input, &paths, &Default::default(), &mut extracted_values);
+    timings.extract = started.elapsed();
+
+    // Placeholders whose target doesn't exist are kept as written, they may be literal text
+    let started = Instant::now();
+    for dependencies in path_map.values_mut() {
+        dependencies.retain(|_, absolute_path| extracted_values.contains_key(absolute_path));
+    }
+    let json_with_absolute_paths =
+        expand_absolute_paths(input, &path_map, &Default::default(), &syntax);
+    timings.expand = started.elapsed();
+
+    // Values holding placeholders are inserted with their paths expanded too
+    let started = Instant::now();
+    if extracted_values
+        .values()
+        .any(|value| holds_placeholder(value, &syntax))
+    {
+        extracted_values.clear();
+        extract_values_by_paths(
+            &json_with_absolute_paths,
+            &paths,
+            &Default::default(),
+            &mut extracted_values,
+        );
+    }
+    timings.extract += started.elapsed();
+
+    Normalized {
+        json: json_with_absolute_paths,
+        path_map,
+        extracted_values,
+    }
+}
+
+/// Resolves JSON and passes every resolved leaf through `f`.
+///
+/// Leaves are all values except objects and arrays, including the ones that were
+/// never placeholders. `f` receives the path of the leaf in the output document,
+/// which makes it suitable for redacting, trimming or otherwise transforming values uniformly.
+///
+/// ## Example:
+/// ```
+/// use serde_json::{json, Value};
+/// use json_deref::resolve_json_map_values;
+///
+/// let input = json!({
+///     "db": { "password": "secret-123", "host": "localhost" },
+///     "dsn": "{/db/host}",
+///     "copy": "{/db/password}"
+/// });
+///
+/// let redacted = resolve_json_map_values(&input, |_path, value| match value {
+///     Value::String(text) if text.starts_with("secret-") => json!("***"),
+///     other => other,
+/// });
+///
+/// assert_eq!(redacted, json!({
+///     "db": { "password": "***", "host": "localhost" },
+///     "dsn": "localhost",
+///     "copy": "***"
+/// }));
+/// ```
+pub fn resolve_json_map_values<F>(input: &Value, f: F) -> Value
+where
+    F: Fn(&AbsolutePath, Value) -> Value,
+{
+    map_leaves(resolve_json(input), &Default::default(), &f)
+}
+
+/// Resolves JSON and checks that the listed fields of the result have the expected types.
+///
+/// This catches placeholders that silently change the type of a field, e.g. `{/port}`
+/// pointing to `"8080"` where a number is expected. Paths refer to the resolved document.
+/// Mismatches are reported as [`ResolveError::KindMismatch`], sorted by path.
+///
+/// ## Example:
+/// ```
+/// use serde_json::json;
+/// use std::collections::HashMap;
+/// use json_deref::{check_reference_types, AbsolutePath, ResolveError, ValueKind};
+///
+/// let input = json!({ "env": { "port": "8080" }, "port": "{/env/port}" });
+/// let expected = HashMap::from([(AbsolutePath::new("/port"), ValueKind::Number)]);
+///
+/// assert_eq!(check_reference_types(&input, &expected), Err(vec![ResolveError::KindMismatch {
+///     at: AbsolutePath::new("/port"),
+///     expected: ValueKind::Number,
+///     found: Some(ValueKind::String),
+/// }]));
+/// ```
+pub fn check_reference_types(
+    input: &Value,
+    expected: &HashMap<AbsolutePath, ValueKind>,
+) -> Result<(), Vec<ResolveError>> {
+    let resolved = resolve_json(input);
+
+    let mut mismatches: Vec<(&AbsolutePath, ValueKind, Option<ValueKind>)> = expected
+        .iter()
+        .map(|(path, &expected)| (path, expected, value_at(&resolved, path).map(ValueKind::of)))
+        .filter(|(_, expected, found)| *found != Some(*expected))
+        .collect();
+
+    if mismatches.is_empty() {
+        return Ok(());
+    }
+
+    mismatches.sort_by_key(|(path, _, _)| path.as_str());
+    Err(mismatches
+        .into_iter()
+        .map(|(at, expected, found)| ResolveError::KindMismatch {
+            at: at.clone(),
+            expected,
+            found,
+        })
+        .collect())
+}
+
+/// Collects every problem found in the document without failing on the first one.
+///
+/// Relative placeholders climbing above the root, see [`check_relatives`], come first,
+/// followed by the problems met while resolving in document order.
+///
+/// ## Example:
+/// ```
+/// use serde_json::json;
+/// use json_deref::{validate, AbsolutePath, ResolveError};
+///
+/// let errors = validate(&json!({ "a": "{/missing}", "b": "ok" }));
+/// assert_eq!(errors, vec![ResolveError::Unresolved {
+///     at: AbsolutePath::new("/a"),
+///     reference: "/missing".to_string(),
+/// }]);
+/// ```
+pub fn validate(input: &Value) -> Vec<ResolveError> {
+    let mut errors = check_relatives(input).err().unwrap_or_default();
+    let (_, problems) =
+        resolve_json_collecting(input, &Default::default(), &mut Default::default());
+    errors.extend(problems.errors);
+    errors
+}
+
+/// Checks that every relative placeholder can be made absolute.
+///
+/// Relative placeholders climbing above the document root, like `{../../x}` in a field
+/// one level deep, are silently clamped at the root by [`resolve_json`]. This check reports
+/// them as [`ResolveError::EscapesRoot`] instead. Whether the targets exist is not checked.
+///
+/// ## Example:
+/// ```
+/// use serde_json::json;
+/// use json_deref::{check_relatives, AbsolutePath, ResolveError};
+///
+/// assert!(check_relatives(&json!({ "a": { "b": "{../c}" }, "c": 1 })).is_ok());
+///
+/// let errors = check_relatives(&json!({ "a": { "b": "{../../../c}" } })).unwrap_err();
+/// assert_eq!(errors, vec![ResolveError::EscapesRoot {
+///     at: AbsolutePath::new("/a/b"),
+///     reference: "../../../c".to_string(),
+/// }]);
+/// ```
+pub fn check_relatives(input: &Value) -> Result<(), Vec<ResolveError>> {
+    let mut path_map = HashMap::new();
+    make_deps_path_map(
+        input,
+        &Default::default(),
+        &mut path_map,
+        &Syntax::default(),
+    );
+
+    let mut escaping: Vec<(&AbsolutePath, &RelativePath)> = path_map
+        .iter()
+        .flat_map(|(at, dependencies)| dependencies.keys().map(move |relative| (at, relative)))
+        .filter(|(at, relative)| {
+            !relative.as_str().starts_with('/') && at.try_resolve_with(relative).is_none()
+        })
+        .collect();
+
+    if escaping.is_empty() {
+        return Ok(());
+    }
+
+    escaping.sort_by_key(|(at, relative)| (at.as_str(), relative.as_str()));
+    Err(escaping
+        .into_iter()
+        .map(|(at, relative)| ResolveError::EscapesRoot {
+            at: at.clone(),
+            reference: relative.as_str().to_string(),
+        })
+        .collect())
+}
+
+/// Checks that a resolved document holds no placeholders, returning the paths of the strings
+/// that still do, in document order.
+///
+/// Meant for tests and CI, e.g. after [`resolve_json_in_place`], to make sure nothing was left
+/// unresolved. Empty `{}` braces are plain text and don't count.
+///
+/// ## Example:
+/// ```
+/// use serde_json::json;
+/// use json_deref::{assert_fully_resolved, resolve_json, AbsolutePath};
+///
+/// let resolved = resolve_json(&json!({ "a": 1, "b": "{/a}", "c": "id {/missing}" }));
+///
+/// assert_eq!(assert_fully_resolved(&resolved), Err(vec![AbsolutePath::new("/c")]));
+/// ```
+pub fn assert_fully_resolved(value: &Value) -> Result<(), Vec<AbsolutePath>> {
+    let mut found = Vec::new();
+    collect_placeholder_paths(
+        value,
+        &AbsolutePath::new("/"),
+        &Syntax::default(),
+        &mut found,
+    );
+    match found.is_empty() {
+        true => Ok(()),
+        false => Err(found),
+    }
+}
+
+/// Resolves JSON, then fills in the fields missing from the output with the `default` values
+/// declared in a JSON Schema.
+///
+/// Only `properties` are followed, into the objects present in the output. Fields the output
+/// has are kept, whatever their value, and defaults are inserted as they are, unresolved.
+///
+/// ## Example:
+/// ```
+/// use serde_json::json;
+/// use json_deref::resolve_with_schema_defaults;
+///
+/// let schema = json!({
+///     "properties": {
+///         "port": { "type": "integer", "default": 8080 },
+///         "host": { "type": "string", "default": "localhost" }
+///     }
+/// });
+/// let input = json!({ "name": "db.local", "host": "{/name}" });
+///
+/// assert_eq!(
+///     resolve_with_schema_defaults(&input, &schema),
+///     json!({ "name": "db.local", "host": "db.local", "port": 8080 })
+/// );
+/// ```
+#[cfg(feature = "schema")]
+pub fn resolve_with_schema_defaults(input: &Value, schema: &Value) -> Value {
+    let mut resolved = resolve_json(input);
+    parsing::fill_schema_defaults(&mut resolved, schema);
+    resolved
+}
+
+/// Suggests fixes for relative placeholders that point to nothing due to a likely typo.
+///
+/// A relative placeholder whose absolute path doesn't exist in the document, while a path
+/// one or two characters away does, is reported as [`Warning::SimilarPath`], sorted by the
+/// location of the placeholder. Placeholders climbing above the root are left to [`check_relatives`].
+///
+/// ## Example:
+/// ```
+/// use serde_json::json;
+/// use json_deref::{suggest_similar_paths, AbsolutePath, Warning};
+///
+/// let input = json!({ "db": { "host": "localhost", "url": "http://{hots}/" } });
+///
+/// assert_eq!(suggest_similar_paths(&input), vec![Warning::SimilarPath {
+///     at: AbsolutePath::new("/db/url"),
+///     reference: "hots".to_string(),
+///     suggestion: AbsolutePath::new("/db/host"),
+/// }]);
+/// ```
+pub fn suggest_similar_paths(input: &Value) -> Vec<Warning> {
+    let mut path_map = HashMap::new();
+    make_deps_path_map(
+        input,
+        &Default::default(),
+        &mut path_map,
+        &Syntax::default(),
+    );
+
+    let mut existing = HashMap::new();
+    collect_all_absolute_paths(input, &AbsolutePath::new("/"), &mut existing);
+
+    let mut suggestions: Vec<(&AbsolutePath, &RelativePath, &AbsolutePath)> = path_map
+        .iter()
+        .flat_map(|(at, dependencies)| dependencies.keys().map(move |relative| (at, relative)))
+        .filter(|(_, relative)| !relative.as_str().starts_with('/'))
+        .filter_map(|(at, relative)| {
+            let target = at.try_resolve_with(relative)?;
+            if existing.contains_key(&target) {
+                return None;
+            }
+            Some((
+                at,
+                relative,
+                closest_path(&target, existing.keys(), MAX_DISTANCE)?,
+            ))
+        })
+        .collect();
+
+    suggestions.sort_by_key(|(at, relative, _)| (at.as_str(), relative.as_str()));
+    suggestions
+        .into_iter()
+        .map(|(at, relative, suggestion)| Warning::SimilarPath {
+            at: at.clone(),
+            reference: relative.as_str().to_string(),
+            suggestion: suggestion.clone(),
+        })
+        .collect()
+}
+
+/// Lists the paths every field holding placeholders refers to, without resolving anything.
+///
+/// Relative paths are made absolute, and paths that don't exist in the document are listed
+/// too. The targets of each field are sorted and listed once. Placeholders of other sources,
+/// like `{@db:/host}`, and empty braces are left out.
+///
+/// ## Example:
+/// ```
+/// use serde_json::json;
+/// use json_deref::{collect_dependencies, AbsolutePath};
+///
+/// let input = json!({ "db": { "host": "localhost", "url": "pg://{host}/{/name}" }, "copy": "{/db/url}" });
+/// let dependencies = collect_dependencies(&input);
+///
+/// assert_eq!(dependencies[&AbsolutePath::new("/db/url")], vec![
+///     AbsolutePath::new("/db/host"),
+///     AbsolutePath::new("/name"),
+/// ]);
+/// assert_eq!(dependencies[&AbsolutePath::new("/copy")], vec![AbsolutePath::new("/db/url")]);
+/// assert_eq!(dependencies.len(), 2);
+/// ```
+pub fn collect_dependencies(input: &Value) -> HashMap<AbsolutePath, Vec<AbsolutePath>> {
+    let mut path_map = HashMap::new();
+    make_deps_path_map(
+        input,
+        &Default::default(),
+        &mut path_map,
+        &Syntax::default(),
+    );
+
+    path_map
+        .into_iter()
+        .map(|(at, dependencies)| {
+            let mut targets: Vec<AbsolutePath> = dependencies.into_values().collect();
+            targets.sort_unstable_by(|a, b| a.as_str().cmp(b.as_str()));
+            targets.dedup();
+            (at, targets)
+        })
+        .collect()
+}
+
+/// Describes which fields refer to which in Graphviz DOT format, e.g. to render with `dot -Tsvg`.
+///
+/// Every field holding placeholders gets an edge to each path it refers to, relative paths
+/// being made absolute. Edges are sorted, and cycles show up as loops in the rendered graph.
+///
+/// ## Example:
+/// ```
+/// use serde_json::json;
+/// use json_deref::dependency_dot;
+///
+/// let input = json!({ "host": "localhost", "url": "http://{host}:{/port}/", "port": "{/url}" });
+///
+/// assert_eq!(dependency_dot(&input), r#"digraph dependencies {
+///     "/port" -> "/url";
+///     "/url" -> "/host";
+///     "/url" -> "/port";
+/// }
+/// "#);
+/// ```
+pub fn dependency_dot(input: &Value) -> String {
+    let mut path_map = HashMap::new();
+    make_deps_path_map(
+        input,
+        &Default::default(),
+        &mut path_map,
+        &Syntax::default(),
+    );
+
+    let mut edges: Vec<(&str, &str)> = path_map
+        .iter()
+        .flat_map(|(at, dependencies)| {
+            dependencies
+                .values()
+                .map(move |target| (at.as_str(), target.as_str()))
+        })
+        .collect();
+    edges.sort_unstable();
+    edges.dedup();
+
+    let quote = |path: &str| format!("\"{}\"", path.replace('\\', "\\\\").replace('"', "\\\""));
+    let mut dot = String::from("digraph dependencies {\n");
+    for (at, target) in edges {
+        dot.push_str(&format!("    {} -> {};\n", quote(at), quote(target)));
+    }
+    dot.push_str("}\n");
+    dot
+}
+
+/// Lists the fields whose placeholders refer back to them, directly or through other fields.
+///
+/// Each cycle is a chain of fields, every one referring to the next and the last one to the
+/// first. A field referring to itself, or to an object holding it, is a chain of one. Chains
+/// start with their smallest path and are sorted. [`resolve_json`] leaves the placeholders of
+/// a cycle unresolved, and [`resolve_json_with_options`] reports them as [`ResolveError::Cycle`].
+///
+/// ## Example:
+/// ```
+/// use serde_json::json;
+/// use json_deref::{find_reference_cycles, AbsolutePath};
+///
+/// let input = json!({ "a": "{/b}", "b": "x{/c}", "c": "{/a}", "d": "{d}", "e": "{/a}" });
+///
+/// assert_eq!(find_reference_cycles(&input), vec![
+///     vec![AbsolutePath::new("/a"), AbsolutePath::new("/b"), AbsolutePath::new("/c")],
+///     vec![AbsolutePath::new("/d")],
+/// ]);
+/// ```
+pub fn find_reference_cycles(input: &Value) -> Vec<Vec<AbsolutePath>> {
+    let mut path_map = HashMap::new();
+    make_deps_path_map(
+        input,
         &Default::default(),
-        &mut extracted_values,
+        &mut path_map,
+        &Syntax::default(),
     );
+    find_cycles(&path_map)
+}
 
-    resolve_values(&json_with_absolute_paths, &extracted_values)
+/// Returns the structure of the document with the placeholders removed.
+///
+/// Every string containing a placeholder becomes an empty string, everything else
+/// is kept as it is. Useful for documenting the shape of a config without its values.
+///
+/// ## Example:
+/// ```
+/// use serde_json::json;
+/// use json_deref::template_skeleton;
+///
+/// let input = json!({
+///     "name": "service",
+///     "port": 8080,
+///     "url": "http://{host}:{port}/",
+///     "db": { "host": "{/hosts/db}", "pool": [1, "{/pool/max}"] }
+/// });
+///
+/// assert_eq!(template_skeleton(&input), json!({
+///     "name": "service",
+///     "port": 8080,
+///     "url": "",
+///     "db": { "host": "", "pool": [1, ""] }
+/// }));
+/// ```
+pub fn template_skeleton(input: &Value) -> Value {
+    strip_placeholders(input)
+}
+
+/// Returns the deepest nesting of objects and arrays in the document.
+///
+/// A flat object or array has depth 1, and a scalar 0. Resolving a placeholder may add the depth
+/// of its target to the depth of the field holding it.
+///
+/// ## Example:
+/// ```
+/// use serde_json::json;
+/// use json_deref::measure_depth;
+///
+/// assert_eq!(measure_depth(&json!({ "a": 1, "b": "{/a}" })), 1);
+/// assert_eq!(measure_depth(&json!({ "a": { "b": [1, [2]] } })), 4);
+/// ```
+pub fn measure_depth(input: &Value) -> usize {
+    nesting_depth(input)
+}
+
+/// Turns a resolved document back into a template, the inverse of resolution.
+///
+/// Every leaf equal to a leaf of the source is replaced with a placeholder referring to it,
+/// which helps to migrate hardcoded configs to templated ones. If the value is found at
+/// several source paths, the shortest path (with the fewest segments) wins, and among equally
+/// long ones the first in alphabetical order. Objects and arrays are never matched as a whole.
+///
+/// ## Example:
+/// ```
+/// use serde_json::json;
+/// use json_deref::templatize;
+///
+/// let source = json!({ "db": { "host": "db.local", "port": 5432 }, "fallback": "db.local" });
+/// let config = json!({ "url": "db.local", "port": 5432, "pool": 10 });
+///
+/// assert_eq!(templatize(&config, &source), json!({
+///     "url": "{/fallback}",
+///     "port": "{/db/port}",
+///     "pool": 10
+/// }));
+/// ```
+pub fn templatize(resolved: &Value, source: &Value) -> Value {
+    let mut source_map = HashMap::new();
+    collect_all_absolute_paths(source, &AbsolutePath::new("/"), &mut source_map);
+
+    // Leaves are compared by their JSON text, keeping the preferred path for each
+    let mut paths_by_value: HashMap<String, &AbsolutePath> = HashMap::new();
+    for (path, value) in &source_map {
+        if value.is_object() || value.is_array() {
+            continue;
+        }
+        paths_by_value
+            .entry(value.to_string())
+            .and_modify(|best| {
+                let key =
+                    |path: &AbsolutePath| (path.segments().count(), path.as_str().to_string());
+                if key(path) < key(best) {
+                    *best = path;
+                }
+            })
+            .or_insert(path);
+    }
+
+    map_leaves(
+        resolved.clone(),
+        &Default::default(),
+        &|_, value| match paths_by_value.get(&value.to_string()) {
+            Some(path) => Value::String(format!("{{{}}}", path.to_reference())),
+            None => value,
+        },
+    )
 }
 
 /// Resolves JSON from Value and returns generic object
@@ -181,20 +1489,470 @@ where
     serde_json::from_value(resolved_json)
 }
 
-/// Resolves JSON from Read and returns generic object
-pub fn resolve_json_reader_to_object<R, T>(reader: R) -> Result<T, serde_json::Error>
+/// Resolves JSON from Read and returns generic object.
+///
+/// Failures are told apart by [`ResolveReadError`]: reading, parsing, or deserializing
+/// the resolved document.
+///
+/// ## Example:
+/// ```
+/// use std::collections::HashMap;
+/// use json_deref::{resolve_json_reader_to_object, ResolveReadError};
+///
+/// let config: HashMap<String, String> =
+///     resolve_json_reader_to_object(r#"{ "host": "localhost", "url": "http://{host}/" }"#.as_bytes())
+///         .unwrap();
+/// assert_eq!(config["url"], "http://localhost/");
+///
+/// let broken = resolve_json_reader_to_object::<_, HashMap<String, String>>("{".as_bytes());
+/// assert!(matches!(broken, Err(ResolveReadError::Parse(_))));
+/// ```
+pub fn resolve_json_reader_to_object<R, T>(reader: R) -> Result<T, ResolveReadError>
 where
     R: std::io::Read,
     T: DeserializeOwned,
 {
-    let input = serde_json::from_reader(reader)?;
-    resolve_json_to_object(&input)
+    resolve_json_reader_to_object_with_options(reader, &Default::default())
+}
+
+/// Resolves JSON from Read with the given options and returns generic object.
+///
+/// With [`ResolveOptions::strict`] set, a placeholder that can't be resolved
+/// is returned as [`ResolveReadError::Resolve`].
+pub fn resolve_json_reader_to_object_with_options<R, T>(
+    reader: R,
+    options: &ResolveOptions,
+) -> Result<T, ResolveReadError>
+where
+    R: std::io::Read,
+    T: DeserializeOwned,
+{
+    let input: Value = serde_json::from_reader(reader).map_err(ResolveReadError::from_read)?;
+    let resolved = resolve_json_with_options(&input, options)?;
+    serde_json::from_value(resolved).map_err(ResolveReadError::Deserialize)
+}
+
+/// Parses JSON5, resolves it and returns generic object.
+///
+/// JSON5 allows unquoted keys, single-quoted strings, comments and trailing commas,
+/// which suits configs edited by hand. Resolution works exactly as in [`resolve_json`].
+///
+/// ## Example:
+/// ```
+/// use std::collections::HashMap;
+/// use json_deref::resolve_json5_str;
+///
+/// let config: HashMap<String, String> = resolve_json5_str(
+///     "{
+///         // The host is shared
+///         host: 'localhost',
+///         url: 'http://{host}/',
+///     }",
+/// )
+/// .unwrap();
+///
+/// assert_eq!(config["url"], "http://localhost/");
+/// ```
+#[cfg(feature = "json5")]
+pub fn resolve_json5_str<T>(s: &str) -> Result<T, ResolveReadError>
+where
+    T: DeserializeOwned,
+{
+    let input: Value = json5::from_str(s)
+        .map_err(|error| ResolveReadError::Parse(serde::de::Error::custom(error)))?;
+    resolve_json_to_object(&input).map_err(ResolveReadError::Deserialize)
+}
+
+/// Resolves a value of the `config` crate, e.g. the merged layers of a `config::Config`.
+///
+/// The value is converted with [`config_to_json`], resolved as in [`resolve_json`]
+/// and converted back with [`json_to_config`]. Origins of the values are not kept.
+///
+/// ## Example:
+/// ```
+/// use json_deref::resolve_config_value;
+///
+/// let settings = config::Config::builder()
+///     .set_default("host", "localhost").unwrap()
+///     .set_override("url", "http://{host}/").unwrap()
+///     .build()
+///     .unwrap();
+///
+/// let resolved = resolve_config_value(&settings.try_deserialize().unwrap());
+/// let url = resolved.into_table().unwrap().remove("url").unwrap();
+///
+/// assert_eq!(url.into_string().unwrap(), "http://localhost/");
+/// ```
+#[cfg(feature = "config")]
+pub fn resolve_config_value(value: &config::Value) -> config::Value {
+    json_to_config(&resolve_json(&config_to_json(value)))
+}
+
+/// Resolves JSON, falling back to the content of a file named by a root `"$include"` key.
+///
+/// The file path is relative to `base_dir` and may not leave it: absolute paths and `..`
+/// segments are refused with an [`io::ErrorKind::InvalidInput`] error, as the document may
+/// come from an untrusted source. References resolve against the document first,
+/// and only paths missing from it are looked up in the included file, which allows splitting
+/// a config into several files. The `"$include"` key itself is kept in the output.
+/// Errors reading or parsing the included file are returned as [`ResolveReadError::Io`]
+/// and [`ResolveReadError::Parse`].
+///
+/// ## Example:
+/// ```
+/// use serde_json::json;
+/// use json_deref::resolve_json_with_includes;
+///
+/// let dir = std::env::temp_dir();
+/// std::fs::write(dir.join("json_deref_doc_db.json"), r#"{ "db": { "host": "db.local" } }"#)?;
+///
+/// let input = json!({ "$include": "json_deref_doc_db.json", "url": "postgres://{/db/host}" });
+/// let resolved = resolve_json_with_includes(&input, &dir)?;
+///
+/// assert_eq!(resolved["url"], json!("postgres://db.local"));
+/// # Ok::<(), json_deref::ResolveReadError>(())
+/// ```
+#[cfg(feature = "std")]
+pub fn resolve_json_with_includes(
+    input: &Value,
+    base_dir: &Path,
+) -> Result<Value, ResolveReadError> {
+    let mut fallback = HashMap::new();
+    if let Some(file) = input.get(INCLUDE_KEY).and_then(Value::as_str) {
+        let inside = Path::new(file)
+            .components()
+            .all(|component| matches!(component, Component::Normal(_) | Component::CurDir));
+        if !inside {
+            return Err(ResolveReadError::Io(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("included file {file} is outside of the base directory"),
+            )));
+        }
+        let included: Value =
+            serde_json::from_reader(BufReader::new(File::open(base_dir.join(file))?))
+                .map_err(ResolveReadError::from_read)?;
+        collect_all_absolute_paths(&included, &AbsolutePath::new("/"), &mut fallback);
+    }
+    Ok(resolve_json_with_fallback(input, fallback))
+}
+
+/// Resolves JSON, looking up the paths missing from the document in `extra`.
+///
+/// Every extra value is addressed by its absolute path along with everything nested in it,
+/// so injecting `/secret` as `{ "token": "abc" }` also provides `/secret/token`.
+/// Values in the document take precedence over extra ones.
+///
+/// ## Example:
+/// ```
+/// use serde_json::json;
+/// use std::collections::HashMap;
+/// use json_deref::{resolve_json_with_extra, AbsolutePath};
+///
+/// let input = json!({ "auth": "Bearer {/secret/token}" });
+/// let extra = HashMap::from([(AbsolutePath::new("/secret"), json!({ "token": "abc" }))]);
+///
+/// assert_eq!(resolve_json_with_extra(&input, &extra), json!({ "auth": "Bearer abc" }));
+/// ```
+pub fn resolve_json_with_extra(input: &Value, extra: &HashMap<AbsolutePath, Value>) -> Value {
+    let mut fallback = HashMap::new();
+    for (path, value) in extra {
+        fallback.insert(path.clone(), value.clone());
+        collect_all_absolute_paths(value, path, &mut fallback);
+    }
+    resolve_json_with_fallback(input, fallback)
+}
+
+/// Same as [`resolve_json_with_extra`], with the extra values keyed by plain strings.
+///
+/// ## Example:
+/// ```
+/// use serde_json::json;
+/// use json_deref::resolve_json_with_extra_str;
+///
+/// let input = json!({ "auth": "Bearer {/secret/token}", "name": "{/user}" });
+/// let resolved = resolve_json_with_extra_str(
+///     &input,
+///     &[("/secret/token", json!("abc")), ("/user", json!("bob"))],
+/// );
+///
+/// assert_eq!(resolved, json!({ "auth": "Bearer abc", "name": "bob" }));
+/// ```
+pub fn resolve_json_with_extra_str(input: &Value, extra: &[(&str, Value)]) -> Value {
+    let extra: HashMap<AbsolutePath, Value> = extra
+        .iter()
+        .map(|(path, value)| (AbsolutePath::new(path), value.clone()))
+        .collect();
+    resolve_json_with_extra(input, &extra)
+}
+
+/// Resolves JSON against its own values, then against `fallback` for the paths it lacks
+fn resolve_json_with_fallback(input: &Value, fallback: HashMap<AbsolutePath, Value>) -> Value {
+    let options = ResolveOptions::default();
+    let mut normalized = normalize_json(input, &options, &mut Default::default());
+    for (path, value) in fallback {
+        normalized.extracted_values.entry(path).or_insert(value);
+    }
+
+    normalized
+        .resolver(input, &options)
+        .resolve(input, &Default::default())
 }
 
 pub fn resolve_template_with_source(template: &Value, source: &Value) -> Value {
+    resolve_template_collecting(template, source, &Default::default()).0
+}
+
+/// Resolves the template against any [`Source`], e.g. a [`FnSource`] computing values on demand.
+///
+/// The source is asked for every referenced path, once per occurrence.
+pub fn resolve_template<S: Source>(template: &Value, source: &S) -> Value {
+    ValueResolver::new(source, &Default::default()).resolve(template, &Default::default())
+}
+
+/// Resolves the template using the source JSON with the given options.
+///
+/// Supports the same placeholder forms and strictness as [`resolve_json_with_options`].
+/// A standalone `{$..name}` additionally collects the values of all `name` fields
+/// anywhere in the source into an array, in document order. `{$..name[1]}` selects
+/// only the second of them, and stays unresolved when there are fewer. So does a selector
+/// with an unclosed bracket, like `{$..name[}`.
+pub fn resolve_template_with_options(
+    template: &Value,
+    source: &Value,
+    options: &ResolveOptions,
+) -> Result<Value, ResolveError> {
+    let (resolved, problems) = resolve_template_collecting(template, source, options);
+    problems.into_result(resolved, options)
+}
+
+/// Resolves the template using the source JSON, also resolving placeholders within the
+/// values pulled from the source.
+///
+/// Source values may refer to other source values, with relative placeholders taken relative
+/// to their place in the source, which makes sources composable. A value that refers back to
+/// itself, directly or through other values, is inserted unresolved.
+///
+/// ## Example:
+/// ```
+/// use serde_json::json;
+/// use json_deref::resolve_recursive_sources;
+///
+/// let source = json!({
+///     "host": "example.com",
+///     "api": "https://{host}/api",
+///     "users": "{/api}/users"
+/// });
+///
+/// let resolved = resolve_recursive_sources(&json!({ "endpoint": "{/users}" }), &source);
+/// assert_eq!(resolved, json!({ "endpoint": "https://example.com/api/users" }));
+/// ```
+pub fn resolve_recursive_sources(template: &Value, source: &Value) -> Value {
+    let mut source_map = HashMap::new();
+    collect_all_absolute_paths(source, &AbsolutePath::new("/"), &mut source_map);
+
+    ValueResolver::new(&source_map, &Default::default())
+        .with_recursive_sources()
+        .resolve(template, &Default::default())
+}
+
+/// Resolves the template using the source JSON, then upserts the source's arrays into the
+/// template's arrays at the same place, matching object elements by their `key` field.
+///
+/// Elements with the same key are merged field by field, the template's fields winning over
+/// the source's. Source elements keep their order and template elements without a match
+/// are appended, so the result holds the elements of both.
+///
+/// ## Example:
+/// ```
+/// use serde_json::json;
+/// use json_deref::resolve_template_merge_arrays_by;
+///
+/// let template = json!({ "hosts": [{ "id": "b", "port": 8081 }, { "id": "c", "port": 8082 }] });
+/// let source = json!({ "hosts": [{ "id": "a", "port": 80 }, { "id": "b", "port": 81 }] });
+///
+/// let merged = resolve_template_merge_arrays_by(&template, &source, "id");
+/// assert_eq!(merged, json!({ "hosts": [
+///     { "id": "a", "port": 80 },
+///     { "id": "b", "port": 8081 },
+///     { "id": "c", "port": 8082 }
+/// ] }));
+/// ```
+pub fn resolve_template_merge_arrays_by(template: &Value, source: &Value, key: &str) -> Value {
+    let resolved = resolve_template_with_source(template, source);
+    merge_arrays_by(resolved, source, key)
+}
+
+/// Resolves the template using a source that is already flattened to `"/a/b" -> value` form.
+///
+/// Keys are used as absolute paths as they are, without walking any nested values,
+/// so `{/a}` only resolves if the map has an `"/a"` key.
+///
+/// ## Example:
+/// ```
+/// use serde_json::json;
+/// use std::collections::HashMap;
+/// use json_deref::resolve_template_with_flat_source;
+///
+/// let flat = HashMap::from([
+///     ("/db/host".to_string(), json!("localhost")),
+///     ("/db/port".to_string(), json!(5432)),
+/// ]);
+///
+/// let template = json!({
+///     "port": "{/db/port}",
+///     "url": "postgres://{/db/host}",
+///     "user": "{/db/user}"
+/// });
+///
+/// let resolved = resolve_template_with_flat_source(&template, &flat);
+///
+/// assert_eq!(resolved, json!({
+///     "port": 5432,
+///     "url": "postgres://localhost",
+///     "user": "{/db/user}" // Missing keys remain unchanged
+/// }));
+/// ```
+pub fn resolve_template_with_flat_source(template: &Value, flat: &HashMap<String, Value>) -> Value {
+    let source_map: HashMap<AbsolutePath, Value> = flat
+        .iter()
+        .map(|(path, value)| (AbsolutePath::new(path), value.clone()))
+        .collect();
+
+    ValueResolver::new(&source_map, &Default::default()).resolve(template, &Default::default())
+}
+
+/// Resolves the template using the source JSON and lists the source paths it never used.
+///
+/// A source path counts as used if the template references it, one of its parents,
+/// or anything beneath it. The unused paths are sorted, which helps to trim bloated sources.
+///
+/// ## Example:
+/// ```
+/// use serde_json::json;
+/// use json_deref::{resolve_template_report_unused, AbsolutePath};
+///
+/// let template = json!({ "host": "{/db/host}", "app": "{/app}" });
+/// let source = json!({
+///     "db": { "host": "localhost", "port": 5432 },
+///     "app": { "name": "demo" },
+///     "legacy": true
+/// });
+///
+/// let (resolved, unused) = resolve_template_report_unused(&template, &source);
+///
+/// assert_eq!(resolved, json!({ "host": "localhost", "app": { "name": "demo" } }));
+/// assert_eq!(unused, vec![AbsolutePath::new("/db/port"), AbsolutePath::new("/legacy")]);
+/// ```
+pub fn resolve_template_report_unused(
+    template: &Value,
+    source: &Value,
+) -> (Value, Vec<AbsolutePath>) {
+    let mut source_map = HashMap::new();
+    collect_all_absolute_paths(source, &AbsolutePath::new("/"), &mut source_map);
+
+    let mut referenced = HashSet::new();
+    referenced_source_paths(template, &mut referenced);
+
+    let mut unused: Vec<AbsolutePath> = source_map
+        .keys()
+        .filter(|path| {
+            !referenced
+                .iter()
+                .any(|used| path.contains(used) || used.contains(path))
+        })
+        .cloned()
+        .collect();
+    unused.sort_by(|a, b| a.as_str().cmp(b.as_str()));
+
+    let resolved =
+        ValueResolver::new(&source_map, &Default::default()).resolve(template, &Default::default());
+    (resolved, unused)
+}
+
+/// Checks that the source has a value for every absolute path the template refers to.
+///
+/// Meant as a fast pre-flight before resolving, e.g. to fail a deploy early. Paths listed
+/// in the template's `"$optional"` key and wildcard paths, which may match nothing, are
+/// not required. The missing paths are returned sorted.
+///
+/// ## Example:
+/// ```
+/// use serde_json::json;
+/// use json_deref::{source_covers, AbsolutePath};
+///
+/// let template = json!({ "host": "{/db/host}", "url": "postgres://{/db/host}:{/db/port}" });
+///
+/// assert!(source_covers(&template, &json!({ "db": { "host": "h", "port": 1 } })).is_ok());
+/// assert_eq!(
+///     source_covers(&template, &json!({ "db": { "host": "h" } })),
+///     Err(vec![AbsolutePath::new("/db/port")])
+/// );
+/// ```
+pub fn source_covers(template: &Value, source: &Value) -> Result<(), Vec<AbsolutePath>> {
+    let mut source_map = HashMap::new();
+    collect_all_absolute_paths(source, &AbsolutePath::new("/"), &mut source_map);
+
+    let mut referenced = HashSet::new();
+    referenced_source_paths(template, &mut referenced);
+    let optional = optional_paths(template);
+
+    let mut missing: Vec<AbsolutePath> = referenced
+        .into_iter()
+        .filter(|path| {
+            let is_root = path.segments().next().is_none();
+            let is_pattern = path.segments().any(|segment| segment == WILDCARD);
+            !is_root && !is_pattern && !optional.contains(path) && !source_map.contains_key(path)
+        })
+        .collect();
+
+    if missing.is_empty() {
+        return Ok(());
+    }
+
+    missing.sort_by(|a, b| a.as_str().cmp(b.as_str()));
+    Err(missing)
+}
+
+/// Resolves the template using the source JSON and reports suspicious findings.
+///
+/// A value pulled from the source may itself contain relative placeholders like `{../x}`.
+/// Such placeholders only make sense inside the source document, so they are copied
+/// as-is and reported as [`Warning::OrphanedRelativeReference`].
+///
+/// ## Example:
+/// ```
+/// use serde_json::json;
+/// use json_deref::{resolve_template_with_warnings, AbsolutePath, Warning};
+///
+/// let template = json!({ "greeting": "{/messages/hello}" });
+/// let source = json!({ "messages": { "hello": "Hello, {../user}" } });
+///
+/// let (resolved, warnings) = resolve_template_with_warnings(&template, &source);
+///
+/// assert_eq!(resolved, json!({ "greeting": "Hello, {../user}" }));
+/// assert_eq!(warnings, vec![Warning::OrphanedRelativeReference {
+///     at: AbsolutePath::new("/greeting"),
+///     reference: "../user".to_string(),
+/// }]);
+/// ```
+pub fn resolve_template_with_warnings(template: &Value, source: &Value) -> (Value, Vec<Warning>) {
+    let (resolved, problems) = resolve_template_collecting(template, source, &Default::default());
+    (resolved, problems.warnings)
+}
+
+fn resolve_template_collecting(
+    template: &Value,
+    source: &Value,
+    options: &ResolveOptions,
+) -> (Value, Problems) {
     // Build a HashMap for all absolute paths in the source JSON
     let mut source_map = HashMap::new();
     collect_all_absolute_paths(source, &AbsolutePath::new("/"), &mut source_map);
 
-    resolve_recursive(template, &source_map)
+    let mut resolver = ValueResolver::new(&source_map, options)
+        .with_optional_paths(optional_paths(template))
+        .with_source_root(&options.source_root)
+        .with_fuzzy_source(&source_map);
+    let resolved = resolver.resolve(template, &Default::default());
+    (resolved, resolver.into_problems())
 }
@@ -0,0 +1,122 @@
+use crate::parsing::path::AbsolutePath;
+
+/// Options controlling how placeholders are resolved.
+///
+/// The default options reproduce the behavior of [`crate::resolve_json`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolveOptions {
+    /// Return an error for placeholders that can't be resolved or cast,
+    /// instead of leaving them unchanged.
+    pub strict: bool,
+
+    /// Separator used to join an array referenced inside a larger string,
+    /// e.g. `", "` turns `"Tags: {/tags}"` into `"Tags: a, b"`.
+    /// String elements are inserted as-is, other elements are serialized as JSON.
+    /// Without a separator the whole array is inserted as JSON, `["a","b"]`.
+    pub embed_array_join: Option<String>,
+
+    /// Separator between the `key=value` pairs of a `{spread_kv:/map}` placeholder, `&` by default
+    pub spread_kv_separator: String,
+
+    /// What to do with placeholders whose target doesn't exist
+    pub on_missing: Missing,
+
+    /// Text opening a placeholder, `{` by default. Empty delimiters fall back to the defaults.
+    pub open_delim: String,
+
+    /// Text closing a placeholder, `}` by default
+    pub close_delim: String,
+
+    /// Text that, put right before the opening or closing delimiter, makes it literal text.
+    /// `\` by default, so `\{100\}` becomes `{100}`. With `${`…`}` delimiters and `$` as the
+    /// escape, `$${x}` becomes a literal `${x}`. Set to `None` to keep backslashes before
+    /// delimiters as they are, which was the default before.
+    pub escape_delim: Option<String>,
+
+    /// Treat a string holding a single placeholder surrounded by whitespace as standalone,
+    /// so `"  {/count}  "` resolves to the typed value with the whitespace dropped.
+    pub trim_then_typed: bool,
+
+    /// Turn every number, boolean and null in the output into its JSON text,
+    /// e.g. `8080` into `"8080"`, for consumers that only accept string scalars.
+    pub stringify_scalars: bool,
+
+    /// Replace runs of whitespace with single spaces and trim the ends of strings
+    /// changed by embedded placeholders, e.g. when one of them resolved to an empty string.
+    pub collapse_whitespace: bool,
+
+    /// Merge the entries of an object that a standalone placeholder resolves to into the
+    /// object holding the placeholder, dropping its key: `"base": "{/defaults}"` adds the keys
+    /// of `defaults`. Keys written in the object win, and later placeholders override earlier ones.
+    pub splice_object_refs: bool,
+
+    /// Where absolute paths of a template start in its source, the root by default.
+    /// With `/app`, `{/db/host}` reads `/app/db/host`, so a template works against sources
+    /// nesting everything under a key. A document resolving itself is not affected.
+    pub source_root: AbsolutePath,
+
+    /// Resolve a placeholder whose target is missing with the path one character away from it,
+    /// e.g. `{/user/nmae}` with `/user/name`, reporting [`crate::Warning::FuzzyMatch`].
+    /// Ties are broken alphabetically. Off by default, as a wrong guess goes unnoticed.
+    pub fuzzy: bool,
+
+    /// How array indices are written in placeholder paths, and in the placeholders
+    /// rewritten while normalizing relative paths
+    pub index_style: IndexStyle,
+
+    /// Read keys in placeholder paths with the escapes of RFC 6901 JSON pointers, `~1` for `/`
+    /// and `~0` for `~`, so `{/a~1b}` refers to the key `a/b`. Also applies to the placeholders
+    /// rewritten while normalizing relative paths.
+    pub json_pointer: bool,
+
+    /// Longest placeholder body, in bytes, that is parsed as a reference.
+    /// Longer `{`…`}` spans are kept as literal text, and reported when strict.
+    /// Unlimited by default; lower it when resolving untrusted templates.
+    pub max_reference_len: usize,
+}
+
+impl Default for ResolveOptions {
+    fn default() -> Self {
+        ResolveOptions {
+            strict: false,
+            embed_array_join: None,
+            spread_kv_separator: "&".to_string(),
+            on_missing: Missing::default(),
+            open_delim: "{".to_string(),
+            close_delim: "}".to_string(),
+            escape_delim: Some("\\".to_string()),
+            trim_then_typed: false,
+            stringify_scalars: false,
+            collapse_whitespace: false,
+            splice_object_refs: false,
+            source_root: AbsolutePath::new("/"),
+            fuzzy: false,
+            index_style: IndexStyle::default(),
+            json_pointer: false,
+            max_reference_len: usize::MAX,
+        }
+    }
+}
+
+/// How placeholders with a missing target appear in the output.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum Missing {
+    /// Leave the placeholder unchanged
+    #[default]
+    Keep,
+    /// Mark the placeholder, so `{/x}` becomes `{/x /* missing */}`
+    Annotate,
+    /// Replace the placeholder with a comment, so with `"# "` `{/x}` becomes `# /x`.
+    /// Useful for strings holding shell scripts, INI files and the like.
+    Comment(String),
+}
+
+/// How array indices are written in placeholder paths.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum IndexStyle {
+    /// `/items/3/name`, like any other segment
+    #[default]
+    Slash,
+    /// `/items[3]/name`, as used by JSONPath-like tools. `/items/3/name` keeps working too.
+    Brackets,
+}
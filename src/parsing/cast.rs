@@ -0,0 +1,168 @@
+use serde_json::{Number, Value};
+
+/// A target type requested with the `{/path:type}` syntax.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Cast {
+    Int,
+    Float,
+    Bool,
+    /// Integer or float, depending on how the number is written
+    Number,
+}
+
+impl Cast {
+    /// Parse the name used after the colon, e.g. `int` in `{/port:int}`
+    pub(crate) fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "int" => Some(Cast::Int),
+            "float" => Some(Cast::Float),
+            "bool" => Some(Cast::Bool),
+            "number" => Some(Cast::Number),
+            _ => None,
+        }
+    }
+
+    /// Convert the value to the requested type.
+    /// Returns None if the value has no sensible representation in that type.
+    pub(crate) fn apply(self, value: &Value) -> Option<Value> {
+        match self {
+            Cast::Int => match value {
+                Value::Number(number) => int_value(number),
+                Value::String(text) => int_value(&parse_number(text)?),
+                _ => None,
+            },
+            Cast::Float => match value {
+                Value::Number(number) => number.as_f64().and_then(float_value),
+                Value::String(text) => text.trim().parse::<f64>().ok().and_then(float_value),
+                _ => None,
+            },
+            Cast::Bool => match value {
+                Value::Bool(_) => Some(value.clone()),
+                Value::String(text) => match text.trim() {
+                    "true" => Some(Value::Bool(true)),
+                    "false" => Some(Value::Bool(false)),
+                    _ => None,
+                },
+                _ => None,
+            },
+            Cast::Number => match value {
+                Value::Number(_) => Some(value.clone()),
+                Value::String(text) => parse_number(text).map(Value::Number),
+                _ => None,
+            },
+        }
+    }
+}
+
+/// Parses a number the way JSON does: `3` stays an integer, while `3.0` and `3e2` become floats.
+///
+/// Integers are never rounded: one that doesn't fit into 64 bits is only accepted if
+/// `serde_json` keeps its exact digits (the `arbitrary_precision` feature), otherwise it's rejected.
+pub(crate) fn parse_number(text: &str) -> Option<Number> {
+    let text = text.trim();
+    let number: Number = text.parse().ok()?;
+
+    let written_as_integer = !text.contains(['.', 'e', 'E']);
+    let is_exact = number.is_i64() || number.is_u64() || number.to_string() == text;
+    if written_as_integer && !is_exact {
+        return None;
+    }
+    Some(number)
+}
+
+/// Integers are kept, floats are accepted only without a fractional part
+fn int_value(number: &Number) -> Option<Value> {
+    // Integers beyond 64 bits keep their digits with `arbitrary_precision`
+    let is_big_integer = || !number.to_string().contains(['.', 'e', 'E']);
+    if number.is_i64() || number.is_u64() || is_big_integer() {
+        return Some(Value::Number(number.clone()));
+    }
+
+    let float = number.as_f64()?;
+    (float.fract() == 0.0 && float.abs() < i64::MAX as f64)
+        .then(|| Value::Number((float as i64).into()))
+}
+
+fn float_value(float: f64) -> Option<Value> {
+    Number::from_f64(float).map(Value::Number)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_cast_int() {
+        assert_eq!(Cast::Int.apply(&json!("8080")), Some(json!(8080)));
+        assert_eq!(Cast::Int.apply(&json!(8080)), Some(json!(8080)));
+        assert_eq!(Cast::Int.apply(&json!("abc")), None);
+        assert_eq!(Cast::Int.apply(&json!(true)), None);
+    }
+
+    #[test]
+    fn test_cast_float() {
+        assert_eq!(Cast::Float.apply(&json!("0.5")), Some(json!(0.5)));
+        assert_eq!(Cast::Float.apply(&json!(2)), Some(json!(2.0)));
+        assert_eq!(Cast::Float.apply(&json!("half")), None);
+    }
+
+    #[test]
+    fn test_cast_bool() {
+        assert_eq!(Cast::Bool.apply(&json!("true")), Some(json!(true)));
+        assert_eq!(Cast::Bool.apply(&json!("false")), Some(json!(false)));
+        assert_eq!(Cast::Bool.apply(&json!("yes")), None);
+    }
+
+    #[test]
+    fn test_cast_number_keeps_written_form() {
+        let int = Cast::Number.apply(&json!("3")).unwrap();
+        assert!(int.is_u64());
+        assert_eq!(int, json!(3));
+
+        let float = Cast::Number.apply(&json!("3.0")).unwrap();
+        assert!(float.is_f64());
+        assert_eq!(float, json!(3.0));
+
+        let exponent = Cast::Number.apply(&json!("3e2")).unwrap();
+        assert!(exponent.is_f64());
+        assert_eq!(exponent.as_f64(), Some(300.0));
+
+        assert_eq!(Cast::Number.apply(&json!("-7")), Some(json!(-7)));
+        assert_eq!(Cast::Number.apply(&json!("three")), None);
+    }
+
+    #[test]
+    fn test_cast_twenty_digit_integer() {
+        let digits = "12345678901234567890";
+        let expected = Value::Number(digits.parse().unwrap());
+
+        for cast in [Cast::Int, Cast::Number] {
+            let cast_value = cast.apply(&json!(digits)).unwrap();
+            assert_eq!(cast_value, expected);
+            assert_eq!(cast_value.to_string(), digits);
+            // Numbers that already are numbers are passed through untouched
+            assert_eq!(cast.apply(&expected).unwrap().to_string(), digits);
+        }
+    }
+
+    #[cfg(not(feature = "arbitrary_precision"))]
+    #[test]
+    fn test_cast_overflowing_integer() {
+        // Rounding to a float would silently change the value
+        let overflowing = json!("99999999999999999999999");
+        assert_eq!(Cast::Number.apply(&overflowing), None);
+        assert_eq!(Cast::Int.apply(&overflowing), None);
+        assert_eq!(
+            Cast::Float.apply(&overflowing),
+            Some(json!(99999999999999999999999.0))
+        );
+    }
+
+    #[test]
+    fn test_cast_int_from_float_text() {
+        assert_eq!(Cast::Int.apply(&json!("3.0")), Some(json!(3)));
+        assert_eq!(Cast::Int.apply(&json!("3e2")), Some(json!(300)));
+        assert_eq!(Cast::Int.apply(&json!("3.5")), None);
+    }
+}
@@ -0,0 +1,376 @@
+use serde_json::Value;
+
+/// A transformation requested with the `{/path|filter}` syntax.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Filter {
+    /// `truncate:N` keeps the first N characters of a string, appending `…` if anything was cut
+    Truncate(usize),
+    /// `upper` converts a string to upper case
+    Upper,
+    /// `lower` converts a string to lower case
+    Lower,
+    /// `map:active=on;inactive=off` replaces a matching value with the mapped string
+    Map(Vec<(String, String)>),
+    /// Keeps the elements of an array from the first index up to, but excluding, the second.
+    /// Written as a path suffix, e.g. `/items/1:3`, rather than as a `|filter`.
+    Slice(usize, usize),
+    /// `count` replaces an array with its number of elements, or an object with its number
+    /// of fields. Handy with wildcards: `{/users/*/id|count}`.
+    Count,
+    /// `group` writes a number with its thousands separated by `,`, or by the given text
+    /// as in `group:.`. Produces a string, e.g. `1,234,567.5`.
+    Group(String),
+    /// `bytes` decodes a hex string into an array of byte values, e.g. `0aff` into `[10, 255]`
+    Bytes,
+    /// `hash` replaces a value with the first 8 hex digits of the SHA-256 of its text.
+    /// Strings are hashed as they are, other values as JSON.
+    #[cfg(feature = "hash")]
+    Hash,
+    /// `base64:decode` decodes a base64 string into a UTF-8 string
+    #[cfg(feature = "base64")]
+    Base64Decode,
+    /// `base64:encode` encodes a string as base64
+    #[cfg(feature = "base64")]
+    Base64Encode,
+}
+
+impl Filter {
+    /// Parse a single filter, e.g. `truncate:20`.
+    /// Returns None if the text is not a known filter.
+    pub(crate) fn parse(text: &str) -> Option<Self> {
+        let (name, argument) = text.split_once(':').unwrap_or((text, ""));
+        match name {
+            "truncate" => argument.parse().ok().map(Filter::Truncate),
+            "upper" if argument.is_empty() => Some(Filter::Upper),
+            "lower" if argument.is_empty() => Some(Filter::Lower),
+            "count" if argument.is_empty() => Some(Filter::Count),
+            "bytes" if argument.is_empty() => Some(Filter::Bytes),
+            "group" if argument.is_empty() => Some(Filter::Group(",".to_string())),
+            "group" => Some(Filter::Group(argument.to_string())),
+            #[cfg(feature = "hash")]
+            "hash" if argument.is_empty() => Some(Filter::Hash),
+            "map" => parse_table(argument).map(Filter::Map),
+            #[cfg(feature = "base64")]
+            "base64" => match argument {
+                "decode" => Some(Filter::Base64Decode),
+                "encode" => Some(Filter::Base64Encode),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Transform the resolved value.
+    /// Returns None if the filter can't be applied to a value of that type.
+    pub(crate) fn apply(&self, value: Value) -> Option<Value> {
+        match self {
+            Filter::Truncate(max_chars) => {
+                let Value::String(text) = value else {
+                    return None;
+                };
+                // Cut on a char boundary, never in the middle of a multibyte character
+                match text.char_indices().nth(*max_chars) {
+                    Some((cut, _)) => Some(Value::String(format!("{}…", &text[..cut]))),
+                    None => Some(Value::String(text)),
+                }
+            }
+            Filter::Upper => match value {
+                Value::String(text) => Some(Value::String(text.to_uppercase())),
+                _ => None,
+            },
+            Filter::Lower => match value {
+                Value::String(text) => Some(Value::String(text.to_lowercase())),
+                _ => None,
+            },
+            Filter::Map(table) => {
+                // Numbers and booleans are matched by their JSON text, e.g. `map:200=OK`
+                let key = match value {
+                    Value::String(text) => text,
+                    Value::Number(_) | Value::Bool(_) => value.to_string(),
+                    _ => return None,
+                };
+                table
+                    .iter()
+                    .find(|(from, _)| *from == key)
+                    .map(|(_, to)| Value::String(to.clone()))
+            }
+            Filter::Slice(start, end) => {
+                let Value::Array(arr) = value else {
+                    return None;
+                };
+                // Bounds are clamped, and inverted ranges are empty
+                let end = (*end).min(arr.len());
+                let start = (*start).min(end);
+                Some(Value::Array(arr[start..end].to_vec()))
+            }
+            Filter::Count => match value {
+                Value::Array(arr) => Some(Value::from(arr.len())),
+                Value::Object(map) => Some(Value::from(map.len())),
+                _ => None,
+            },
+            Filter::Group(separator) => match value {
+                Value::Number(number) => Some(Value::String(group_thousands(
+                    &number.to_string(),
+                    separator,
+                ))),
+                _ => None,
+            },
+            Filter::Bytes => {
+                let Value::String(text) = value else {
+                    return None;
+                };
+                let digits = text.as_bytes();
+                if digits.len() % 2 != 0 || !digits.iter().all(u8::is_ascii_hexdigit) {
+                    return None;
+                }
+                digits
+                    .chunks(2)
+                    .map(|pair| {
+                        let pair = std::str::from_utf8(pair).ok()?;
+                        u8::from_str_radix(pair, 16).ok().map(Value::from)
+                    })
+                    .collect::<Option<_>>()
+                    .map(Value::Array)
+            }
+            #[cfg(feature = "hash")]
+            Filter::Hash => {
+                use sha2::{Digest, Sha256};
+                let text = match value {
+                    Value::String(text) => text,
+                    other => other.to_string(),
+                };
+                let digest = Sha256::digest(text.as_bytes());
+                let hex: String = digest[..4]
+                    .iter()
+                    .map(|byte| format!("{byte:02x}"))
+                    .collect();
+                Some(Value::String(hex))
+            }
+            #[cfg(feature = "base64")]
+            Filter::Base64Decode => {
+                use base64::{Engine, engine::general_purpose::STANDARD};
+                let Value::String(text) = value else {
+                    return None;
+                };
+                let bytes = STANDARD.decode(text.trim()).ok()?;
+                String::from_utf8(bytes).ok().map(Value::String)
+            }
+            #[cfg(feature = "base64")]
+            Filter::Base64Encode => {
+                use base64::{Engine, engine::general_purpose::STANDARD};
+                match value {
+                    Value::String(text) => Some(Value::String(STANDARD.encode(text))),
+                    _ => None,
+                }
+            }
+        }
+    }
+}
+
+/// Inserts the separator between every three digits of the integer part of a number,
+/// keeping the sign, fraction and exponent as they are
+fn group_thousands(number: &str, separator: &str) -> String {
+    let (sign, unsigned) = match number.strip_prefix('-') {
+        Some(unsigned) => ("-", unsigned),
+        None => ("", number),
+    };
+    let digits = unsigned
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(unsigned.len());
+    let (integer, rest) = unsigned.split_at(digits);
+
+    let mut grouped = String::from(sign);
+    for (i, digit) in integer.chars().enumerate() {
+        if i > 0 && (integer.len() - i) % 3 == 0 {
+            grouped.push_str(separator);
+        }
+        grouped.push(digit);
+    }
+    grouped.push_str(rest);
+    grouped
+}
+
+/// Parse `key=value` entries separated by `;`
+fn parse_table(argument: &str) -> Option<Vec<(String, String)>> {
+    argument
+        .split(';')
+        .map(|entry| {
+            let (from, to) = entry.split_once('=')?;
+            (!from.is_empty()).then(|| (from.to_string(), to.to_string()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_filter() {
+        assert_eq!(Filter::parse("truncate:20"), Some(Filter::Truncate(20)));
+        assert_eq!(Filter::parse("truncate:many"), None);
+        assert_eq!(Filter::parse("upper"), Some(Filter::Upper));
+        assert_eq!(Filter::parse("lower"), Some(Filter::Lower));
+        assert_eq!(Filter::parse("count"), Some(Filter::Count));
+        assert_eq!(Filter::parse("count:2"), None);
+        assert_eq!(Filter::parse("unknown"), None);
+    }
+
+    #[test]
+    fn test_truncate_long_string() {
+        let filter = Filter::Truncate(5);
+        assert_eq!(filter.apply(json!("Hello, world")), Some(json!("Hello…")));
+    }
+
+    #[test]
+    fn test_truncate_short_string() {
+        let filter = Filter::Truncate(20);
+        assert_eq!(filter.apply(json!("Hello")), Some(json!("Hello")));
+        assert_eq!(filter.apply(json!("")), Some(json!("")));
+    }
+
+    #[test]
+    fn test_truncate_multibyte_string() {
+        let filter = Filter::Truncate(3);
+        assert_eq!(filter.apply(json!("Привет, мир")), Some(json!("При…")));
+        assert_eq!(filter.apply(json!("日本語")), Some(json!("日本語")));
+    }
+
+    #[test]
+    fn test_truncate_non_string() {
+        assert_eq!(Filter::Truncate(3).apply(json!(12345)), None);
+    }
+
+    #[test]
+    fn test_change_case() {
+        assert_eq!(Filter::Upper.apply(json!("Straße")), Some(json!("STRASSE")));
+        assert_eq!(Filter::Lower.apply(json!("ÀB")), Some(json!("àb")));
+        assert_eq!(Filter::Upper.apply(json!(true)), None);
+    }
+
+    #[test]
+    fn test_count() {
+        assert_eq!(Filter::Count.apply(json!([1, 2, 3])), Some(json!(3)));
+        assert_eq!(Filter::Count.apply(json!({ "a": 1 })), Some(json!(1)));
+        assert_eq!(Filter::Count.apply(json!("abc")), None);
+    }
+
+    #[test]
+    fn test_group() {
+        let group = Filter::parse("group").unwrap();
+        assert_eq!(group.apply(json!(1234567)), Some(json!("1,234,567")));
+        assert_eq!(group.apply(json!(-1234.5)), Some(json!("-1,234.5")));
+        assert_eq!(group.apply(json!(123)), Some(json!("123")));
+        assert_eq!(group.apply(json!(0)), Some(json!("0")));
+        assert_eq!(group.apply(json!("1234")), None);
+
+        let dotted = Filter::parse("group:.").unwrap();
+        assert_eq!(dotted, Filter::Group(".".to_string()));
+        assert_eq!(dotted.apply(json!(1234567)), Some(json!("1.234.567")));
+        let spaced = Filter::parse("group: ").unwrap();
+        assert_eq!(spaced.apply(json!(100000)), Some(json!("100 000")));
+    }
+
+    #[test]
+    fn test_bytes() {
+        assert_eq!(Filter::parse("bytes"), Some(Filter::Bytes));
+        assert_eq!(
+            Filter::Bytes.apply(json!("00ff7F10")),
+            Some(json!([0, 255, 127, 16]))
+        );
+        assert_eq!(Filter::Bytes.apply(json!("")), Some(json!([])));
+        assert_eq!(Filter::Bytes.apply(json!("abc")), None);
+        assert_eq!(Filter::Bytes.apply(json!("zz")), None);
+        assert_eq!(Filter::Bytes.apply(json!("+f")), None);
+        assert_eq!(Filter::Bytes.apply(json!("é0")), None);
+        assert_eq!(Filter::Bytes.apply(json!(10)), None);
+    }
+
+    #[cfg(feature = "hash")]
+    #[test]
+    fn test_hash() {
+        assert_eq!(Filter::parse("hash"), Some(Filter::Hash));
+        // SHA-256 of "hello" starts with 2cf24dba
+        assert_eq!(Filter::Hash.apply(json!("hello")), Some(json!("2cf24dba")));
+        assert_eq!(Filter::Hash.apply(json!("")), Some(json!("e3b0c442")));
+        assert_eq!(
+            Filter::Hash.apply(json!(42)),
+            Filter::Hash.apply(json!("42"))
+        );
+    }
+
+    #[test]
+    fn test_parse_map() {
+        assert_eq!(
+            Filter::parse("map:active=🟢;inactive=🔴"),
+            Some(Filter::Map(vec![
+                ("active".to_string(), "🟢".to_string()),
+                ("inactive".to_string(), "🔴".to_string()),
+            ]))
+        );
+        assert_eq!(Filter::parse("map:active"), None);
+        assert_eq!(Filter::parse("map:=x"), None);
+        assert_eq!(Filter::parse("map"), None);
+    }
+
+    #[test]
+    fn test_map_matching_key() {
+        let filter = Filter::parse("map:active=🟢;inactive=🔴;banned=⛔").unwrap();
+        assert_eq!(filter.apply(json!("active")), Some(json!("🟢")));
+        assert_eq!(filter.apply(json!("inactive")), Some(json!("🔴")));
+        assert_eq!(filter.apply(json!("banned")), Some(json!("⛔")));
+
+        let filter = Filter::parse("map:200=OK;404=Not found").unwrap();
+        assert_eq!(filter.apply(json!(404)), Some(json!("Not found")));
+    }
+
+    #[test]
+    fn test_map_non_matching_key() {
+        let filter = Filter::parse("map:active=🟢;inactive=🔴").unwrap();
+        assert_eq!(filter.apply(json!("pending")), None);
+        assert_eq!(filter.apply(json!(["active"])), None);
+    }
+
+    #[test]
+    fn test_slice() {
+        let items = json!(["a", "b", "c", "d"]);
+        assert_eq!(
+            Filter::Slice(1, 3).apply(items.clone()),
+            Some(json!(["b", "c"]))
+        );
+        assert_eq!(
+            Filter::Slice(2, 10).apply(items.clone()),
+            Some(json!(["c", "d"]))
+        );
+        assert_eq!(Filter::Slice(3, 1).apply(items.clone()), Some(json!([])));
+        assert_eq!(Filter::Slice(7, 9).apply(items), Some(json!([])));
+        assert_eq!(Filter::Slice(0, 1).apply(json!("abc")), None);
+    }
+
+    #[cfg(feature = "base64")]
+    #[test]
+    fn test_base64_decode() {
+        let filter = Filter::parse("base64:decode").unwrap();
+        assert_eq!(
+            filter.apply(json!("aGVsbG8gd29ybGQ=")),
+            Some(json!("hello world"))
+        );
+        assert_eq!(filter.apply(json!("not base64!")), None);
+        // Valid base64, but not UTF-8
+        assert_eq!(filter.apply(json!("/w==")), None);
+        assert_eq!(Filter::parse("base64:zip"), None);
+    }
+
+    #[cfg(feature = "base64")]
+    #[test]
+    fn test_base64_round_trip() {
+        let encode = Filter::parse("base64:encode").unwrap();
+        let decode = Filter::parse("base64:decode").unwrap();
+        let text = json!("Привет, {world}");
+
+        let encoded = encode.apply(text.clone()).unwrap();
+        assert_eq!(encoded, json!("0J/RgNC40LLQtdGCLCB7d29ybGR9"));
+        assert_eq!(decode.apply(encoded), Some(text));
+    }
+}
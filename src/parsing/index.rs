@@ -0,0 +1,202 @@
+use super::path::AbsolutePath;
+use serde_json::Value;
+use std::{borrow::Cow, collections::HashMap};
+
+/// Source values indexed by path segments.
+///
+/// Exact lookups take one hash lookup per segment, and since children keep their insertion
+/// order, wildcard queries return matches in document order.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct PathIndex {
+    root: Node,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Node {
+    value: Option<Value>,
+    children: Vec<(String, Node)>,
+    positions: HashMap<String, usize>,
+}
+
+/// Path segment matching any key or array index
+pub(crate) const WILDCARD: &str = "*";
+
+impl PathIndex {
+    /// Adds `value` at the path, replacing the previous value there
+    pub(crate) fn insert(&mut self, path: &AbsolutePath, value: Value) {
+        let node = path.segments().fold(&mut self.root, |node, segment| {
+            let position = *node
+                .positions
+                .entry(segment.to_string())
+                .or_insert_with(|| {
+                    node.children.push((segment.to_string(), Node::default()));
+                    node.children.len() - 1
+                });
+            &mut node.children[position].1
+        });
+        node.value = Some(value);
+    }
+
+    /// Removes the value at the path along with everything beneath it
+    pub(crate) fn remove(&mut self, path: &AbsolutePath) {
+        let segments: Vec<Cow<str>> = path.segments().collect();
+        let Some((last, parents)) = segments.split_last() else {
+            self.root = Node::default();
+            return;
+        };
+        let parent = parents
+            .iter()
+            .try_fold(&mut self.root, |node, segment| node.child_mut(segment));
+        if let Some(parent) = parent
+            && let Some(position) = parent.positions.remove(last.as_ref())
+        {
+            parent.children.remove(position);
+            for index in parent.positions.values_mut() {
+                if *index > position {
+                    *index -= 1;
+                }
+            }
+        }
+    }
+
+    pub(crate) fn get(&self, path: &AbsolutePath) -> Option<&Value> {
+        path.segments()
+            .try_fold(&self.root, |node, segment| node.child(&segment))?
+            .value
+            .as_ref()
+    }
+
+    /// Values at all paths matching the pattern, in which `*` segments match anything
+    pub(crate) fn matching(&self, pattern: &AbsolutePath) -> Vec<&Value> {
+        let mut nodes = vec![&self.root];
+        for segment in pattern.segments() {
+            nodes = match segment.as_ref() {
+                WILDCARD => nodes
+                    .into_iter()
+                    .flat_map(|node| node.children.iter().map(|(_, child)| child))
+                    .collect(),
+                _ => nodes
+                    .into_iter()
+                    .filter_map(|node| node.child(&segment))
+                    .collect(),
+            };
+        }
+        nodes
+            .into_iter()
+            .filter_map(|node| node.value.as_ref())
+            .collect()
+    }
+
+    /// Values of all nodes named `field` at any depth, parents before their children
+    pub(crate) fn descendants(&self, field: &str) -> Vec<&Value> {
+        let mut found = Vec::new();
+        self.root.collect_descendants(field, &mut found);
+        found
+    }
+}
+
+impl Extend<(AbsolutePath, Value)> for PathIndex {
+    fn extend<T: IntoIterator<Item = (AbsolutePath, Value)>>(&mut self, entries: T) {
+        for (path, value) in entries {
+            self.insert(&path, value);
+        }
+    }
+}
+
+impl Node {
+    fn child(&self, segment: &str) -> Option<&Node> {
+        let position = *self.positions.get(segment)?;
+        Some(&self.children[position].1)
+    }
+
+    fn child_mut(&mut self, segment: &str) -> Option<&mut Node> {
+        let position = *self.positions.get(segment)?;
+        Some(&mut self.children[position].1)
+    }
+
+    fn collect_descendants<'a>(&'a self, field: &str, found: &mut Vec<&'a Value>) {
+        for (segment, child) in &self.children {
+            if segment == field
+                && let Some(value) = &child.value
+            {
+                found.push(value);
+            }
+            child.collect_descendants(field, found);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn index(entries: &[(&str, Value)]) -> PathIndex {
+        let mut index = PathIndex::default();
+        index.extend(
+            entries
+                .iter()
+                .map(|(path, value)| (AbsolutePath::new(path), value.clone())),
+        );
+        index
+    }
+
+    #[test]
+    fn test_exact_lookup() {
+        let index = index(&[("/a/b", json!(1)), ("/a/c", json!(2)), ("/a/b", json!(3))]);
+        assert_eq!(index.get(&AbsolutePath::new("/a/b")), Some(&json!(3)));
+        assert_eq!(index.get(&AbsolutePath::new("/a/c")), Some(&json!(2)));
+        // Intermediate nodes only have a value if one was inserted
+        assert_eq!(index.get(&AbsolutePath::new("/a")), None);
+        assert_eq!(index.get(&AbsolutePath::new("/a/d")), None);
+    }
+
+    #[test]
+    fn test_wildcard_keeps_insertion_order() {
+        let index = index(&[
+            ("/users/2/name", json!("Carol")),
+            ("/users/10/name", json!("Alice")),
+            ("/users/10/age", json!(30)),
+            ("/users/1/age", json!(20)),
+        ]);
+        assert_eq!(
+            index.matching(&AbsolutePath::new("/users/*/name")),
+            vec![&json!("Carol"), &json!("Alice")]
+        );
+        assert_eq!(index.matching(&AbsolutePath::new("/users/*/*")).len(), 4);
+        assert!(index.matching(&AbsolutePath::new("/groups/*")).is_empty());
+    }
+
+    #[test]
+    fn test_remove_subtree() {
+        let mut index = index(&[("/a/b", json!(1)), ("/a/c", json!(2)), ("/d", json!(3))]);
+        index.remove(&AbsolutePath::new("/a/b"));
+        assert_eq!(index.get(&AbsolutePath::new("/a/b")), None);
+        assert_eq!(index.get(&AbsolutePath::new("/a/c")), Some(&json!(2)));
+
+        index.remove(&AbsolutePath::new("/a"));
+        assert_eq!(index.get(&AbsolutePath::new("/a/c")), None);
+        assert_eq!(index.get(&AbsolutePath::new("/d")), Some(&json!(3)));
+    }
+
+    #[test]
+    fn test_descendants_at_any_depth() {
+        let index = index(&[
+            ("/name", json!("root")),
+            ("/users/0/name", json!("Alice")),
+            ("/users/0/pets/0/name", json!("Rex")),
+            ("/users/1/name", json!("Bob")),
+            ("/users/1/age", json!(30)),
+        ]);
+        assert_eq!(
+            index.descendants("name"),
+            vec![
+                &json!("root"),
+                &json!("Alice"),
+                &json!("Rex"),
+                &json!("Bob")
+            ]
+        );
+        assert!(index.descendants("email").is_empty());
+    }
+}
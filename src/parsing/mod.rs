@@ -1,7 +1,17 @@
+pub(crate) mod cast;
+pub(crate) mod filter;
+pub(crate) mod index;
 pub(crate) mod path;
+#[cfg(feature = "random")]
+pub(crate) mod random;
+pub(crate) mod reference;
+pub(crate) mod suggest;
+#[cfg(feature = "chrono")]
+pub(crate) mod timestamp;
 pub(crate) mod values_resolving;
 
 use path::{AbsolutePath, RelativePath};
+use reference::{Reference, Syntax, split_fallback_namespace, split_namespace};
 use serde_json::{Map, Value};
 use std::collections::{HashMap, HashSet};
 
@@ -9,41 +19,51 @@ pub(crate) fn make_deps_path_map(
     json: &Value,
     base_path: &AbsolutePath,
     complete_path_map: &mut HashMap<AbsolutePath, HashMap<RelativePath, AbsolutePath>>,
+    syntax: &Syntax,
 ) {
     match json {
         Value::Object(map) => {
             for (key, value) in map {
                 let current_absolute_path = base_path.append(key.as_str());
-                make_deps_path_map(value, &current_absolute_path, complete_path_map);
+                make_deps_path_map(value, &current_absolute_path, complete_path_map, syntax);
             }
         }
         Value::Array(arr) => {
             for (i, value) in arr.iter().enumerate() {
-                let current_absolute_path = base_path.append(&format!("{i}"));
-                make_deps_path_map(value, &current_absolute_path, complete_path_map);
+                let current_absolute_path = base_path.append_index(i);
+                make_deps_path_map(value, &current_absolute_path, complete_path_map, syntax);
             }
         }
         Value::String(text) => {
             let mut dependencies = HashMap::new();
             let mut start_pos = 0;
-            while let Some(start) = text[start_pos..].find('{') {
-                let absolute_start = start_pos + start;
-                if let Some(end) = text[absolute_start..].find('}') {
-                    let absolute_end = absolute_start + end;
-                    let reference = &text[absolute_start + 1..absolute_end];
-
-                    // Determine whether the reference is relative or absolute
-                    let relative_path = RelativePath::new(reference);
-                    if reference.starts_with('/') {
-                        dependencies.insert(relative_path.clone(), AbsolutePath::new(reference));
+            while let Some(span) = syntax.find_placeholder(text, start_pos) {
+                let reference = Reference::parse(&text[span.body_range()]);
+
+                // Empty braces are literal text, not a reference to the parent
+                for path in reference
+                    .paths()
+                    .into_iter()
+                    .filter(|path| !path.is_empty())
+                {
+                    // Namespaced references point outside of the document
+                    if split_namespace(path).is_some() || split_fallback_namespace(path).is_some() {
+                        continue;
+                    }
+
+                    // Determine whether the reference is relative or absolute.
+                    // Dependencies are keyed by the path as written.
+                    let written_path = RelativePath::new(path);
+                    let path = syntax.slash_indices(path);
+                    if path.starts_with('/') {
+                        dependencies.insert(written_path, AbsolutePath::new(&path));
                     } else {
-                        let absolute_dependency_path = base_path.resolve_with(&relative_path);
-                        dependencies.insert(relative_path, absolute_dependency_path);
+                        let absolute_dependency_path =
+                            base_path.resolve_with(&RelativePath::new(&path));
+                        dependencies.insert(written_path, absolute_dependency_path);
                     }
-                    start_pos = absolute_end + 1;
-                } else {
-                    break;
                 }
+                start_pos = span.end;
             }
 
             // Only insert into `complete_path_map` if there are actual dependencies
@@ -55,19 +75,156 @@ pub(crate) fn make_deps_path_map(
     }
 }
 
+/// Fields whose placeholders lead back to them, directly or through other fields, as chains
+/// in which each field refers to the next one and the last one to the first. A placeholder
+/// refers to the fields at its target and beneath it, so `{"a": {"b": "{/a}"}}` is a chain of
+/// `/a/b` alone. Every field of a cycle is part of at least one chain. Chains start with
+/// their smallest path and are sorted.
+pub(crate) fn find_cycles(
+    path_map: &HashMap<AbsolutePath, HashMap<RelativePath, AbsolutePath>>,
+) -> Vec<Vec<AbsolutePath>> {
+    let mut nodes: Vec<&AbsolutePath> = path_map.keys().collect();
+    nodes.sort_unstable_by_key(|path| path.as_str());
+    let successors: Vec<Vec<usize>> = nodes
+        .iter()
+        .map(|node| {
+            let mut next = Vec::new();
+            for target in path_map[*node].values() {
+                // Paths beneath the target share its text as a prefix, so they sort together
+                let start = nodes.partition_point(|path| path.as_str() < target.as_str());
+                next.extend(
+                    (start..nodes.len())
+                        .take_while(|&i| nodes[i].as_str().starts_with(target.as_str()))
+                        .filter(|&i| target.contains(nodes[i])),
+                );
+            }
+            next.sort_unstable();
+            next.dedup();
+            next
+        })
+        .collect();
+
+    let mut chains = Vec::new();
+    for component in strongly_connected(&successors) {
+        let members: HashSet<usize> = component.iter().copied().collect();
+        let mut covered = HashSet::new();
+        for &start in &component {
+            let is_cycle = component.len() > 1 || successors[start].contains(&start);
+            if !is_cycle || covered.contains(&start) {
+                continue;
+            }
+            let mut chain = shortest_cycle(start, &successors, &members);
+            covered.extend(chain.iter().copied());
+            let first = (0..chain.len()).min_by_key(|&i| chain[i]).unwrap_or(0);
+            chain.rotate_left(first);
+            chains.push(chain);
+        }
+    }
+    // Nodes are numbered in path order
+    chains.sort_unstable();
+    chains
+        .into_iter()
+        .map(|chain| chain.into_iter().map(|i| nodes[i].clone()).collect())
+        .collect()
+}
+
+/// Tarjan's strongly connected components of a graph given by successor lists, each sorted.
+/// Walks the graph with an explicit stack, so long chains of references can't overflow.
+fn strongly_connected(successors: &[Vec<usize>]) -> Vec<Vec<usize>> {
+    let unvisited = usize::MAX;
+    let mut index = vec![unvisited; successors.len()];
+    let mut low = vec![0; successors.len()];
+    let mut on_stack = vec![false; successors.len()];
+    let mut stack = Vec::new();
+    let mut counter = 0;
+    let mut components = Vec::new();
+
+    for root in 0..successors.len() {
+        if index[root] != unvisited {
+            continue;
+        }
+        index[root] = counter;
+        low[root] = counter;
+        counter += 1;
+        stack.push(root);
+        on_stack[root] = true;
+        let mut work = vec![(root, 0)];
+
+        while let Some((node, next)) = work.last_mut() {
+            let node = *node;
+            if let Some(&successor) = successors[node].get(*next) {
+                *next += 1;
+                if index[successor] == unvisited {
+                    index[successor] = counter;
+                    low[successor] = counter;
+                    counter += 1;
+                    stack.push(successor);
+                    on_stack[successor] = true;
+                    work.push((successor, 0));
+                } else if on_stack[successor] {
+                    low[node] = low[node].min(index[successor]);
+                }
+                continue;
+            }
+
+            work.pop();
+            if let Some((parent, _)) = work.last() {
+                low[*parent] = low[*parent].min(low[node]);
+            }
+            if low[node] == index[node] {
+                let mut component = Vec::new();
+                while let Some(member) = stack.pop() {
+                    on_stack[member] = false;
+                    component.push(member);
+                    if member == node {
+                        break;
+                    }
+                }
+                component.sort_unstable();
+                components.push(component);
+            }
+        }
+    }
+    components
+}
+
+/// The shortest way from `start` back to itself, through the given members only
+fn shortest_cycle(start: usize, successors: &[Vec<usize>], members: &HashSet<usize>) -> Vec<usize> {
+    let mut previous = HashMap::new();
+    let mut queue = std::collections::VecDeque::from([start]);
+    while let Some(node) = queue.pop_front() {
+        for &successor in &successors[node] {
+            if successor == start {
+                let mut chain = vec![node];
+                while let Some(&before) = previous.get(chain.last().unwrap()) {
+                    chain.push(before);
+                }
+                chain.reverse();
+                return chain;
+            }
+            if members.contains(&successor) && !previous.contains_key(&successor) {
+                previous.insert(successor, node);
+                queue.push_back(successor);
+            }
+        }
+    }
+    vec![start]
+}
+
 pub(crate) fn expand_absolute_paths(
     json: &Value,
     path_map: &HashMap<AbsolutePath, HashMap<RelativePath, AbsolutePath>>,
     current_path: &AbsolutePath,
+    syntax: &Syntax,
 ) -> Value {
     match json {
         Value::Object(map) => {
-            let mut new_map = Map::new();
+            let mut new_map = Map::with_capacity(map.len());
             for (key, value) in map {
-                let new_path = current_path.append(&key);
+                let new_path = current_path.append(key);
                 new_map.insert(
                     key.clone(),
-                    expand_absolute_paths(value, path_map, &new_path),
+                    expand_absolute_paths(value, path_map, &new_path, syntax),
                 );
             }
             Value::Object(new_map)
@@ -76,8 +233,8 @@ pub(crate) fn expand_absolute_paths(
             arr.iter()
                 .enumerate()
                 .map(|(i, v)| {
-                    let new_path = current_path.append(&format!("{i}"));
-                    expand_absolute_paths(v, path_map, &new_path)
+                    let new_path = current_path.append_index(i);
+                    expand_absolute_paths(v, path_map, &new_path, syntax)
                 })
                 .collect(),
         ),
@@ -85,28 +242,20 @@ pub(crate) fn expand_absolute_paths(
             let mut updated_text = text.clone();
             let mut start_pos = 0;
 
-            while let Some(start) = updated_text[start_pos..].find('{') {
-                let absolute_start = start_pos + start;
-                if let Some(end) = updated_text[absolute_start..].find('}') {
-                    let absolute_end = absolute_start + end;
-                    let relative_key =
-                        &RelativePath::new(&updated_text[absolute_start + 1..absolute_end]);
-
-                    if let Some(dependencies) = path_map.get(current_path) {
-                        if let Some(absolute_path) = dependencies.get(relative_key) {
-                            updated_text.replace_range(
-                                absolute_start..=absolute_end,
-                                &format!("{{{}}}", absolute_path.as_str()),
-                            );
-                            start_pos = absolute_start + absolute_path.as_str().len() + 2;
-                        } else {
-                            start_pos = absolute_end + 1;
-                        }
-                    } else {
-                        start_pos = absolute_end + 1;
-                    }
+            while let Some(span) = syntax.find_placeholder(&updated_text, start_pos) {
+                let reference = Reference::parse(&updated_text[span.body_range()]);
+
+                if let Some(dependencies) = path_map.get(current_path) {
+                    // Only the paths are replaced, modifiers are kept as they are
+                    let expanded = syntax.wrap(&reference.rewrite_paths(|path| {
+                        dependencies
+                            .get(&RelativePath::new(path))
+                            .map(|absolute_path| syntax.write_path(absolute_path))
+                    }));
+                    updated_text.replace_range(span.range(), &expanded);
+                    start_pos = span.start + expanded.len();
                 } else {
-                    break;
+                    start_pos = span.end;
                 }
             }
 
@@ -134,11 +283,16 @@ pub(crate) fn extract_values_by_paths(
                 let new_path = current_path.append(key);
                 extract_values_by_paths(value, paths, &new_path, extracted_values);
             }
+            for (key, name, element) in field_aliases(map) {
+                let alias_path = current_path.append(key).append(name);
+                extract_values_by_paths(element, paths, &alias_path, extracted_values);
+            }
         }
         Value::Array(arr) => {
             for (index, value) in arr.iter().enumerate() {
-                // Create a new path for the array index
-                let new_path = current_path.append(&index.to_string());
+                // Create a new path for the array index.
+                // Only actual positions are listed, so RFC 6901's `-` (past the end) never matches.
+                let new_path = current_path.append_index(index);
                 // Recurse into the array
                 extract_values_by_paths(value, paths, &new_path, extracted_values);
             }
@@ -159,26 +313,364 @@ pub(crate) fn extract_values_by_paths(
 pub(crate) fn collect_all_absolute_paths(
     json: &Value,
     current_path: &AbsolutePath,
-    source_map: &mut HashMap<AbsolutePath, Value>,
+    source_map: &mut impl Extend<(AbsolutePath, Value)>,
 ) {
     match json {
         Value::Object(map) => {
             for (key, value) in map {
                 let new_path = current_path.append(key);
-                source_map.insert(new_path.clone(), value.clone());
+                source_map.extend([(new_path.clone(), value.clone())]);
                 collect_all_absolute_paths(value, &new_path, source_map);
             }
+            for (key, name, element) in field_aliases(map) {
+                let alias_path = current_path.append(key).append(name);
+                source_map.extend([(alias_path.clone(), element.clone())]);
+                collect_all_absolute_paths(element, &alias_path, source_map);
+            }
         }
         Value::Array(array) => {
             for (index, value) in array.iter().enumerate() {
-                let new_path = current_path.append(&index.to_string());
-                source_map.insert(new_path.clone(), value.clone());
+                let new_path = current_path.append_index(index);
+                source_map.extend([(new_path.clone(), value.clone())]);
                 collect_all_absolute_paths(value, &new_path, source_map);
             }
         }
         _ => {
-            source_map.insert(current_path.clone(), json.clone());
+            source_map.extend([(current_path.clone(), json.clone())]);
+        }
+    }
+}
+
+/// Finds the value at the path, stepping into objects by key and into arrays by index
+pub(crate) fn value_at<'a>(json: &'a Value, path: &AbsolutePath) -> Option<&'a Value> {
+    path.segments()
+        .try_fold(json, |value, segment| match value {
+            Value::Object(map) => map.get(segment.as_ref()),
+            Value::Array(arr) => arr.get(segment.parse::<usize>().ok()?),
+            _ => None,
+        })
+}
+
+/// Mutable counterpart of [`value_at`]
+pub(crate) fn value_at_mut<'a>(json: &'a mut Value, path: &AbsolutePath) -> Option<&'a mut Value> {
+    path.segments()
+        .try_fold(json, |value, segment| match value {
+            Value::Object(map) => map.get_mut(segment.as_ref()),
+            Value::Array(arr) => arr.get_mut(segment.parse::<usize>().ok()?),
+            _ => None,
+        })
+}
+
+/// Object key naming the positions of the arrays next to it
+pub(crate) const FIELDS_KEY: &str = "$fields";
+
+/// Elements of the arrays in the object, paired with the names listed in its `"$fields"` key.
+/// With `"$fields": ["lat", "lng"]`, `/point/lat` refers to `/point/0` for a sibling `point` array.
+fn field_aliases(map: &Map<String, Value>) -> impl Iterator<Item = (&str, &str, &Value)> {
+    let names = map
+        .get(FIELDS_KEY)
+        .and_then(Value::as_array)
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+
+    map.iter()
+        .filter_map(|(key, value)| Some((key.as_str(), value.as_array()?)))
+        .flat_map(move |(key, arr)| {
+            names
+                .iter()
+                .zip(arr)
+                .filter_map(move |(name, element)| Some((key, name.as_str()?, element)))
+        })
+}
+
+/// Root key listing the absolute paths that are allowed to be missing
+pub(crate) const OPTIONAL_KEY: &str = "$optional";
+
+/// Reads the `"$optional": ["/a/b", "/c"]` list from the root of the document.
+/// Anything that isn't a string in that list is ignored.
+pub(crate) fn optional_paths(document: &Value) -> HashSet<AbsolutePath> {
+    document
+        .get(OPTIONAL_KEY)
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_str)
+        .map(AbsolutePath::new)
+        .collect()
+}
+
+/// Root key naming a file whose content is a fallback source, see [`crate::resolve_json_with_includes`]
+#[cfg(feature = "std")]
+pub(crate) const INCLUDE_KEY: &str = "$include";
+
+/// Collects the absolute paths referenced by placeholders anywhere in the template
+pub(crate) fn referenced_source_paths(template: &Value, paths: &mut HashSet<AbsolutePath>) {
+    match template {
+        Value::Object(map) => map
+            .values()
+            .for_each(|value| referenced_source_paths(value, paths)),
+        Value::Array(arr) => arr
+            .iter()
+            .for_each(|value| referenced_source_paths(value, paths)),
+        Value::String(text) => {
+            let syntax = Syntax::default();
+            let mut start_pos = 0;
+            while let Some(span) = syntax.find_placeholder(text, start_pos) {
+                let reference = Reference::parse(&text[span.body_range()]);
+                for path in reference.paths() {
+                    if path.starts_with('/') {
+                        paths.insert(AbsolutePath::new(path));
+                    }
+                }
+                start_pos = span.end;
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Replaces every string containing placeholders with an empty string
+pub(crate) fn strip_placeholders(json: &Value) -> Value {
+    match json {
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .map(|(key, value)| (key.clone(), strip_placeholders(value)))
+                .collect(),
+        ),
+        Value::Array(arr) => Value::Array(arr.iter().map(strip_placeholders).collect()),
+        Value::String(text) if Syntax::default().contains_placeholder(text) => {
+            Value::String(String::new())
+        }
+        _ => json.clone(),
+    }
+}
+
+/// Number of nested objects and arrays on the deepest path, 0 for a scalar
+pub(crate) fn nesting_depth(json: &Value) -> usize {
+    match json {
+        Value::Object(map) => 1 + map.values().map(nesting_depth).max().unwrap_or(0),
+        Value::Array(arr) => 1 + arr.iter().map(nesting_depth).max().unwrap_or(0),
+        _ => 0,
+    }
+}
+
+/// Inserts the `default` of every property of the JSON Schema missing from the object,
+/// descending into the properties that are objects themselves
+#[cfg(feature = "schema")]
+pub(crate) fn fill_schema_defaults(json: &mut Value, schema: &Value) {
+    let (Value::Object(map), Some(Value::Object(properties))) = (json, schema.get("properties"))
+    else {
+        return;
+    };
+    for (key, property) in properties {
+        match map.get_mut(key) {
+            Some(value) => fill_schema_defaults(value, property),
+            None => {
+                if let Some(default) = property.get("default") {
+                    map.insert(key.clone(), default.clone());
+                }
+            }
+        }
+    }
+}
+
+/// Merges the arrays of `overlay` with the arrays at the same place in `base`, matching
+/// object elements by their `key` field. Matched elements take the fields of both, with
+/// those of `overlay` winning. Elements of `base` keep their order, and the remaining
+/// elements of `overlay` follow. Everything outside of such arrays comes from `overlay`.
+pub(crate) fn merge_arrays_by(overlay: Value, base: &Value, key: &str) -> Value {
+    match (overlay, base) {
+        (Value::Object(map), Value::Object(base_map)) => Value::Object(
+            map.into_iter()
+                .map(|(name, value)| {
+                    let merged = match base_map.get(&name) {
+                        Some(base_value) => merge_arrays_by(value, base_value, key),
+                        None => value,
+                    };
+                    (name, merged)
+                })
+                .collect(),
+        ),
+        (Value::Array(elements), Value::Array(base_elements)) => {
+            let mut unmatched: Vec<Option<Value>> = elements.into_iter().map(Some).collect();
+            let mut merged = Vec::with_capacity(base_elements.len() + unmatched.len());
+            for base_element in base_elements {
+                // Each overlay element is taken by the first base element with its key
+                let overlay_element = base_element.get(key).and_then(|id| {
+                    unmatched
+                        .iter_mut()
+                        .find(|element| element.as_ref().and_then(|e| e.get(key)) == Some(id))
+                        .and_then(Option::take)
+                });
+                merged.push(match (overlay_element, base_element) {
+                    (Some(Value::Object(fields)), Value::Object(base_fields)) => {
+                        let mut fields_merged = base_fields.clone();
+                        fields_merged.extend(fields);
+                        Value::Object(fields_merged)
+                    }
+                    (Some(element), _) => element,
+                    (None, base_element) => base_element.clone(),
+                });
+            }
+            merged.extend(unmatched.into_iter().flatten());
+            Value::Array(merged)
+        }
+        (overlay, _) => overlay,
+    }
+}
+
+/// Length of the compact JSON text of the value, without building the text
+pub(crate) fn serialized_len(json: &Value) -> usize {
+    struct Counter(usize);
+
+    impl std::io::Write for Counter {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0 += buf.len();
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let mut counter = Counter(0);
+    // Writing to the counter can't fail, and neither can serializing a Value
+    let _ = serde_json::to_writer(&mut counter, json);
+    counter.0
+}
+
+/// Replaces numbers, booleans and nulls with their JSON text, keeping the structure
+pub(crate) fn stringify_scalars(json: Value) -> Value {
+    map_leaves(json, &Default::default(), &|_, leaf| match leaf {
+        Value::String(_) => leaf,
+        other => Value::String(other.to_string()),
+    })
+}
+
+/// Whether any string in the value holds a placeholder
+pub(crate) fn holds_placeholder(json: &Value, syntax: &Syntax) -> bool {
+    match json {
+        Value::Object(map) => map.values().any(|value| holds_placeholder(value, syntax)),
+        Value::Array(arr) => arr.iter().any(|value| holds_placeholder(value, syntax)),
+        Value::String(text) => syntax.contains_placeholder(text),
+        _ => false,
+    }
+}
+
+/// Number of strings holding placeholders, and of the placeholders in them
+pub(crate) fn count_placeholders(json: &Value, syntax: &Syntax) -> (usize, usize) {
+    match json {
+        Value::Object(map) => map.values().fold((0, 0), |(fields, placeholders), value| {
+            let (f, p) = count_placeholders(value, syntax);
+            (fields + f, placeholders + p)
+        }),
+        Value::Array(arr) => arr.iter().fold((0, 0), |(fields, placeholders), value| {
+            let (f, p) = count_placeholders(value, syntax);
+            (fields + f, placeholders + p)
+        }),
+        Value::String(text) => {
+            let placeholders = syntax.count_placeholders(text);
+            (usize::from(placeholders > 0), placeholders)
+        }
+        _ => (0, 0),
+    }
+}
+
+/// Collects the paths of the strings holding placeholders, in document order
+pub(crate) fn collect_placeholder_paths(
+    json: &Value,
+    current_path: &AbsolutePath,
+    syntax: &Syntax,
+    found: &mut Vec<AbsolutePath>,
+) {
+    match json {
+        Value::Object(map) => {
+            for (key, value) in map {
+                collect_placeholder_paths(value, &current_path.append(key), syntax, found);
+            }
+        }
+        Value::Array(arr) => {
+            for (i, value) in arr.iter().enumerate() {
+                collect_placeholder_paths(value, &current_path.append_index(i), syntax, found);
+            }
+        }
+        Value::String(text) if syntax.contains_placeholder(text) => {
+            found.push(current_path.clone())
+        }
+        _ => {}
+    }
+}
+
+/// Collects the strings of the template that differ in the resolved document, by path
+pub(crate) fn collect_replaced_strings(
+    template: &Value,
+    resolved: &Value,
+    current_path: &AbsolutePath,
+    originals: &mut HashMap<AbsolutePath, String>,
+) {
+    match (template, resolved) {
+        (Value::Object(map), Value::Object(resolved_map)) => {
+            for (key, value) in map {
+                if let Some(resolved_value) = resolved_map.get(key) {
+                    let new_path = current_path.append(key);
+                    collect_replaced_strings(value, resolved_value, &new_path, originals);
+                }
+            }
+        }
+        (Value::Array(arr), Value::Array(resolved_arr)) => {
+            for (i, (value, resolved_value)) in arr.iter().zip(resolved_arr).enumerate() {
+                let new_path = current_path.append_index(i);
+                collect_replaced_strings(value, resolved_value, &new_path, originals);
+            }
+        }
+        (Value::String(text), resolved) if resolved.as_str() != Some(text) => {
+            originals.insert(current_path.clone(), text.clone());
+        }
+        _ => {}
+    }
+}
+
+/// Collects the leaves of the document with their JSON Pointers, in document order.
+/// Empty objects and arrays count as leaves.
+pub(crate) fn collect_leaves(json: Value, pointer: String, leaves: &mut Vec<(String, Value)>) {
+    match json {
+        Value::Object(map) if !map.is_empty() => {
+            for (key, value) in map {
+                let escaped = key.replace('~', "~0").replace('/', "~1");
+                collect_leaves(value, format!("{pointer}/{escaped}"), leaves);
+            }
+        }
+        Value::Array(arr) if !arr.is_empty() => {
+            for (i, value) in arr.into_iter().enumerate() {
+                collect_leaves(value, format!("{pointer}/{i}"), leaves);
+            }
         }
+        leaf => leaves.push((pointer, leaf)),
+    }
+}
+
+/// Applies `f` to every leaf (anything but objects and arrays), passing the leaf path.
+pub(crate) fn map_leaves<F>(json: Value, current_path: &AbsolutePath, f: &F) -> Value
+where
+    F: Fn(&AbsolutePath, Value) -> Value,
+{
+    match json {
+        Value::Object(map) => Value::Object(
+            map.into_iter()
+                .map(|(key, value)| {
+                    let new_path = current_path.append(&key);
+                    (key, map_leaves(value, &new_path, f))
+                })
+                .collect(),
+        ),
+        Value::Array(arr) => Value::Array(
+            arr.into_iter()
+                .enumerate()
+                .map(|(i, value)| map_leaves(value, &current_path.append_index(i), f))
+                .collect(),
+        ),
+        leaf => f(current_path, leaf),
     }
 }
 
@@ -199,9 +691,15 @@ mod tests {
         });
 
         let mut path_map = HashMap::new();
-        make_deps_path_map(&input, &Default::default(), &mut path_map);
+        make_deps_path_map(
+            &input,
+            &Default::default(),
+            &mut path_map,
+            &Syntax::default(),
+        );
 
-        let result = expand_absolute_paths(&input, &path_map, &Default::default());
+        let result =
+            expand_absolute_paths(&input, &path_map, &Default::default(), &Syntax::default());
         let expected = json!({
             "posting_config": {
                 "published_message_caption": "Check this {/invite_group_link} or {/posting_config/invite_group_link}",
@@ -237,7 +735,12 @@ mod tests {
 
         // Generate the `path_map` using `make_deps_path_map`
         let mut path_map = HashMap::new();
-        make_deps_path_map(&input, &AbsolutePath::new("/"), &mut path_map);
+        make_deps_path_map(
+            &input,
+            &AbsolutePath::new("/"),
+            &mut path_map,
+            &Syntax::default(),
+        );
 
         // The corrected `expected_path_map`
         let expected_path_map = HashMap::from([
@@ -289,7 +792,12 @@ mod tests {
         assert_eq!(path_map, expected_path_map);
 
         // Ensure `expand_absolute_paths` resolves dependencies
-        let result_json = expand_absolute_paths(&input, &path_map, &AbsolutePath::new("/"));
+        let result_json = expand_absolute_paths(
+            &input,
+            &path_map,
+            &AbsolutePath::new("/"),
+            &Syntax::default(),
+        );
         let expected_json = serde_json::json!({
             "config": {
                 "level1": {
@@ -448,4 +956,1548 @@ mod tests {
         // Assert that the extracted values match the expected values
         assert_eq!(extracted_values, expected_extracted_values);
     }
+
+    #[test]
+    fn test_map_leaves_passes_paths() {
+        let input = json!({
+            "user": { "name": "Alice", "tags": ["a", "b"] },
+            "count": 2
+        });
+
+        let result = map_leaves(input, &AbsolutePath::default(), &|path, value| {
+            if path.as_str().starts_with("/user/tags") {
+                Value::String(path.as_str().to_string())
+            } else {
+                value
+            }
+        });
+
+        let expected = json!({
+            "user": { "name": "Alice", "tags": ["/user/tags/0", "/user/tags/1"] },
+            "count": 2
+        });
+
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_expand_keeps_filters_of_each_occurrence() {
+        let input = json!({
+            "user": {
+                "name": "Alice",
+                "title": "{name|upper} / {name|lower}"
+            }
+        });
+
+        let mut path_map = HashMap::new();
+        make_deps_path_map(
+            &input,
+            &Default::default(),
+            &mut path_map,
+            &Syntax::default(),
+        );
+
+        let result =
+            expand_absolute_paths(&input, &path_map, &Default::default(), &Syntax::default());
+        let expected = json!({
+            "user": {
+                "name": "Alice",
+                "title": "{/user/name|upper} / {/user/name|lower}"
+            }
+        });
+
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_referenced_source_paths() {
+        let template = json!({
+            "a": "{/data/a}",
+            "b": ["Embedded {/data/b|upper} and {/data/c:int}", "{relative}"],
+            "c": 42
+        });
+
+        let mut paths = HashSet::new();
+        referenced_source_paths(&template, &mut paths);
+
+        let expected = HashSet::from([
+            AbsolutePath::new("/data/a"),
+            AbsolutePath::new("/data/b"),
+            AbsolutePath::new("/data/c"),
+        ]);
+
+        assert_eq!(paths, expected);
+    }
+
+    #[test]
+    fn test_resolve_concat_with_relative_parts() {
+        let input = json!({
+            "user": {
+                "first": "Ada",
+                "last": "Lovelace",
+                "full": "{concat:first,\" \",last}"
+            }
+        });
+
+        let mut path_map = HashMap::new();
+        make_deps_path_map(
+            &input,
+            &Default::default(),
+            &mut path_map,
+            &Syntax::default(),
+        );
+
+        let expanded =
+            expand_absolute_paths(&input, &path_map, &Default::default(), &Syntax::default());
+        assert_eq!(
+            expanded["user"]["full"],
+            json!("{concat:/user/first,\" \",/user/last}")
+        );
+
+        let paths = path_map
+            .values()
+            .flat_map(|map| map.values().cloned())
+            .collect();
+        let mut extracted_values = HashMap::new();
+        extract_values_by_paths(
+            &expanded,
+            &paths,
+            &Default::default(),
+            &mut extracted_values,
+        );
+
+        let resolved = resolve_values(&expanded, &extracted_values);
+        assert_eq!(resolved["user"]["full"], json!("Ada Lovelace"));
+    }
+
+    #[test]
+    fn test_double_slash_is_root_anchor() {
+        let input = json!({
+            "x": "root value",
+            "nested": {
+                "x": "nested value",
+                "sibling": "{x}",
+                "anchored": "{//x}",
+                "embedded": "Value: {//x}"
+            }
+        });
+
+        let mut path_map = HashMap::new();
+        make_deps_path_map(
+            &input,
+            &Default::default(),
+            &mut path_map,
+            &Syntax::default(),
+        );
+
+        assert_eq!(
+            path_map[&AbsolutePath::new("/nested/anchored")][&RelativePath::new("//x")],
+            AbsolutePath::new("/x")
+        );
+
+        let expanded =
+            expand_absolute_paths(&input, &path_map, &Default::default(), &Syntax::default());
+        assert_eq!(expanded["nested"]["anchored"], json!("{/x}"));
+
+        let paths = path_map
+            .values()
+            .flat_map(|map| map.values().cloned())
+            .collect();
+        let mut extracted_values = HashMap::new();
+        extract_values_by_paths(
+            &expanded,
+            &paths,
+            &Default::default(),
+            &mut extracted_values,
+        );
+
+        let resolved = resolve_values(&expanded, &extracted_values);
+        assert_eq!(resolved["nested"]["sibling"], json!("nested value"));
+        assert_eq!(resolved["nested"]["anchored"], json!("root value"));
+        assert_eq!(resolved["nested"]["embedded"], json!("Value: root value"));
+    }
+
+    #[test]
+    fn test_optional_paths_are_not_errors() {
+        let input = json!({
+            "$optional": ["/overrides/port", 42],
+            "port": "{/overrides/port}",
+            "host": "{/overrides/host}"
+        });
+        assert_eq!(
+            optional_paths(&input),
+            HashSet::from([AbsolutePath::new("/overrides/port")])
+        );
+
+        let strict = crate::ResolveOptions {
+            strict: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            crate::resolve_json_with_options(&input, &strict),
+            Err(crate::ResolveError::Unresolved {
+                at: AbsolutePath::new("/host"),
+                reference: "/overrides/host".to_string()
+            })
+        );
+
+        let input = json!({
+            "$optional": ["/overrides/port"],
+            "port": "{/overrides/port}"
+        });
+        assert_eq!(crate::resolve_json_with_options(&input, &strict), Ok(input));
+    }
+
+    #[test]
+    fn test_dash_index_stays_unresolved() {
+        let input = json!({
+            "arr": [1, 2],
+            "obj": { "-": "dash key" },
+            "last": "{/arr/-}",
+            "embedded": "Last: {/arr/-}",
+            "dash": "{/obj/-}"
+        });
+
+        let resolved = crate::resolve_json(&input);
+        assert_eq!(resolved["last"], json!("{/arr/-}"));
+        assert_eq!(resolved["embedded"], json!("Last: {/arr/-}"));
+        // In objects `-` is an ordinary key
+        assert_eq!(resolved["dash"], json!("dash key"));
+
+        let template = json!({ "last": "{/arr/-}" });
+        assert_eq!(
+            crate::resolve_template_with_source(&template, &input),
+            template
+        );
+    }
+
+    #[test]
+    fn test_value_at() {
+        let json = json!({ "a": { "list": [10, { "b": true }] } });
+        assert_eq!(value_at(&json, &AbsolutePath::new("/")), Some(&json));
+        assert_eq!(
+            value_at(&json, &AbsolutePath::new("/a/list/0")),
+            Some(&json!(10))
+        );
+        assert_eq!(
+            value_at(&json, &AbsolutePath::new("/a/list/1/b")),
+            Some(&json!(true))
+        );
+        assert_eq!(value_at(&json, &AbsolutePath::new("/a/list/-")), None);
+        assert_eq!(value_at(&json, &AbsolutePath::new("/a/missing")), None);
+    }
+
+    #[test]
+    fn test_namespaced_references_are_kept_within_document() {
+        let input = json!({ "a": { "host": "{@db:/host}", "embedded": "at {@db:/host}" } });
+        assert_eq!(crate::resolve_json(&input), input);
+    }
+
+    #[test]
+    fn test_array_field_aliases() {
+        let input = json!({
+            "place": {
+                "$fields": ["lat", "lng"],
+                "point": [52.5, 13.4]
+            },
+            "lat": "{/place/point/lat}",
+            "index": "{/place/point/0}",
+            "unknown": "{/place/point/alt}"
+        });
+
+        let resolved = crate::resolve_json(&input);
+        assert_eq!(resolved["lat"], json!(52.5));
+        assert_eq!(resolved["index"], json!(52.5));
+        assert_eq!(resolved["unknown"], json!("{/place/point/alt}"));
+
+        let template = json!({ "lng": "{/place/point/lng}" });
+        assert_eq!(
+            crate::resolve_template_with_source(&template, &input),
+            json!({ "lng": 13.4 })
+        );
+    }
+
+    #[test]
+    fn test_resolve_json_with_normalized() {
+        let input = json!({
+            "config": {
+                "level1": {
+                    "key1": "value1",
+                    "key2": "{key1}",
+                    "nested": {
+                        "key4": "{../../level2/key5}",
+                        "key5": "local_value"
+                    }
+                },
+                "level2": {
+                    "key5": [1, 2, 3],
+                    "key6": "Local: {../level1/nested/key5}"
+                }
+            }
+        });
+
+        let (normalized, resolved) = crate::resolve_json_with_normalized(&input);
+
+        assert_eq!(
+            normalized,
+            json!({
+                "config": {
+                    "level1": {
+                        "key1": "value1",
+                        "key2": "{/config/level1/key1}",
+                        "nested": {
+                            "key4": "{/config/level2/key5}",
+                            "key5": "local_value"
+                        }
+                    },
+                    "level2": {
+                        "key5": [1, 2, 3],
+                        "key6": "Local: {/config/level1/nested/key5}"
+                    }
+                }
+            })
+        );
+        assert_eq!(
+            resolved,
+            json!({
+                "config": {
+                    "level1": {
+                        "key1": "value1",
+                        "key2": "value1",
+                        "nested": {
+                            "key4": [1, 2, 3],
+                            "key5": "local_value"
+                        }
+                    },
+                    "level2": {
+                        "key5": [1, 2, 3],
+                        "key6": "Local: local_value"
+                    }
+                }
+            })
+        );
+        assert_eq!(crate::resolve_json(&normalized), resolved);
+    }
+
+    #[test]
+    fn test_quoted_keys_with_spaces() {
+        let input = json!({
+            "   ": "only spaces",
+            "first name": "Bob",
+            " spaced ": { "x:int": "tricky" },
+            "blank": "{/[\"   \"]}",
+            "name": "Hi {/[\"first name\"]}",
+            "relative": "{[\" spaced \"]/[\"x:int\"]}"
+        });
+
+        let resolved = crate::resolve_json(&input);
+        assert_eq!(resolved["blank"], json!("only spaces"));
+        assert_eq!(resolved["name"], json!("Hi Bob"));
+        assert_eq!(resolved["relative"], json!("tricky"));
+
+        let template = json!({ "name": "{/[\"first name\"]}", "blank": "{/[\"   \"]}" });
+        assert_eq!(
+            crate::resolve_template_with_source(&template, &input),
+            json!({ "name": "Bob", "blank": "only spaces" })
+        );
+    }
+
+    #[test]
+    fn test_templatize_round_trip() {
+        let source = json!({
+            "hosts": { "db": "db.local", "cache": "cache.local" },
+            "ports": [5432, 6379],
+            "flags": { "debug": false }
+        });
+        let config = json!({
+            "database": { "host": "db.local", "port": 5432 },
+            "cache": ["cache.local", 6379],
+            "debug": false,
+            "name": "service"
+        });
+
+        let template = crate::templatize(&config, &source);
+        assert_eq!(
+            template,
+            json!({
+                "database": { "host": "{/hosts/db}", "port": "{/ports/0}" },
+                "cache": ["{/hosts/cache}", "{/ports/1}"],
+                "debug": "{/flags/debug}",
+                "name": "service"
+            })
+        );
+        assert_eq!(
+            crate::resolve_template_with_source(&template, &source),
+            config
+        );
+    }
+
+    #[test]
+    fn test_embedded_relative_references() {
+        // Relative placeholders among text are taken from the field holding them
+        let template = json!({
+            "services": {
+                "api": { "url": "http://{host}:{port}/v1", "note": "see {../web/name}, then {/title}" },
+                "web": ["{0/x} and {../api/host}!"]
+            }
+        });
+        let source = json!({
+            "title": "Docs",
+            "services": {
+                "api": { "host": "api.local", "port": "8080" },
+                "web": { "name": "portal" }
+            }
+        });
+        let expected = json!({
+            "services": {
+                "api": { "url": "http://api.local:8080/v1", "note": "see portal, then Docs" },
+                "web": ["{0/x} and api.local!"]
+            }
+        });
+        assert_eq!(
+            crate::resolve_template_with_source(&template, &source),
+            expected
+        );
+
+        // The document resolves its own fields the same way
+        let mut input = source.clone();
+        input["services"]["api"]["url"] = json!("http://{host}:{port}/v1");
+        input["services"]["api"]["note"] = json!("see {../web/name}, then {/title}");
+        let resolved = crate::resolve_json(&input);
+        assert_eq!(
+            resolved["services"]["api"]["url"],
+            "http://api.local:8080/v1"
+        );
+        assert_eq!(resolved["services"]["api"]["note"], "see portal, then Docs");
+    }
+
+    #[test]
+    fn test_resolve_constructed_numbers() {
+        // `From` conversions work the same with and without `arbitrary_precision`
+        let input = json!({
+            "answer": Value::Number(42.into()),
+            "copy": "{/answer}",
+            "text": "42",
+            "cast": "{/text:int}"
+        });
+        let resolved = crate::resolve_json(&input);
+        assert_eq!(resolved["copy"], Value::Number(42.into()));
+        assert_eq!(resolved["cast"], Value::Number(42.into()));
+    }
+
+    #[cfg(feature = "arbitrary_precision")]
+    #[test]
+    fn test_resolve_high_precision_numbers() {
+        let pi = "3.14159265358979323846264338327950288";
+        let big = "123456789012345678901234567890";
+        let input: Value = serde_json::from_str(&format!(
+            r#"{{
+                "pi": {pi},
+                "big": {big},
+                "big_text": "{big}",
+                "pi_copy": "{{/pi}}",
+                "big_copy": "{{/big}}",
+                "big_cast": "{{/big_text:int}}",
+                "pi_cast": "{{/pi:number}}"
+            }}"#
+        ))
+        .unwrap();
+
+        let resolved = crate::resolve_json(&input);
+        assert_eq!(resolved["pi_copy"].to_string(), pi);
+        assert_eq!(resolved["big_copy"].to_string(), big);
+        assert_eq!(resolved["big_cast"].to_string(), big);
+        assert_eq!(resolved["pi_cast"].to_string(), pi);
+
+        // Serializing and parsing again keeps every digit
+        let round_trip: Value = serde_json::from_str(&resolved.to_string()).unwrap();
+        assert_eq!(round_trip, resolved);
+    }
+
+    #[test]
+    fn test_custom_delimiters_with_escape() {
+        let options = crate::ResolveOptions {
+            open_delim: "${".to_string(),
+            close_delim: "}".to_string(),
+            escape_delim: Some("$".to_string()),
+            ..Default::default()
+        };
+        let input = json!({
+            "user": { "name": "Bob", "greeting": "Hi ${name}" },
+            "copy": "${/user/name}",
+            "escaped": "$${x}",
+            "mixed": "$${/user/name} is ${/user/name}",
+            "braces": "{/user/name} stays"
+        });
+
+        let resolved = crate::resolve_json_with_options(&input, &options).unwrap();
+        assert_eq!(resolved["user"]["greeting"], json!("Hi Bob"));
+        assert_eq!(resolved["copy"], json!("Bob"));
+        assert_eq!(resolved["escaped"], json!("${x}"));
+        assert_eq!(resolved["mixed"], json!("${/user/name} is Bob"));
+        assert_eq!(resolved["braces"], json!("{/user/name} stays"));
+
+        // Relative placeholders in referenced values are expanded with the same delimiters
+        let nested = json!({
+            "db": { "host": "localhost", "url": "pg://${host}/${../name}" },
+            "name": "app",
+            "url": "${/db/url}",
+            "plain": "{/name}"
+        });
+        let resolved = crate::resolve_json_with_options(&nested, &options).unwrap();
+        assert_eq!(resolved["db"]["url"], json!("pg://localhost/app"));
+        assert_eq!(resolved["url"], json!("pg://${/db/host}/${/name}"));
+        assert_eq!(resolved["plain"], json!("{/name}"));
+
+        let template = json!({ "name": "${/user/name}", "literal": "$${/user/name}" });
+        assert_eq!(
+            crate::resolve_template_with_options(&template, &input, &options),
+            Ok(json!({ "name": "Bob", "literal": "${/user/name}" }))
+        );
+    }
+
+    #[test]
+    fn test_json_pointer_paths() {
+        let options = crate::ResolveOptions {
+            json_pointer: true,
+            ..Default::default()
+        };
+        let input = json!({
+            "routes": { "api/v1": "backend", "~home": "static" },
+            "api": "{/routes/api~1v1}",
+            "home": "home is {/routes/~0home}",
+            "nested": { "own~key": "x", "copy": "{own~0key}!" }
+        });
+
+        let resolved = crate::resolve_json_with_options(&input, &options).unwrap();
+        assert_eq!(resolved["api"], json!("backend"));
+        assert_eq!(resolved["home"], json!("home is static"));
+        assert_eq!(resolved["nested"]["copy"], json!("x!"));
+
+        // Without the option tildes are part of the key
+        let resolved = crate::resolve_json(&input);
+        assert_eq!(resolved["api"], json!("{/routes/api~1v1}"));
+        assert_eq!(resolved["nested"]["copy"], json!("{own~0key}!"));
+
+        let template = json!({ "route": "{/routes/api~1v1}" });
+        assert_eq!(
+            crate::resolve_template_with_options(&template, &input, &options),
+            Ok(json!({ "route": "backend" }))
+        );
+
+        // A key holding a slash and the same keys nested are different values
+        let input = json!({
+            "a/b": 1,
+            "a": { "b": 2 },
+            "escaped": "{/a~1b}",
+            "nested": "{/a/b}",
+            "text": "{/a~1b} {/a/b}"
+        });
+        assert_eq!(
+            crate::resolve_json_with_options(&input, &options).unwrap(),
+            json!({ "a/b": 1, "a": { "b": 2 }, "escaped": 1, "nested": 2, "text": "1 2" })
+        );
+        let input =
+            json!({ "a/b": 1, "a": { "b": 2 }, "quoted": r#"{/["a/b"]}"#, "nested": "{/a/b}" });
+        assert_eq!(
+            crate::resolve_json(&input),
+            json!({ "a/b": 1, "a": { "b": 2 }, "quoted": 1, "nested": 2 })
+        );
+    }
+
+    #[test]
+    fn test_default_escapes() {
+        let input = json!({
+            "name": "Bob",
+            "price": r"Cost is \{100\}",
+            "mixed": r"\{name\} is {name}, {/name} likes \{braces}",
+        });
+
+        let resolved = crate::resolve_json(&input);
+        assert_eq!(resolved["price"], json!("Cost is {100}"));
+        assert_eq!(
+            resolved["mixed"],
+            json!("{name} is Bob, Bob likes {braces}")
+        );
+
+        // Without an escape backslashes are plain text, `{name\}` included
+        let options = crate::ResolveOptions {
+            escape_delim: None,
+            ..Default::default()
+        };
+        let resolved = crate::resolve_json_with_options(&input, &options).unwrap();
+        assert_eq!(
+            resolved["mixed"],
+            json!(r"\{name\} is Bob, Bob likes \{braces}")
+        );
+    }
+
+    #[test]
+    fn test_backslash_escapes() {
+        let options = crate::ResolveOptions {
+            escape_delim: Some("\\".to_string()),
+            ..Default::default()
+        };
+        let input = json!({
+            "name": "Bob",
+            "price": r"Cost is \{100\}",
+            "mixed": r"\{name\} is {name}, {/name} likes \{braces}",
+            "copy": "{/price}",
+            "closing": r"only \} here, {name}"
+        });
+
+        let resolved = crate::resolve_json_with_options(&input, &options).unwrap();
+        assert_eq!(resolved["price"], json!("Cost is {100}"));
+        assert_eq!(
+            resolved["mixed"],
+            json!("{name} is Bob, Bob likes {braces}")
+        );
+        assert_eq!(resolved["copy"], json!(r"Cost is \{100\}"));
+        assert_eq!(resolved["closing"], json!("only } here, Bob"));
+    }
+
+    #[cfg(feature = "json5")]
+    #[test]
+    fn test_resolve_json5() {
+        let input = r#"{
+            server: { host: 'example.com', port: '8080' },
+            url: 'https://{/server/host}:{/server/port}',
+            port: '{/server/port:int}',
+            'quoted key': "{server/host}", // trailing commas are fine
+        }"#;
+
+        let resolved: Value = crate::resolve_json5_str(input).unwrap();
+        assert_eq!(resolved["url"], json!("https://example.com:8080"));
+        assert_eq!(resolved["port"], json!(8080));
+        assert_eq!(resolved["quoted key"], json!("example.com"));
+
+        assert!(crate::resolve_json5_str::<Value>("{ unclosed: 'x' ").is_err());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_resolve_with_includes() {
+        let base_dir =
+            std::env::temp_dir().join(format!("json_deref_include_{}", std::process::id()));
+        std::fs::create_dir_all(&base_dir).unwrap();
+        std::fs::write(
+            base_dir.join("db.json"),
+            r#"{ "db": { "host": "db.local", "port": 5432 }, "name": "shared" }"#,
+        )
+        .unwrap();
+
+        let input = json!({
+            "$include": "db.json",
+            "name": "main",
+            "url": "postgres://{/db/host}",
+            "port": "{/db/port}",
+            "title": "{/name}",
+            "missing": "{/db/user}"
+        });
+        let resolved = crate::resolve_json_with_includes(&input, &base_dir).unwrap();
+
+        // The document itself takes precedence over the included file
+        assert_eq!(
+            resolved,
+            json!({
+                "$include": "db.json",
+                "name": "main",
+                "url": "postgres://db.local",
+                "port": 5432,
+                "title": "main",
+                "missing": "{/db/user}"
+            })
+        );
+
+        let broken = json!({ "$include": "absent.json", "a": "{/b}" });
+        assert!(matches!(
+            crate::resolve_json_with_includes(&broken, &base_dir),
+            Err(crate::ResolveReadError::Io(_))
+        ));
+        std::fs::write(base_dir.join("invalid.json"), "{ db").unwrap();
+        let invalid = json!({ "$include": "invalid.json" });
+        assert!(matches!(
+            crate::resolve_json_with_includes(&invalid, &base_dir),
+            Err(crate::ResolveReadError::Parse(_))
+        ));
+
+        // Files outside of the base directory are refused, even if they exist
+        let outside = base_dir.join("db.json");
+        let parent = format!(
+            "../{}/db.json",
+            base_dir.file_name().unwrap().to_str().unwrap()
+        );
+        for file in [outside.to_str().unwrap(), &parent] {
+            let escaping = json!({ "$include": file, "port": "{/db/port}" });
+            assert!(matches!(
+                crate::resolve_json_with_includes(&escaping, &base_dir),
+                Err(crate::ResolveReadError::Io(error)) if error.kind() == std::io::ErrorKind::InvalidInput
+            ));
+        }
+        let nested = json!({ "$include": "./db.json", "port": "{/db/port}" });
+        assert_eq!(
+            crate::resolve_json_with_includes(&nested, &base_dir).unwrap()["port"],
+            json!(5432)
+        );
+
+        std::fs::remove_dir_all(&base_dir).unwrap();
+    }
+
+    #[test]
+    fn test_resolve_top_level() {
+        let input = json!({
+            "env": { "name": "prod", "region": "eu" },
+            "app": { "title": "App ({/env/name})", "region": "{../env/region}" },
+            "tags": ["{/env/name}", "static"]
+        });
+        let sections = crate::resolve_top_level(&input);
+
+        assert_eq!(sections.len(), 3);
+        assert_eq!(sections["env"], json!({ "name": "prod", "region": "eu" }));
+        assert_eq!(
+            sections["app"],
+            json!({ "title": "App (prod)", "region": "eu" })
+        );
+        assert_eq!(sections["tags"], json!(["prod", "static"]));
+
+        let sections = crate::resolve_top_level(&json!(["{/1}", "x"]));
+        assert_eq!(
+            sections,
+            HashMap::from([(String::new(), json!(["x", "x"]))])
+        );
+    }
+
+    #[test]
+    fn test_stringify_scalars() {
+        let input = json!({
+            "env": { "port": 8080, "debug": true, "token": null },
+            "port": "{/env/port}",
+            "ratio": 0.5,
+            "flags": ["{/env/debug}", false],
+            "name": "app"
+        });
+        let options = crate::ResolveOptions {
+            stringify_scalars: true,
+            ..Default::default()
+        };
+
+        let resolved = crate::resolve_json_with_options(&input, &options).unwrap();
+        assert_eq!(
+            resolved,
+            json!({
+                "env": { "port": "8080", "debug": "true", "token": "null" },
+                "port": "8080",
+                "ratio": "0.5",
+                "flags": ["true", "false"],
+                "name": "app"
+            })
+        );
+    }
+
+    #[cfg(feature = "random")]
+    #[test]
+    fn test_resolve_with_rng_is_reproducible() {
+        let input = json!({
+            "users": [
+                { "id": "{random:uuid}", "age": "{random:int:1-100}" },
+                { "id": "{random:uuid}", "age": "{random:int:1-100}" }
+            ],
+            "label": "user-{random:uuid}",
+            "name": "fixture",
+            "title": "{name}"
+        });
+
+        let first = crate::resolve_json_with_rng(&input, 1);
+        assert_eq!(first, crate::resolve_json_with_rng(&input, 1));
+        assert_ne!(first, crate::resolve_json_with_rng(&input, 2));
+
+        assert_ne!(first["users"][0]["id"], first["users"][1]["id"]);
+        assert!(first["label"].as_str().unwrap().starts_with("user-"));
+        assert_eq!(first["title"], json!("fixture"));
+
+        // Without a seed the generators stay unresolved
+        assert_eq!(
+            crate::resolve_json(&input)["users"][0]["id"],
+            json!("{random:uuid}")
+        );
+    }
+
+    #[test]
+    fn test_resolve_size_capped() {
+        let items: Vec<u32> = (0..1000).collect();
+        let input = json!({
+            "items": items,
+            "copies": ["{/items}", "{/items}", "{/items}", "{/items}"],
+            "name": "{/title}",
+            "title": "x"
+        });
+        let input_len = serialized_len(&input);
+
+        let result = crate::resolve_json_size_capped(&input, input_len * 2);
+        assert_eq!(
+            result,
+            Err(crate::ResolveError::OutputTooLarge {
+                at: AbsolutePath::new("/copies/1"),
+                limit: input_len * 2,
+            })
+        );
+
+        let resolved = crate::resolve_json_size_capped(&input, input_len * 5).unwrap();
+        assert_eq!(resolved, crate::resolve_json(&input));
+        assert_eq!(
+            serialized_len(&resolved),
+            serde_json::to_string(&resolved).unwrap().len()
+        );
+        assert!(serialized_len(&resolved) <= input_len * 5);
+
+        // Only the output counts, even when the input is over the limit
+        let input = json!({
+            "value": "{/very/long/path/that/does/not/matter}",
+            "very": { "long": { "path": { "that": { "does": { "not": { "matter": 1 } } } } } }
+        });
+        let resolved_len = serialized_len(&crate::resolve_json(&input));
+        assert!(serialized_len(&input) > resolved_len + 10);
+        assert_eq!(
+            crate::resolve_json_size_capped(&input, resolved_len + 10),
+            Ok(crate::resolve_json(&input))
+        );
+        // Going over the limit on the way is fine when a later placeholder shrinks it back
+        let shrunk = json!({
+            "grow": "{/word}",
+            "shrink": "{/very/long/path/that/does/not/matter}",
+            "very": input["very"],
+            "word": "abcdefghijklmnopqrstuvwxyz"
+        });
+        let shrunk_len = serialized_len(&crate::resolve_json(&shrunk));
+        assert!(crate::resolve_json_size_capped(&shrunk, shrunk_len).is_ok());
+
+        assert_eq!(
+            crate::resolve_json_size_capped(&input, resolved_len - 1),
+            Err(crate::ResolveError::OutputTooLarge {
+                at: AbsolutePath::new("/value"),
+                limit: resolved_len - 1,
+            })
+        );
+    }
+
+    #[test]
+    fn test_source_covers() {
+        let template = json!({
+            "$optional": ["/cache/ttl"],
+            "db": { "host": "{/db/host}", "user": "{/db/user}" },
+            "ttl": "{/cache/ttl}",
+            "names": "{/users/*/name}",
+            "all": "{/}",
+            "greeting": "Hello {/user/first} {/user/last}",
+            "relative": "{db/host}"
+        });
+        let source = json!({ "db": { "host": "localhost" }, "user": { "first": "Bob" } });
+
+        assert_eq!(
+            crate::source_covers(&template, &source),
+            Err(vec![
+                AbsolutePath::new("/db/user"),
+                AbsolutePath::new("/user/last")
+            ])
+        );
+
+        let source =
+            json!({ "db": { "host": "h", "user": "u" }, "user": { "first": "B", "last": "L" } });
+        assert_eq!(crate::source_covers(&template, &source), Ok(()));
+    }
+
+    #[test]
+    fn test_resolve_with_extra_str() {
+        let input = json!({
+            "secret": { "kind": "bearer" },
+            "auth": "{/secret/kind} {/secret/token}",
+            "token": "{/secret/token}",
+            "kind": "{/secret/kind}"
+        });
+        let resolved = crate::resolve_json_with_extra_str(
+            &input,
+            &[
+                ("/secret/token", json!("abc")),
+                ("/secret/kind", json!("basic")),
+            ],
+        );
+
+        // The document's own `/secret/kind` wins over the extra one
+        assert_eq!(resolved["auth"], json!("bearer abc"));
+        assert_eq!(resolved["token"], json!("abc"));
+        assert_eq!(resolved["kind"], json!("bearer"));
+    }
+
+    #[test]
+    fn test_merge_arrays_by_key() {
+        let template = json!({
+            "users": [
+                { "id": 2, "role": "{/roles/admin}" },
+                { "id": 3, "name": "Carol" },
+                { "name": "no id" }
+            ],
+            "title": "{/title}"
+        });
+        let source = json!({
+            "roles": { "admin": "admin" },
+            "title": "Team",
+            "users": [
+                { "id": 1, "name": "Alice", "role": "user" },
+                { "id": 2, "name": "Bob", "role": "user" }
+            ]
+        });
+
+        let merged = crate::resolve_template_merge_arrays_by(&template, &source, "id");
+        assert_eq!(
+            merged,
+            json!({
+                "users": [
+                    { "id": 1, "name": "Alice", "role": "user" },
+                    { "id": 2, "name": "Bob", "role": "admin" },
+                    { "id": 3, "name": "Carol" },
+                    { "name": "no id" }
+                ],
+                "title": "Team"
+            })
+        );
+    }
+
+    #[test]
+    fn test_resolve_json_defaults() {
+        let input = json!({
+            "service": {
+                "config": r#"{limits|{"timeout":30,"retries":[1,2]}}"#,
+                "name": "{title|unnamed} ({/env|dev})"
+            },
+            "env": "prod"
+        });
+        let resolved = crate::resolve_json(&input);
+
+        assert_eq!(
+            resolved["service"],
+            json!({ "config": { "timeout": 30, "retries": [1, 2] }, "name": "unnamed (prod)" })
+        );
+    }
+
+    #[test]
+    fn test_resolve_json_bracket_indices() {
+        let input = json!({
+            "items": [{ "name": "first" }, { "name": "second" }],
+            "picked": { "second": "{/items[1]/name}", "first": "{../items[0]/name}" }
+        });
+        let options = crate::ResolveOptions {
+            index_style: crate::IndexStyle::Brackets,
+            ..Default::default()
+        };
+        let resolved = crate::resolve_json_with_options(&input, &options).unwrap();
+
+        assert_eq!(
+            resolved["picked"],
+            json!({ "second": "second", "first": "first" })
+        );
+        assert_eq!(
+            crate::resolve_json(&json!({ "items": [1, 2], "a": "{/items/1}" }))["a"],
+            json!(2)
+        );
+    }
+
+    #[test]
+    fn test_resolve_json_max_reference_len() {
+        let long_reference = format!("{{/{}}}", "a".repeat(100));
+        let input = json!({ "a": "value", "long": long_reference, "short": "{/a} {a}" });
+        let mut options = crate::ResolveOptions {
+            max_reference_len: 10,
+            ..Default::default()
+        };
+        let resolved = crate::resolve_json_with_options(&input, &options).unwrap();
+
+        assert_eq!(resolved["long"], json!(long_reference));
+        assert_eq!(resolved["short"], json!("value value"));
+
+        options.strict = true;
+        assert_eq!(
+            crate::resolve_json_with_options(&input, &options),
+            Err(crate::ResolveError::ReferenceTooLong {
+                at: AbsolutePath::new("/long"),
+                limit: 10
+            })
+        );
+    }
+
+    #[test]
+    fn test_resolved_leaves_sorted() {
+        let input = json!({
+            "server": { "host": "localhost", "port": "8080" },
+            "urls": ["http://{/server/host}:{/server/port}", "{/server/port}"],
+            "tags": {},
+            "x~y": null
+        });
+
+        assert_eq!(
+            crate::resolved_leaves_sorted(&input),
+            vec![
+                ("/server/host".to_string(), json!("localhost")),
+                ("/server/port".to_string(), json!("8080")),
+                ("/tags".to_string(), json!({})),
+                ("/urls/0".to_string(), json!("http://localhost:8080")),
+                ("/urls/1".to_string(), json!("8080")),
+                ("/x~0y".to_string(), Value::Null),
+            ]
+        );
+        assert_eq!(
+            crate::resolved_leaves_sorted(&json!(1)),
+            vec![(String::new(), json!(1))]
+        );
+    }
+
+    #[test]
+    fn test_resolve_json_with_originals() {
+        let input = json!({
+            "db": { "host": "localhost", "port": 5432 },
+            "port": "{db/port}",
+            "hosts": ["{/db/host}", "fixed", "{/db/user}"],
+            "dsn": "{/db/host}:5432",
+            "literal": "no placeholders"
+        });
+        let (resolved, originals) = crate::resolve_json_with_originals(&input);
+
+        assert_eq!(
+            resolved["hosts"],
+            json!(["localhost", "fixed", "{/db/user}"])
+        );
+        assert_eq!(
+            originals,
+            HashMap::from([
+                (AbsolutePath::new("/port"), "{db/port}".to_string()),
+                (AbsolutePath::new("/hosts/0"), "{/db/host}".to_string()),
+                (AbsolutePath::new("/dsn"), "{/db/host}:5432".to_string()),
+            ])
+        );
+
+        // Putting the originals back gives the template again
+        let mut template = resolved;
+        for (path, original) in originals {
+            *value_at_mut(&mut template, &path).unwrap() = Value::String(original);
+        }
+        assert_eq!(template, input);
+    }
+
+    #[test]
+    fn test_resolve_json_is_idempotent() {
+        let input = json!({
+            "name": "Bob",
+            "greeting": "Hi {name}",
+            "literal": "set {x} literally",
+            "empty": "{}",
+            "pattern": "[a-z]{2,3}",
+            "object": "{\"a\": 1}",
+            "nested": "{{name}}",
+            "copy": "{/pattern}"
+        });
+        let once = crate::resolve_json(&input);
+
+        assert_eq!(
+            once,
+            json!({
+                "name": "Bob",
+                "greeting": "Hi Bob",
+                "literal": "set {x} literally",
+                "empty": "{}",
+                "pattern": "[a-z]{2,3}",
+                "object": "{\"a\": 1}",
+                "nested": "{{name}}",
+                "copy": "[a-z]{2,3}"
+            })
+        );
+        assert_eq!(crate::resolve_json(&once), once);
+
+        let template = json!({ "who": "{/greeting}", "re": "{/pattern}", "keep": "{x}" });
+        assert_eq!(
+            crate::resolve_template_with_source(&template, &once),
+            json!({ "who": "Hi Bob", "re": "[a-z]{2,3}", "keep": "{x}" })
+        );
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn test_resolve_json_with_now() {
+        use chrono::{FixedOffset, TimeZone};
+
+        let input = json!({
+            "build": { "date": "{now:%Y-%m-%d}", "time": "{now:%H:%M:%S}" },
+            "label": "release-{now:%Y%m%d}-{/build/date}",
+            "invalid": "{now:%Q}"
+        });
+        let now = FixedOffset::east_opt(3 * 3600)
+            .unwrap()
+            .with_ymd_and_hms(2025, 12, 31, 23, 59, 58)
+            .unwrap();
+
+        assert_eq!(
+            crate::resolve_json_with_now(&input, now),
+            json!({
+                "build": { "date": "2025-12-31", "time": "23:59:58" },
+                "label": "release-20251231-{now:%Y-%m-%d}",
+                "invalid": "{now:%Q}"
+            })
+        );
+        // Without a time the placeholders stay unresolved
+        assert_eq!(
+            crate::resolve_json(&input)["build"]["date"],
+            json!("{now:%Y-%m-%d}")
+        );
+    }
+
+    #[test]
+    fn test_resolve_json_conditional_keys() {
+        let input = json!({
+            "env": { "port": 8080, "tags": [], "host": null },
+            "server": {
+                "port?": "{/env/port}",
+                "tags?": "{/env/tags}",
+                "host?": "{/env/host}",
+                "user?": "{/env/user}",
+                "url?": "http://{/env/domain}/",
+                "name?": "fixed"
+            }
+        });
+
+        let expected = json!({ "port": 8080, "name": "fixed" });
+        assert_eq!(crate::resolve_json(&input)["server"], expected);
+
+        let strict = crate::ResolveOptions {
+            strict: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            crate::resolve_json_with_options(&input, &strict).unwrap()["server"],
+            expected
+        );
+    }
+
+    #[test]
+    fn test_resolve_json_sparse() {
+        let input = json!({
+            "static": { "list": [1, [2, 3], { "a": "text" }], "empty": {} },
+            "mixed": [{ "fixed": 1 }, { "ref": "{/static/list/1}" }, "{/literal}"],
+            "conditional": { "nested": { "kept?": "plain" } },
+            "literal": "{}"
+        });
+        let resolved = crate::resolve_json_sparse(&input);
+
+        assert_eq!(resolved, crate::resolve_json(&input));
+        assert_eq!(
+            resolved["mixed"],
+            json!([{ "fixed": 1 }, { "ref": [2, 3] }, "{}"])
+        );
+        assert_eq!(
+            resolved["conditional"],
+            json!({ "nested": { "kept": "plain" } })
+        );
+
+        // Cyclic placeholders stay as written in both, next to static branches
+        let input = json!({
+            "static": { "list": [1, 2] },
+            "cycle": { "a": "{b}", "b": "{/cycle/a}", "self": "{self}" },
+            "into": "{/cycle/a}!",
+            "first": "{/static/list/0}"
+        });
+        let resolved = crate::resolve_json_sparse(&input);
+        assert_eq!(resolved, crate::resolve_json(&input));
+        assert_eq!(resolved["cycle"], input["cycle"]);
+    }
+
+    #[test]
+    fn test_dependency_dot() {
+        let input = json!({
+            "a": "{/b}",
+            "b": "{a}",
+            "c": { "d": "{../a} {/self \"quoted\"}", "e": "{e}" },
+            "f": "no references"
+        });
+        let dot = crate::dependency_dot(&input);
+        let lines: Vec<&str> = dot.lines().collect();
+
+        assert_eq!(lines.first(), Some(&"digraph dependencies {"));
+        assert_eq!(lines.last(), Some(&"}"));
+        assert!(lines.contains(&r#"    "/a" -> "/b";"#));
+        assert!(lines.contains(&r#"    "/b" -> "/a";"#));
+        assert!(lines.contains(&r#"    "/c/d" -> "/a";"#));
+        assert!(lines.contains(&r#"    "/c/d" -> "/self \"quoted\"";"#));
+        assert!(lines.contains(&r#"    "/c/e" -> "/c/e";"#));
+        assert_eq!(lines.len(), 7);
+        assert_eq!(
+            crate::dependency_dot(&json!({ "a": 1 })),
+            "digraph dependencies {\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_collect_dependencies() {
+        let input = json!({
+            "a": "{/b} and {b}",
+            "list": ["{../a}", { "x": "{y} {/missing|default} {@db:/host}" }, "{}"],
+            "b": "plain",
+            "c": "{concat:/a,\" \",/b}"
+        });
+        let path = AbsolutePath::new;
+        assert_eq!(
+            crate::collect_dependencies(&input),
+            HashMap::from([
+                (path("/a"), vec![path("/b")]),
+                (path("/list/0"), vec![path("/a")]),
+                (path("/list/1/x"), vec![path("/list/1/y"), path("/missing")]),
+                (path("/c"), vec![path("/a"), path("/b")]),
+            ])
+        );
+        assert!(crate::collect_dependencies(&json!({ "a": 1 })).is_empty());
+    }
+
+    #[test]
+    fn test_find_reference_cycles() {
+        let input = json!({
+            "a": "{/b}",
+            "b": "{/c} and {/a}",
+            "c": "{/a}",
+            "nested": { "inner": "{/nested}" },
+            "self": "{self}",
+            "plain": "{/a}",
+            "chain": ["{/chain/1}", "{/chain/2}", "end"]
+        });
+        let path = AbsolutePath::new;
+        assert_eq!(
+            crate::find_reference_cycles(&input),
+            vec![
+                vec![path("/a"), path("/b")],
+                vec![path("/a"), path("/b"), path("/c")],
+                vec![path("/nested/inner")],
+                vec![path("/self")],
+            ]
+        );
+        assert_eq!(
+            crate::find_reference_cycles(&json!({ "a": "{/b}", "b": "x" })),
+            Vec::<Vec<AbsolutePath>>::new()
+        );
+
+        // Placeholders closing a cycle stay as written, the others are resolved
+        let input = json!({ "a": "{/b}", "b": "{/a} {/c}", "c": "x" });
+        assert_eq!(
+            crate::resolve_json(&input),
+            json!({ "a": "{/b}", "b": "{/a} x", "c": "x" })
+        );
+        let strict = crate::ResolveOptions {
+            strict: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            crate::resolve_json_with_options(&input, &strict),
+            Err(crate::ResolveError::Cycle {
+                at: path("/a"),
+                reference: "/b".to_string(),
+                chain: vec![path("/a"), path("/b")],
+            })
+        );
+    }
+
+    #[test]
+    fn test_entry_points_leave_cycles() {
+        let input = json!({ "a": "{/b}", "b": "{/a}", "c": "{/c}!" });
+        let expected = crate::resolve_json(&input);
+        assert_eq!(expected, input);
+
+        assert_eq!(crate::resolve_json_sparse(&input), expected);
+        assert_eq!(crate::resolve_json_with_normalized(&input).1, expected);
+        assert_eq!(
+            crate::resolve_json_size_capped(&input, 1000),
+            Ok(expected.clone())
+        );
+        assert_eq!(
+            crate::resolve_json_with_extra(&input, &HashMap::new()),
+            expected
+        );
+        assert_eq!(crate::resolve_json_with_extra_str(&input, &[]), expected);
+        let mut in_place = input.clone();
+        crate::resolve_json_in_place(&mut in_place);
+        assert_eq!(in_place, expected);
+        #[cfg(feature = "std")]
+        assert_eq!(
+            crate::resolve_json_with_includes(&input, &std::env::temp_dir()).unwrap(),
+            expected
+        );
+        #[cfg(feature = "random")]
+        assert_eq!(crate::resolve_json_with_rng(&input, 7), expected);
+        #[cfg(feature = "chrono")]
+        assert_eq!(
+            crate::resolve_json_with_now(&input, chrono::DateTime::UNIX_EPOCH),
+            expected
+        );
+
+        let error = crate::resolve_json_checked(&input).unwrap_err();
+        assert_eq!(
+            error[0].cycle,
+            Some(vec![AbsolutePath::new("/a"), AbsolutePath::new("/b")])
+        );
+        let strict = crate::ResolveOptions {
+            strict: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            crate::resolve_json_with_options(&input, &strict)
+                .unwrap_err()
+                .to_string(),
+            "reference {/b} at /a is part of a cycle: /a -> /b -> /a"
+        );
+    }
+
+    #[test]
+    fn test_resolve_json_recursive() {
+        let input = json!({
+            "a": "{/b}",
+            "b": "{/c}",
+            "c": "final",
+            "layers": { "base": "{../a}!", "top": "[{base}]" },
+            "loop": "{/loop}"
+        });
+        assert_eq!(crate::resolve_json(&input)["a"], json!("{/c}"));
+        assert_eq!(
+            crate::resolve_json_recursive(&input),
+            json!({
+                "a": "final",
+                "b": "final",
+                "c": "final",
+                "layers": { "base": "final!", "top": "[final!]" },
+                "loop": "{/loop}"
+            })
+        );
+
+        let chain: Map<String, Value> = (0..100)
+            .map(|i| (format!("k{i:03}"), json!(format!("{{/k{:03}}}", i + 1))))
+            .chain([("k100".to_string(), json!("end"))])
+            .collect();
+        let resolved = crate::resolve_json_recursive(&Value::Object(chain));
+        assert!(
+            resolved
+                .as_object()
+                .unwrap()
+                .values()
+                .all(|value| value == "end")
+        );
+
+        // A field referring into a cycle never settles, the passes stop anyway
+        let input = json!({ "a": "{/b}", "b": "{/a}", "c": "{/a}!" });
+        let resolved = crate::resolve_json_recursive(&input);
+        assert_eq!((&resolved["a"], &resolved["b"]), (&input["a"], &input["b"]));
+        assert!(resolved["c"] == "{/a}!" || resolved["c"] == "{/b}!");
+    }
+
+    #[test]
+    fn test_resolve_json_in_place() {
+        let inputs = [
+            json!({ "a": "{/b}", "b": "{/c}", "c": 1, "text": "{a} and {c}" }),
+            json!({
+                "base": { "host": "localhost", "port": 80 },
+                "server": { "port": 8080, "rest": "{spread:/base}" },
+                "url?": "http://{/server/host}:{/server/port}/",
+                "proxy?": "{/missing}",
+                "items": [{ "name": "{$key}" }, "{../0/name}", "{/base/port}"]
+            }),
+            json!({
+                "users": [{ "name": "ann", "role": "admin" }, { "name": "bob", "role": "dev" }],
+                "list": "{#each /users}{.name}={.role}, {/each}",
+                "layers": { "base": "{../a}!", "top": "[{base}]" },
+                "a": "\\{not} {/b}",
+                "b": "text"
+            }),
+            json!({ "a": "{/b}", "b": "{/a}", "c": "{/a}!", "d": "{/c}" }),
+            json!({
+                "$optional": ["/overrides/port"],
+                "place": { "$fields": ["lat", "lng"], "point": ["{/lat}", 13.4] },
+                "lat": 52.5,
+                "lng": "{/place/point/lng}",
+                "first": "{/place/point/lat}",
+                "port": "{/overrides/port}"
+            }),
+        ];
+        for input in inputs {
+            let mut resolved = input.clone();
+            crate::resolve_json_in_place(&mut resolved);
+            assert_eq!(resolved, crate::resolve_json(&input), "{input}");
+        }
+    }
+
+    #[test]
+    fn test_resolve_json_wide_object() {
+        let input: Map<String, Value> = (0..1000)
+            .map(|i| match i % 2 {
+                0 => (format!("key{i}"), json!(i)),
+                _ => (format!("key{i}"), json!(format!("{{/key{}}}", i - 1))),
+            })
+            .collect();
+        let expected: Map<String, Value> = (0..1000)
+            .map(|i| (format!("key{i}"), json!(i - i % 2)))
+            .collect();
+
+        assert_eq!(
+            crate::resolve_json(&Value::Object(input)),
+            Value::Object(expected)
+        );
+    }
+
+    #[test]
+    fn test_assert_fully_resolved() {
+        let input = json!({
+            "host": "localhost",
+            "url": "http://{/host}/",
+            "braces": "{} and {}",
+            "list": ["{/missing}", "ok", { "deep": "x {$key} {/other}" }]
+        });
+        assert_eq!(
+            crate::assert_fully_resolved(&input),
+            Err(vec![
+                AbsolutePath::new("/list/0"),
+                AbsolutePath::new("/list/2/deep"),
+                AbsolutePath::new("/url"),
+            ])
+        );
+
+        let resolved = crate::resolve_json(&input);
+        assert_eq!(
+            crate::assert_fully_resolved(&resolved),
+            Err(vec![
+                AbsolutePath::new("/list/0"),
+                AbsolutePath::new("/list/2/deep")
+            ])
+        );
+
+        let complete = json!({ "host": "localhost", "url": "{/host}", "braces": "{}" });
+        assert_eq!(
+            crate::assert_fully_resolved(&crate::resolve_json(&complete)),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_measure_depth() {
+        assert_eq!(crate::measure_depth(&json!("{/a}")), 0);
+        assert_eq!(crate::measure_depth(&json!({})), 1);
+        assert_eq!(crate::measure_depth(&json!({ "a": 1, "b": "x" })), 1);
+
+        let nested = json!({ "a": 1, "b": { "c": { "d": true } }, "e": {} });
+        assert_eq!(crate::measure_depth(&nested), 3);
+
+        let array = json!([1, [2, [3, []]], [4]]);
+        assert_eq!(crate::measure_depth(&array), 4);
+    }
+
+    #[test]
+    fn test_reader_errors_by_cause() {
+        use crate::{ResolveError, ResolveOptions, ResolveReadError};
+        use std::collections::BTreeMap;
+
+        type Config = BTreeMap<String, String>;
+        let read = |text: &str, options: &ResolveOptions| {
+            crate::resolve_json_reader_to_object_with_options::<_, Config>(text.as_bytes(), options)
+        };
+        let strict = ResolveOptions {
+            strict: true,
+            ..Default::default()
+        };
+
+        let config = read(r#"{ "a": "x", "b": "{/a}" }"#, &strict).unwrap();
+        assert_eq!(config["b"], "x");
+
+        assert!(matches!(
+            read(r#"{ "a": "#, &strict),
+            Err(ResolveReadError::Parse(_))
+        ));
+        assert!(matches!(
+            read(r#"{ "a": "{/missing}" }"#, &strict),
+            Err(ResolveReadError::Resolve(ResolveError::Unresolved { .. }))
+        ));
+        // Leniently the placeholder stays as it is
+        assert!(read(r#"{ "a": "{/missing}" }"#, &Default::default()).is_ok());
+        // A number isn't a string
+        assert!(matches!(
+            read(r#"{ "a": 1 }"#, &strict),
+            Err(ResolveReadError::Deserialize(_))
+        ));
+    }
+
+    #[test]
+    fn test_source_root() {
+        let source = json!({
+            "app": { "db": { "host": "db.local", "ports": [5432] }, "name": "shop" },
+            "db": { "host": "outside" }
+        });
+        let template = json!({
+            "host": "{/db/host}",
+            "port": "{/db/ports/0}",
+            "all": "{/}",
+            "local": { "name": "Ann", "greeting": "Hi {name}" },
+            "missing": "{/app/name}"
+        });
+        let options = crate::ResolveOptions {
+            source_root: AbsolutePath::new("/app"),
+            ..Default::default()
+        };
+
+        // Relative paths are not moved beneath the root
+        let resolved = crate::resolve_template_with_options(&template, &source, &options).unwrap();
+        assert_eq!(resolved["host"], json!("db.local"));
+        assert_eq!(resolved["port"], json!(5432));
+        assert_eq!(resolved["all"], source["app"]);
+        assert_eq!(resolved["local"]["greeting"], json!("Hi {name}"));
+        assert_eq!(resolved["missing"], json!("{/app/name}"));
+
+        let applied = crate::CompiledTemplate::new(&template)
+            .apply_with_options(&source, &options)
+            .unwrap();
+        assert_eq!(applied, resolved);
+
+        // The default root reads the source as it is
+        let resolved = crate::resolve_template_with_source(&template, &source);
+        assert_eq!(resolved["host"], json!("outside"));
+    }
+
+    #[cfg(feature = "schema")]
+    #[test]
+    fn test_resolve_with_schema_defaults() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "host": { "type": "string", "default": "localhost" },
+                "port": { "type": "integer", "default": 80 },
+                "url": { "type": "string" },
+                "db": {
+                    "type": "object",
+                    "properties": {
+                        "pool": { "type": "integer", "default": 4 },
+                        "user": { "type": "string", "default": "admin" }
+                    }
+                },
+                "cache": { "type": "object", "properties": { "ttl": { "default": 60 } } }
+            }
+        });
+        let input = json!({
+            "host": "example.com",
+            "port?": "{/port_override}",
+            "url": "http://{/host}/",
+            "db": { "user": "{/host}" }
+        });
+
+        // The conditional port is dropped by resolution, then defaulted
+        let resolved = crate::resolve_with_schema_defaults(&input, &schema);
+        assert_eq!(
+            resolved,
+            json!({
+                "host": "example.com",
+                "port": 80,
+                "url": "http://example.com/",
+                "db": { "pool": 4, "user": "example.com" }
+            })
+        );
+
+        let resolved = crate::resolve_with_schema_defaults(&json!({}), &schema);
+        assert_eq!(resolved, json!({ "host": "localhost", "port": 80 }));
+    }
 }
@@ -1,3 +1,10 @@
+use serde_json::Value;
+use std::{borrow::Cow, fmt, sync::LazyLock};
+
+/// A path from the document root, like `/config/level1/key1`.
+///
+/// Keys holding `/` or `~` are kept escaped as in JSON pointers, `~1` and `~0`,
+/// so the key `"a/b"` is a different path from the key `"b"` nested in `"a"`.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct AbsolutePath(String);
 
@@ -11,9 +18,101 @@ impl AbsolutePath {
         AbsolutePath(normalized_path)
     }
 
-    /// Normalize the absolute path (remove extra slashes)
+    /// Normalize the absolute path (remove extra slashes, unquote quoted segments)
     fn normalize(path: &str) -> String {
-        format!("/{}", path.trim_start_matches('/').trim_end_matches('/'))
+        let path = path.trim_start_matches('/').trim_end_matches('/');
+        if !path.contains(QUOTE_START) && !path.contains('~') {
+            return format!("/{path}");
+        }
+
+        let segments: Vec<Cow<str>> = split_segments(path)
+            .into_iter()
+            .map(|(segment, _)| escape_pointer_segment(&segment).into_owned().into())
+            .collect();
+        format!("/{}", segments.join("/"))
+    }
+
+    /// The path as it would be written in a placeholder.
+    /// Segments that would be misread, like keys with surrounding spaces, a `:` or a `/`,
+    /// are quoted.
+    pub(crate) fn to_reference(&self) -> String {
+        let segments: Vec<Cow<str>> = self.0.split('/').map(reference_segment).collect();
+        segments.join("/")
+    }
+
+    /// Same as [`AbsolutePath::to_reference`], with `/` and `~` in unquoted keys escaped
+    /// as `~1` and `~0`
+    pub(crate) fn to_pointer_reference(&self) -> String {
+        let segments: Vec<Cow<str>> = self
+            .0
+            .split('/')
+            .map(|segment| {
+                let key = unescape_pointer_segment(segment);
+                match needs_quotes(&key) {
+                    true => Cow::Owned(quote(&key)),
+                    false => Cow::Borrowed(segment),
+                }
+            })
+            .collect();
+        segments.join("/")
+    }
+
+    /// Reads an RFC 6901 JSON pointer, like `/a~1b/m~0n`, in which `~1` stands for `/`
+    /// and `~0` for `~` within keys. The empty pointer is the root.
+    ///
+    /// ## Example:
+    /// ```
+    /// use json_deref::AbsolutePath;
+    ///
+    /// let path = AbsolutePath::from_pointer("/files/a~1b");
+    /// assert_ne!(path, AbsolutePath::new("/files/a/b"));
+    /// assert_eq!(path, AbsolutePath::new(r#"/files/["a/b"]"#));
+    /// assert_eq!(AbsolutePath::from_pointer(""), AbsolutePath::new("/"));
+    /// ```
+    pub fn from_pointer(pointer: &str) -> Self {
+        let segments: Vec<Cow<str>> = pointer
+            .split('/')
+            .filter(|segment| !segment.is_empty())
+            .map(|segment| escape_pointer_segment(&unescape_pointer_segment(segment)).into_owned())
+            .map(Cow::Owned)
+            .collect();
+        AbsolutePath(format!("/{}", segments.join("/")))
+    }
+
+    /// Writes the path as an RFC 6901 JSON pointer, the inverse of
+    /// [`AbsolutePath::from_pointer`]. The root is the empty pointer.
+    ///
+    /// ## Example:
+    /// ```
+    /// use json_deref::AbsolutePath;
+    ///
+    /// assert_eq!(AbsolutePath::new(r#"/files/["m~n/x"]/0"#).to_pointer(), "/files/m~0n~1x/0");
+    /// assert_eq!(AbsolutePath::new("/").to_pointer(), "");
+    /// ```
+    pub fn to_pointer(&self) -> String {
+        self.0
+            .split('/')
+            .filter(|segment| !segment.is_empty())
+            .map(|segment| format!("/{segment}"))
+            .collect()
+    }
+
+    /// Same as [`AbsolutePath::to_reference`], with all-digit segments written as `[3]` indices
+    pub(crate) fn to_reference_with_brackets(&self) -> String {
+        let mut written = String::with_capacity(self.0.len());
+        for (i, segment) in self.0.split('/').enumerate() {
+            let is_index = !segment.is_empty() && segment.bytes().all(|byte| byte.is_ascii_digit());
+            match i {
+                0 => {}
+                // An index right at the root has nothing to attach to
+                _ if is_index && i > 1 => written.push_str(&format!("[{segment}]")),
+                _ => {
+                    written.push('/');
+                    written.push_str(&reference_segment(segment));
+                }
+            }
+        }
+        written
     }
 
     /// Get the internal string
@@ -21,34 +120,112 @@ impl AbsolutePath {
         &self.0
     }
 
+    /// Path of `path` within the source registered under `namespace`.
+    /// Namespaces live in their own top-level segment, so `@db:/host` becomes `/@db/host`.
+    pub(crate) fn namespaced(namespace: &str, path: &str) -> Self {
+        Self::new(&format!("@{namespace}{path}"))
+    }
+
     /// Combine the current absolute path with a relative one
     pub fn resolve_with(&self, relative_path: &RelativePath) -> AbsolutePath {
-        let mut base_parts: Vec<&str> = self.0.split('/').filter(|part| !part.is_empty()).collect();
+        self.join_relative(relative_path).0
+    }
+
+    /// Combine the current absolute path with a relative one.
+    /// Returns None if the relative path climbs above the document root.
+    pub fn try_resolve_with(&self, relative_path: &RelativePath) -> Option<AbsolutePath> {
+        match self.join_relative(relative_path) {
+            (path, false) => Some(path),
+            (_, true) => None,
+        }
+    }
+
+    /// Joins the paths, clamping at the root.
+    /// The flag tells whether clamping was necessary.
+    fn join_relative(&self, relative_path: &RelativePath) -> (AbsolutePath, bool) {
+        let mut base_parts: Vec<Cow<str>> = self
+            .0
+            .split('/')
+            .filter(|part| !part.is_empty())
+            .map(Cow::Borrowed)
+            .collect();
+        let mut escapes_root = false;
 
         // Remove the current file component (if it's not the root)
         base_parts.pop();
 
-        for segment in relative_path.0.split('/') {
-            match segment {
+        for (segment, quoted) in split_segments(&relative_path.0) {
+            match segment.as_ref() {
+                // Quoted segments are always keys, even `[".."]`
+                _ if quoted => {
+                    base_parts.push(escape_pointer_segment(&segment).into_owned().into())
+                }
                 ".." => {
-                    base_parts.pop();
+                    escapes_root |= base_parts.pop().is_none();
                 }
                 "" => { /* Skip empty segments */ }
-                _ => base_parts.push(segment),
+                _ => base_parts.push(escape_pointer_segment(&segment).into_owned().into()),
             }
         }
 
-        AbsolutePath(format!("/{}", base_parts.join("/")))
+        (
+            AbsolutePath(format!("/{}", base_parts.join("/"))),
+            escapes_root,
+        )
+    }
+
+    /// Whether `other` is this path or lies somewhere beneath it
+    pub fn contains(&self, other: &AbsolutePath) -> bool {
+        self.0 == "/"
+            || other.0 == self.0
+            || (other.0.starts_with(&self.0) && other.0[self.0.len()..].starts_with('/'))
     }
 
-    pub fn append(&self, path: &str) -> AbsolutePath {
+    /// The keys and array indices leading from the root to this path
+    pub(crate) fn segments(&self) -> impl Iterator<Item = Cow<'_, str>> {
+        self.0
+            .split('/')
+            .filter(|segment| !segment.is_empty())
+            .map(unescape_pointer_segment)
+    }
+
+    /// Path of the value under `key` in the object at this path. Slashes are part of the key.
+    pub fn append(&self, key: &str) -> AbsolutePath {
         let result = format!(
             "{}/{}",
             self.0.trim_end_matches('/'),
-            path.trim_start_matches('/').trim_end_matches('/')
+            escape_pointer_segment(key)
         );
         AbsolutePath(result)
     }
+
+    /// Path of `path` taken from this path instead of the root
+    pub(crate) fn join(&self, path: &AbsolutePath) -> AbsolutePath {
+        AbsolutePath(format!("{}{}", self.0.trim_end_matches('/'), path.0))
+    }
+
+    /// Same as `append(&index.to_string())`, reusing the text of small indices
+    pub(crate) fn append_index(&self, index: usize) -> AbsolutePath {
+        let segment = index_segment(index);
+        let base = self.0.trim_end_matches('/');
+        let mut path = String::with_capacity(base.len() + 1 + segment.len());
+        path.push_str(base);
+        path.push('/');
+        path.push_str(&segment);
+        AbsolutePath(path)
+    }
+}
+
+/// Array indices below this are rendered once and reused by every traversal
+const CACHED_INDICES: usize = 1024;
+
+fn index_segment(index: usize) -> Cow<'static, str> {
+    static CACHE: LazyLock<Vec<String>> =
+        LazyLock::new(|| (0..CACHED_INDICES).map(|index| index.to_string()).collect());
+    match CACHE.get(index) {
+        Some(segment) => Cow::Borrowed(segment),
+        None => Cow::Owned(index.to_string()),
+    }
 }
 
 impl Default for AbsolutePath {
@@ -57,17 +234,198 @@ impl Default for AbsolutePath {
     }
 }
 
+impl fmt::Display for AbsolutePath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
 impl RelativePath {
     /// Create a new relative path from a string
     pub fn new(path: &str) -> Self {
         RelativePath(path.to_string())
     }
+
+    /// Get the internal string
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+/// Opens a quoted segment, like `["  spaced  "]`
+const QUOTE_START: &str = "[\"";
+
+/// Splits a path on slashes. A segment written as a JSON string in brackets, like `["a b"]`,
+/// is unquoted and taken literally, so it may contain spaces, slashes or `..`.
+/// The flag tells whether the segment was quoted.
+fn split_segments(path: &str) -> Vec<(Cow<'_, str>, bool)> {
+    let mut segments = Vec::new();
+    let mut rest = path;
+    loop {
+        if let Some((key, tail)) = parse_quoted_segment(rest) {
+            segments.push((Cow::Owned(key), true));
+            match tail.strip_prefix('/') {
+                Some(tail) => rest = tail,
+                None => return segments,
+            }
+            continue;
+        }
+
+        match rest.split_once('/') {
+            Some((segment, tail)) => {
+                segments.push((Cow::Borrowed(segment), false));
+                rest = tail;
+            }
+            None => {
+                segments.push((Cow::Borrowed(rest), false));
+                return segments;
+            }
+        }
+    }
+}
+
+/// Parses a quoted segment at the start of `text`, returning the key and what follows it.
+/// The segment must be followed by a slash or the end of the path.
+fn parse_quoted_segment(text: &str) -> Option<(String, &str)> {
+    let quoted = text.strip_prefix('[')?;
+    if !quoted.starts_with('"') {
+        return None;
+    }
+
+    let mut stream = serde_json::Deserializer::from_str(quoted).into_iter::<String>();
+    let key = stream.next()?.ok()?;
+    let tail = quoted[stream.byte_offset()..].strip_prefix(']')?;
+    (tail.is_empty() || tail.starts_with('/')).then_some((key, tail))
+}
+
+/// Rewrites the keys of a placeholder path holding JSON pointer escapes as quoted segments,
+/// so `/a~1b/c` becomes `/["a/b"]/c`. Quoted segments are kept as they are.
+pub(crate) fn unescape_pointer(path: &str) -> Cow<'_, str> {
+    if !path.contains('~') {
+        return Cow::Borrowed(path);
+    }
+
+    let segments: Vec<Cow<str>> = split_segments(path)
+        .into_iter()
+        .map(|(segment, quoted)| match (quoted, segment.contains('~')) {
+            (true, _) => Cow::Owned(quote(&segment)),
+            (false, true) => Cow::Owned(quote(&unescape_pointer_segment(&segment))),
+            (false, false) => segment,
+        })
+        .collect();
+    Cow::Owned(segments.join("/"))
+}
+
+fn unescape_pointer_segment(segment: &str) -> Cow<'_, str> {
+    match segment.contains('~') {
+        true => Cow::Owned(segment.replace("~1", "/").replace("~0", "~")),
+        false => Cow::Borrowed(segment),
+    }
+}
+
+fn escape_pointer_segment(segment: &str) -> Cow<'_, str> {
+    match segment.contains(['~', '/']) {
+        true => Cow::Owned(segment.replace('~', "~0").replace('/', "~1")),
+        false => Cow::Borrowed(segment),
+    }
+}
+
+/// Rewrites `[3]` indices in a placeholder path as `/3` segments, e.g. `items[0]/name`
+/// as `items/0/name`. Quoted segments are kept as they are.
+pub(crate) fn slash_indices(path: &str) -> Cow<'_, str> {
+    if !path.contains('[') {
+        return Cow::Borrowed(path);
+    }
+
+    let mut rewritten = String::with_capacity(path.len() + 1);
+    let mut rest = path;
+    while let Some(open) = rest.find('[') {
+        let (before, from_bracket) = rest.split_at(open);
+        rewritten.push_str(before);
+        if let Some((_, tail)) = parse_quoted_segment(from_bracket) {
+            // Copy the quoted segment verbatim
+            let end = from_bracket.len() - tail.len();
+            rewritten.push_str(&from_bracket[..end]);
+            rest = tail;
+            continue;
+        }
+        let digits = from_bracket[1..]
+            .find(|c: char| !c.is_ascii_digit())
+            .map_or(from_bracket.len() - 1, |len| len);
+        if digits > 0 && from_bracket[1 + digits..].starts_with(']') {
+            if !rewritten.is_empty() && !rewritten.ends_with('/') {
+                rewritten.push('/');
+            }
+            rewritten.push_str(&from_bracket[1..1 + digits]);
+            rest = &from_bracket[digits + 2..];
+        } else {
+            rewritten.push('[');
+            rest = &from_bracket[1..];
+        }
+    }
+    rewritten.push_str(rest);
+    Cow::Owned(rewritten)
+}
+
+/// A segment of the internal string as written in a placeholder, quoted if it would be misread
+fn reference_segment(segment: &str) -> Cow<'_, str> {
+    let key = unescape_pointer_segment(segment);
+    match needs_quotes(&key) || key.contains('/') {
+        true => Cow::Owned(quote(&key)),
+        false => key,
+    }
+}
+
+/// Whether a key must be quoted to be read back as the same key from a placeholder
+fn needs_quotes(segment: &str) -> bool {
+    segment.trim() != segment
+        || segment.starts_with(QUOTE_START)
+        || segment.ends_with('!')
+        || segment.contains([':', '|', ',', '{', '}'])
+}
+
+fn quote(segment: &str) -> String {
+    format!("[{}]", Value::String(segment.to_string()))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_json_pointers() {
+        let path = AbsolutePath::from_pointer("/m~0n/a~1b/~01");
+        assert_eq!(
+            path.segments().collect::<Vec<_>>(),
+            vec!["m~n", "a/b", "~1"]
+        );
+        assert_eq!(path.to_pointer(), "/m~0n/a~1b/~01");
+        assert_eq!(AbsolutePath::from_pointer("").to_pointer(), "");
+
+        // A key holding a slash isn't the same as nested keys
+        let slashed = AbsolutePath::from_pointer("/a~1b");
+        assert_ne!(slashed, AbsolutePath::from_pointer("/a/b"));
+        assert_eq!(slashed, AbsolutePath::new("/").append("a/b"));
+        assert_eq!(slashed, AbsolutePath::new(r#"/["a/b"]"#));
+        assert_eq!(slashed.to_pointer(), "/a~1b");
+        assert_eq!(slashed.to_reference(), r#"/["a/b"]"#);
+        assert_eq!(AbsolutePath::new(&slashed.to_reference()), slashed);
+        assert!(!AbsolutePath::new("/a").contains(&slashed));
+
+        assert_eq!(unescape_pointer("/plain/0"), "/plain/0");
+        assert_eq!(unescape_pointer("../a~1b/c"), r#"../["a/b"]/c"#);
+        assert_eq!(unescape_pointer(r#"/["x~1"]/m~0n"#), r#"/["x~1"]/["m~n"]"#);
+        assert_eq!(
+            AbsolutePath::new(&unescape_pointer("/a~1b/m~0n")),
+            AbsolutePath::from_pointer("/a~1b/m~0n")
+        );
+        assert_eq!(
+            AbsolutePath::new("/m~n/a:b").to_pointer_reference(),
+            r#"/m~0n/["a:b"]"#
+        );
+        assert_eq!(slashed.to_pointer_reference(), "/a~1b");
+    }
+
     #[test]
     fn test_absolute_path_creation() {
         let abs_path = AbsolutePath::new("/some/nested/path/");
@@ -103,4 +461,91 @@ mod tests {
         let resolved = base.resolve_with(&relative);
         assert_eq!(resolved.as_str(), "/nested/path");
     }
+
+    #[test]
+    fn test_try_resolve_within_root() {
+        let base = AbsolutePath::new("/a/b/c");
+        let relative = RelativePath::new("../../d");
+        let resolved = base.try_resolve_with(&relative);
+        assert_eq!(resolved, Some(AbsolutePath::new("/d")));
+    }
+
+    #[test]
+    fn test_try_resolve_above_root() {
+        let base = AbsolutePath::new("/a/b");
+        let relative = RelativePath::new("../../x");
+        assert_eq!(base.try_resolve_with(&relative), None);
+        // The lenient version clamps at the root
+        assert_eq!(base.resolve_with(&relative).as_str(), "/x");
+    }
+
+    #[test]
+    fn test_contains() {
+        let base = AbsolutePath::new("/a/b");
+        assert!(base.contains(&AbsolutePath::new("/a/b")));
+        assert!(base.contains(&AbsolutePath::new("/a/b/c")));
+        assert!(!base.contains(&AbsolutePath::new("/a/bc")));
+        assert!(!base.contains(&AbsolutePath::new("/a")));
+        assert!(AbsolutePath::new("/").contains(&base));
+    }
+
+    #[test]
+    fn test_quoted_segments() {
+        assert_eq!(
+            AbsolutePath::new(r#"/["  spaced  "]/["   "]"#).as_str(),
+            "/  spaced  /   "
+        );
+        // A slash in a quoted key doesn't start another segment
+        assert_eq!(
+            AbsolutePath::new(r#"/a/["b/c"]/["d"]"#).as_str(),
+            "/a/b~1c/d"
+        );
+        // Unterminated quotes are kept as they are
+        assert_eq!(AbsolutePath::new(r#"/["open"#).as_str(), r#"/["open"#);
+
+        let base = AbsolutePath::new("/a/b");
+        assert_eq!(
+            base.resolve_with(&RelativePath::new(r#"["..  "]/x"#))
+                .as_str(),
+            "/a/..  /x"
+        );
+        assert_eq!(
+            base.resolve_with(&RelativePath::new(r#"../["two words"]"#))
+                .as_str(),
+            "/two words"
+        );
+    }
+
+    #[test]
+    fn test_to_reference() {
+        assert_eq!(AbsolutePath::new("/a/b").to_reference(), "/a/b");
+        let path = AbsolutePath::new("/a").append("  spaced  ").append("x:int");
+        assert_eq!(path.to_reference(), r#"/a/["  spaced  "]/["x:int"]"#);
+        assert_eq!(AbsolutePath::new(&path.to_reference()), path);
+    }
+
+    #[test]
+    fn test_append_index() {
+        let base = AbsolutePath::new("/items");
+        assert_eq!(base.append_index(3), base.append("3"));
+        assert_eq!(base.append_index(CACHED_INDICES + 5), base.append("1029"));
+        assert_eq!(AbsolutePath::default().append_index(0).as_str(), "/0");
+    }
+
+    #[test]
+    fn test_bracket_indices() {
+        assert_eq!(slash_indices("/items[3]/name"), "/items/3/name");
+        assert_eq!(slash_indices("grid[1][2]"), "grid/1/2");
+        assert_eq!(slash_indices("../[0]/name"), "../0/name");
+        assert_eq!(slash_indices("[0]"), "0");
+        assert_eq!(slash_indices(r#"/["a[1]"]/b[x]"#), r#"/["a[1]"]/b[x]"#);
+        assert_eq!(slash_indices("/plain/3"), "/plain/3");
+
+        let path = AbsolutePath::new("/0/items/3/4/name");
+        assert_eq!(path.to_reference_with_brackets(), "/0/items[3][4]/name");
+        assert_eq!(
+            AbsolutePath::new(&slash_indices(&path.to_reference_with_brackets())),
+            path
+        );
+    }
 }
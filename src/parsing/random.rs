@@ -0,0 +1,81 @@
+use fastrand::Rng;
+use serde_json::Value;
+
+/// A value generated instead of looked up, written as `{random:...}`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Generator {
+    /// `random:uuid` generates a version 4 UUID string
+    Uuid,
+    /// `random:int:1-100` generates an integer within the bounds, both included
+    Int(i64, i64),
+}
+
+impl Generator {
+    /// Parse the part after `random:`, e.g. `int:1-100`.
+    /// Returns None for unknown generators and empty ranges.
+    pub(crate) fn parse(text: &str) -> Option<Self> {
+        let (name, argument) = text.split_once(':').unwrap_or((text, ""));
+        match name {
+            "uuid" if argument.is_empty() => Some(Generator::Uuid),
+            "int" => {
+                // The lower bound may be negative, so its own sign is skipped
+                let dash = argument.get(1..)?.find('-')? + 1;
+                let start = argument[..dash].parse().ok()?;
+                let end = argument[dash + 1..].parse().ok()?;
+                (start <= end).then_some(Generator::Int(start, end))
+            }
+            _ => None,
+        }
+    }
+
+    pub(crate) fn generate(&self, rng: &mut Rng) -> Value {
+        match self {
+            Generator::Uuid => {
+                let mut bytes = rng.u128(..).to_be_bytes();
+                bytes[6] = (bytes[6] & 0x0f) | 0x40;
+                bytes[8] = (bytes[8] & 0x3f) | 0x80;
+                let hex: String = bytes.iter().map(|byte| format!("{byte:02x}")).collect();
+                Value::String(format!(
+                    "{}-{}-{}-{}-{}",
+                    &hex[..8],
+                    &hex[8..12],
+                    &hex[12..16],
+                    &hex[16..20],
+                    &hex[20..]
+                ))
+            }
+            Generator::Int(start, end) => Value::from(rng.i64(*start..=*end)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_generators() {
+        assert_eq!(Generator::parse("uuid"), Some(Generator::Uuid));
+        assert_eq!(Generator::parse("int:1-100"), Some(Generator::Int(1, 100)));
+        assert_eq!(Generator::parse("int:-5--1"), Some(Generator::Int(-5, -1)));
+        assert_eq!(Generator::parse("int:10-1"), None);
+        assert_eq!(Generator::parse("int:1"), None);
+        assert_eq!(Generator::parse("uuid:4"), None);
+        assert_eq!(Generator::parse("name"), None);
+    }
+
+    #[test]
+    fn test_generated_values() {
+        let mut rng = Rng::with_seed(7);
+        let uuid = Generator::Uuid.generate(&mut rng);
+        let uuid = uuid.as_str().unwrap();
+        assert_eq!(uuid.len(), 36);
+        assert_eq!(&uuid[14..15], "4");
+        assert!(matches!(&uuid[19..20], "8" | "9" | "a" | "b"));
+
+        for _ in 0..100 {
+            let value = Generator::Int(-2, 2).generate(&mut rng).as_i64().unwrap();
+            assert!((-2..=2).contains(&value));
+        }
+    }
+}
@@ -0,0 +1,895 @@
+#[cfg(feature = "random")]
+use super::random::Generator;
+#[cfg(feature = "chrono")]
+use super::timestamp;
+use super::{
+    cast::Cast,
+    filter::Filter,
+    path::{AbsolutePath, slash_indices, unescape_pointer},
+};
+use crate::options::{IndexStyle, ResolveOptions};
+use serde_json::Value;
+use std::{borrow::Cow, ops::Range};
+
+/// How placeholders are written: the delimiters around the body, and an optional escape
+/// that turns an opening delimiter into plain text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Syntax<'a> {
+    open: &'a str,
+    close: &'a str,
+    escape: Option<&'a str>,
+    index_style: IndexStyle,
+    json_pointer: bool,
+    max_body_len: usize,
+}
+
+/// Where a placeholder is in a text, as byte offsets
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Span {
+    /// Start of the opening delimiter
+    pub(crate) start: usize,
+    /// Range of the body between the delimiters
+    pub(crate) body: (usize, usize),
+    /// End of the closing delimiter, exclusive
+    pub(crate) end: usize,
+}
+
+/// Something found while scanning a text for placeholders
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Token {
+    Placeholder(Span),
+    /// An escaped opening or closing delimiter; the range covers the escape, which is dropped
+    /// from the output
+    Escaped(usize, usize),
+    /// A placeholder whose body is too long to be parsed, kept as literal text
+    TooLong(Span),
+}
+
+impl Default for Syntax<'static> {
+    fn default() -> Self {
+        Syntax {
+            open: "{",
+            close: "}",
+            escape: Some("\\"),
+            index_style: IndexStyle::Slash,
+            json_pointer: false,
+            max_body_len: usize::MAX,
+        }
+    }
+}
+
+impl<'a> Syntax<'a> {
+    /// The syntax requested by the options. Empty delimiters fall back to braces.
+    pub(crate) fn new(options: &'a ResolveOptions) -> Self {
+        let default = Syntax::default();
+        let non_empty = |text: &'a str, fallback: &'static str| match text.is_empty() {
+            true => fallback,
+            false => text,
+        };
+        Syntax {
+            open: non_empty(&options.open_delim, default.open),
+            close: non_empty(&options.close_delim, default.close),
+            escape: options
+                .escape_delim
+                .as_deref()
+                .filter(|escape| !escape.is_empty()),
+            index_style: options.index_style,
+            json_pointer: options.json_pointer,
+            max_body_len: options.max_reference_len,
+        }
+    }
+
+    /// Finds the next placeholder or escaped delimiter in `text`,
+    /// starting the search at byte offset `from`.
+    pub(crate) fn next_token(&self, text: &str, from: usize) -> Option<Token> {
+        let start = text[from..].find(self.open).map(|i| from + i);
+
+        if let Some(escape) = self.escape {
+            // Escaped closing delimiters count outside of placeholders too
+            let escaped = text[from..]
+                .match_indices(escape)
+                .map(|(i, _)| from + i)
+                .take_while(|&i| i <= start.unwrap_or(text.len()))
+                .find(|&i| {
+                    let rest = &text[i + escape.len()..];
+                    rest.starts_with(self.open) || rest.starts_with(self.close)
+                });
+            if let Some(escaped) = escaped {
+                return Some(Token::Escaped(escaped, escaped + escape.len()));
+            }
+        }
+        let start = start?;
+
+        let body_start = start + self.open.len();
+        let body_end = self
+            .balanced_close(text, body_start)
+            .or_else(|| Some(body_start + text[body_start..].find(self.close)?))?;
+        let span = Span {
+            start,
+            body: (body_start, body_end),
+            end: body_end + self.close.len(),
+        };
+        match body_end - body_start > self.max_body_len {
+            true => Some(Token::TooLong(span)),
+            false => Some(Token::Placeholder(span)),
+        }
+    }
+
+    /// Finds the closing delimiter matching an opening one, skipping nested pairs like the
+    /// braces of a `{/a|{"b":1}}` default. Returns None if the delimiters are unbalanced.
+    fn balanced_close(&self, text: &str, body_start: usize) -> Option<usize> {
+        if self.open == self.close {
+            return None;
+        }
+        let mut depth = 0;
+        let mut from = body_start;
+        loop {
+            let close = from + text[from..].find(self.close)?;
+            match text[from..close].find(self.open) {
+                Some(open) => {
+                    depth += 1;
+                    from += open + self.open.len();
+                }
+                None if depth == 0 => return Some(close),
+                None => {
+                    depth -= 1;
+                    from = close + self.close.len();
+                }
+            }
+        }
+    }
+
+    /// Finds the next placeholder in `text`, starting the search at byte offset `from`.
+    /// Escaped delimiters and over-long placeholders are skipped.
+    pub(crate) fn find_placeholder(&self, text: &str, mut from: usize) -> Option<Span> {
+        loop {
+            match self.next_token(text, from)? {
+                Token::Placeholder(span) => return Some(span),
+                Token::Escaped(_, escape_end) => from = self.skip_escaped(text, escape_end),
+                Token::TooLong(span) => from = span.end,
+            }
+        }
+    }
+
+    /// Where to continue searching after an escape ending at `escape_end`, past the delimiter
+    pub(crate) fn skip_escaped(&self, text: &str, escape_end: usize) -> usize {
+        match text[escape_end..].starts_with(self.open) {
+            true => escape_end + self.open.len(),
+            false => escape_end + self.close.len(),
+        }
+    }
+
+    /// Whether the text contains at least one non-empty placeholder
+    pub(crate) fn contains_placeholder(&self, text: &str) -> bool {
+        let mut start_pos = 0;
+        while let Some(span) = self.find_placeholder(text, start_pos) {
+            if !span.body_range().is_empty() {
+                return true;
+            }
+            start_pos = span.end;
+        }
+        false
+    }
+
+    /// Number of non-empty placeholders in the text
+    pub(crate) fn count_placeholders(&self, text: &str) -> usize {
+        let mut count = 0;
+        let mut start_pos = 0;
+        while let Some(span) = self.find_placeholder(text, start_pos) {
+            count += usize::from(!span.body_range().is_empty());
+            start_pos = span.end;
+        }
+        count
+    }
+
+    /// Writes a placeholder with the body
+    pub(crate) fn wrap(&self, body: &str) -> String {
+        format!("{}{body}{}", self.open, self.close)
+    }
+
+    /// A placeholder path with its indices written as `/3` segments, whatever the index style,
+    /// and its JSON pointer escapes as quoted segments
+    pub(crate) fn slash_indices<'p>(&self, path: &'p str) -> Cow<'p, str> {
+        let path = match self.json_pointer {
+            true => unescape_pointer(path),
+            false => Cow::Borrowed(path),
+        };
+        match (self.index_style, path) {
+            (IndexStyle::Slash, path) => path,
+            (IndexStyle::Brackets, Cow::Borrowed(path)) => slash_indices(path),
+            (IndexStyle::Brackets, Cow::Owned(path)) => {
+                Cow::Owned(slash_indices(&path).into_owned())
+            }
+        }
+    }
+
+    /// Writes the path as it appears in a placeholder, in the index style
+    pub(crate) fn write_path(&self, path: &AbsolutePath) -> String {
+        match (self.index_style, self.json_pointer) {
+            (_, true) => path.to_pointer_reference(),
+            (IndexStyle::Slash, false) => path.to_reference(),
+            (IndexStyle::Brackets, false) => path.to_reference_with_brackets(),
+        }
+    }
+}
+
+impl Span {
+    pub(crate) fn body_range(&self) -> Range<usize> {
+        self.body.0..self.body.1
+    }
+
+    pub(crate) fn range(&self) -> Range<usize> {
+        self.start..self.end
+    }
+}
+
+/// Splits a namespaced path like `@db:/host` into the namespace and the absolute path within it
+pub(crate) fn split_namespace(path: &str) -> Option<(&str, &str)> {
+    let (namespace, path) = path.strip_prefix('@')?.split_once(':')?;
+    (!namespace.is_empty() && path.starts_with('/')).then_some((namespace, path))
+}
+
+/// Splits a path like `db@/host`, which prefers the source registered as `db` and falls back
+/// to the default source, into the namespace and the absolute path
+pub(crate) fn split_fallback_namespace(path: &str) -> Option<(&str, &str)> {
+    let (namespace, path) = path.split_once('@')?;
+    let is_name = !namespace.is_empty() && !namespace.contains(['/', '.']);
+    (is_name && path.starts_with('/')).then_some((namespace, path))
+}
+
+/// A parsed placeholder body, i.e. the text between `{` and `}`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Reference<'a> {
+    /// The whole placeholder body
+    pub(crate) text: &'a str,
+    /// What the placeholder resolves to
+    pub(crate) target: Target<'a>,
+    /// Type requested with the `:type` suffix
+    pub(crate) cast: Option<Cast>,
+    /// Transformations requested with `|filter` suffixes, in order of application
+    pub(crate) filters: Vec<Filter>,
+    /// Whether the target must be present and non-empty, requested with the `!` suffix
+    pub(crate) required: bool,
+    /// Value used when the target is missing, written after `|` in place of filters
+    pub(crate) default: Option<Value>,
+}
+
+/// The part of a placeholder that selects a value.
+/// All paths are slices of the placeholder body, absolute or relative.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Target<'a> {
+    /// `/path/to/value`
+    Path(&'a str),
+    /// `concat:/first," ",/last` joins the parts into a single string
+    Concat(Vec<ConcatPart<'a>>),
+    /// `concat_arrays:/a,/b` joins the arrays at the paths into a single array
+    ConcatArrays(Vec<&'a str>),
+    /// `$..name` collects the values of all `name` fields in the source, at any depth.
+    /// `$..name[1]` selects only the second of them, in document order.
+    Descendants(&'a str, Option<usize>),
+    /// `$key` is the name of the key holding the placeholder, or its index in an array
+    Key,
+    /// `spread_kv:/map` renders the entries of an object as `key=value` pairs
+    SpreadKv(&'a str),
+    /// `#each /items` opens a loop repeating the text up to `{/each}` for every element
+    Each(&'a str),
+    /// `spread:/map` merges the entries of an object into the object holding the placeholder
+    Spread(&'a str),
+    /// `random:uuid` or `random:int:1-100` generates a value
+    #[cfg(feature = "random")]
+    Random(Generator),
+    /// `now:%Y-%m-%d` is the current time in the given strftime format
+    #[cfg(feature = "chrono")]
+    Now(&'a str),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum ConcatPart<'a> {
+    Path(&'a str),
+    /// A quoted literal, already unescaped
+    Literal(String),
+}
+
+impl<'a> Reference<'a> {
+    /// Parse a placeholder body like `/path/to/value`, `/port:int!` or `/bio|truncate:20`
+    pub(crate) fn parse(text: &'a str) -> Self {
+        // Filters follow the first unquoted `|`. If some of them are unknown,
+        // everything after it is the default for a missing target instead.
+        let (head, filters, default) = match find_unquoted(text, '|') {
+            Some(bar) => {
+                let (head, tail) = (&text[..bar], &text[bar + 1..]);
+                match tail.split('|').map(Filter::parse).collect::<Option<_>>() {
+                    Some(filters) => (head, filters, None),
+                    None => (head, Vec::new(), Some(parse_default(tail))),
+                }
+            }
+            None => (text, Vec::new(), None),
+        };
+
+        let (head, required) = match head.strip_suffix('!') {
+            Some(head) => (head, true),
+            None => (head, false),
+        };
+
+        #[cfg(feature = "random")]
+        if let Some(generator) = head.strip_prefix("random:").and_then(Generator::parse) {
+            return Reference {
+                text,
+                target: Target::Random(generator),
+                cast: None,
+                filters,
+                required,
+                default,
+            };
+        }
+
+        #[cfg(feature = "chrono")]
+        if let Some(format) = head.strip_prefix("now:").and_then(timestamp::parse_format) {
+            return Reference {
+                text,
+                target: Target::Now(format),
+                cast: None,
+                filters,
+                required,
+                default,
+            };
+        }
+
+        if let Some(path) = head.strip_prefix("spread_kv:") {
+            return Reference {
+                text,
+                target: Target::SpreadKv(path),
+                cast: None,
+                filters,
+                required,
+                default,
+            };
+        }
+
+        if let Some(path) = head.strip_prefix(EACH_PREFIX) {
+            return Reference {
+                text,
+                target: Target::Each(path.trim()),
+                cast: None,
+                filters,
+                required,
+                default,
+            };
+        }
+
+        if let Some(path) = head.strip_prefix(SPREAD_PREFIX) {
+            return Reference {
+                text,
+                target: Target::Spread(path),
+                cast: None,
+                filters,
+                required,
+                default,
+            };
+        }
+
+        if let Some(paths) = head
+            .strip_prefix("concat_arrays:")
+            .and_then(parse_path_list)
+        {
+            return Reference {
+                text,
+                target: Target::ConcatArrays(paths),
+                cast: None,
+                filters,
+                required,
+                default,
+            };
+        }
+
+        if let Some(parts) = head.strip_prefix("concat:").and_then(parse_concat_parts) {
+            return Reference {
+                text,
+                target: Target::Concat(parts),
+                cast: None,
+                filters,
+                required,
+                default,
+            };
+        }
+
+        // `hash:/path` is a shorthand for `/path|hash`
+        #[cfg(feature = "hash")]
+        let (head, filters) = match head.strip_prefix("hash:") {
+            Some(path) => (path, [vec![Filter::Hash], filters].concat()),
+            None => (head, filters),
+        };
+
+        // `bytes:/path` is a shorthand for `/path|bytes`
+        let (head, filters) = match head.strip_prefix("bytes:") {
+            Some(path) => (path, [vec![Filter::Bytes], filters].concat()),
+            None => (head, filters),
+        };
+
+        let (path, cast) = match head.rsplit_once(':') {
+            Some((path, name)) => match Cast::from_name(name) {
+                Some(cast) => (path, Some(cast)),
+                None => (head, None),
+            },
+            None => (head, None),
+        };
+
+        // A trailing `start:end` segment slices the array at the path
+        let (path, filters) = match split_slice(path) {
+            Some((path, slice)) => (path, [vec![slice], filters].concat()),
+            None => (path, filters),
+        };
+
+        let target = match path.strip_prefix(DESCENDANTS_PREFIX) {
+            _ if path == KEY_SELECTOR => Target::Key,
+            Some(field) if !field.is_empty() && !field.contains('/') && brackets_match(field) => {
+                match split_occurrence(field) {
+                    Some((field, nth)) if !field.is_empty() => {
+                        Target::Descendants(field, Some(nth))
+                    }
+                    _ => Target::Descendants(field, None),
+                }
+            }
+            _ => Target::Path(path),
+        };
+
+        Reference {
+            text,
+            target,
+            cast,
+            filters,
+            required,
+            default,
+        }
+    }
+
+    /// The default as written, which is what placeholders embedded in text insert
+    pub(crate) fn default_text(&self) -> Option<&'a str> {
+        self.default.as_ref()?;
+        find_unquoted(self.text, '|').map(|bar| &self.text[bar + 1..])
+    }
+
+    /// All paths the placeholder refers to, in order of appearance
+    pub(crate) fn paths(&self) -> Vec<&'a str> {
+        match &self.target {
+            Target::Path(path)
+            | Target::SpreadKv(path)
+            | Target::Spread(path)
+            | Target::Each(path) => vec![path],
+            Target::Descendants(..) | Target::Key => Vec::new(),
+            #[cfg(feature = "random")]
+            Target::Random(_) => Vec::new(),
+            #[cfg(feature = "chrono")]
+            Target::Now(_) => Vec::new(),
+            Target::ConcatArrays(paths) => paths.clone(),
+            Target::Concat(parts) => parts
+                .iter()
+                .filter_map(|part| match part {
+                    ConcatPart::Path(path) => Some(*path),
+                    ConcatPart::Literal(_) => None,
+                })
+                .collect(),
+        }
+    }
+
+    /// Rebuild the placeholder body, replacing the paths for which `f` returns a new one.
+    /// Everything else is kept verbatim.
+    pub(crate) fn rewrite_paths<F>(&self, mut f: F) -> String
+    where
+        F: FnMut(&str) -> Option<String>,
+    {
+        let mut rewritten = String::with_capacity(self.text.len());
+        let mut copied = 0;
+        for path in self.paths() {
+            // Paths are slices of the body, so their position can be recovered from the pointers
+            let start = path.as_ptr() as usize - self.text.as_ptr() as usize;
+            if let Some(replacement) = f(path) {
+                rewritten.push_str(&self.text[copied..start]);
+                rewritten.push_str(&replacement);
+                copied = start + path.len();
+            }
+        }
+        rewritten.push_str(&self.text[copied..]);
+        rewritten
+    }
+}
+
+/// A default is JSON if it parses as JSON, e.g. `{"timeout":30}` or `[1,2]`, and plain text otherwise
+fn parse_default(text: &str) -> Value {
+    serde_json::from_str(text).unwrap_or_else(|_| Value::String(text.to_string()))
+}
+
+/// Prefix of a loop over an array, `#each /items`
+pub(crate) const EACH_PREFIX: &str = "#each ";
+
+/// Body of the placeholder closing a loop, `{/each}`
+pub(crate) const EACH_END: &str = "/each";
+
+/// Prefix of the object spread, `spread:/map`
+pub(crate) const SPREAD_PREFIX: &str = "spread:";
+
+/// Prefix of the recursive descent selector, `$..name`
+pub(crate) const DESCENDANTS_PREFIX: &str = "$..";
+
+/// Splits a trailing `[n]` occurrence index off a descendant field name
+fn split_occurrence(field: &str) -> Option<(&str, usize)> {
+    let (field, index) = field.strip_suffix(']')?.rsplit_once('[')?;
+    if index.is_empty() || !index.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    Some((field, index.parse().ok()?))
+}
+
+/// Whether every `[` in a descendant field name is closed by a `]`, so `$..x[` is not
+/// taken for a selector
+fn brackets_match(field: &str) -> bool {
+    let mut open = false;
+    for b in field.bytes() {
+        match b {
+            b'[' if !open => open = true,
+            b']' if open => open = false,
+            b'[' | b']' => return false,
+            _ => {}
+        }
+    }
+    !open
+}
+
+/// Selector of the key or index holding the placeholder
+const KEY_SELECTOR: &str = "$key";
+
+/// Splits a trailing `start:end` segment off the path, e.g. `/items/1:3`
+fn split_slice(path: &str) -> Option<(&str, Filter)> {
+    let (array_path, range) = path.rsplit_once('/')?;
+    let (start, end) = range.split_once(':')?;
+    let slice = Filter::Slice(start.parse().ok()?, end.parse().ok()?);
+    // Slicing the root array keeps its slash
+    Some((&path[..array_path.len().max(1)], slice))
+}
+
+/// Splits the arguments of `concat_arrays:` on commas, None if one of them is empty
+fn parse_path_list(arguments: &str) -> Option<Vec<&str>> {
+    arguments
+        .split(',')
+        .map(str::trim)
+        .map(|path| (!path.is_empty()).then_some(path))
+        .collect()
+}
+
+/// Splits the arguments of `concat:` on commas outside of quoted literals
+fn parse_concat_parts(arguments: &str) -> Option<Vec<ConcatPart<'_>>> {
+    let mut parts = Vec::new();
+    let mut rest = arguments;
+    while let Some(comma) = find_unquoted(rest, ',') {
+        parts.push(parse_concat_part(&rest[..comma])?);
+        rest = &rest[comma + 1..];
+    }
+    parts.push(parse_concat_part(rest)?);
+
+    Some(parts)
+}
+
+/// Byte offset of the first `delimiter` outside of double-quoted JSON strings
+fn find_unquoted(text: &str, delimiter: char) -> Option<usize> {
+    let mut in_quotes = false;
+    let mut escaped = false;
+    for (i, c) in text.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if in_quotes => escaped = true,
+            '"' => in_quotes = !in_quotes,
+            _ if c == delimiter && !in_quotes => return Some(i),
+            _ => {}
+        }
+    }
+    None
+}
+
+fn parse_concat_part(part: &str) -> Option<ConcatPart<'_>> {
+    let part = part.trim();
+    if part.starts_with('"') {
+        serde_json::from_str(part).ok().map(ConcatPart::Literal)
+    } else if part.is_empty() {
+        None
+    } else {
+        Some(ConcatPart::Path(part))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_placeholder() {
+        let syntax = Syntax::default();
+        let text = "a {/b} c {d}";
+        let span = syntax.find_placeholder(text, 0).unwrap();
+        assert_eq!((span.start, span.body, span.end), (2, (3, 5), 6));
+        assert_eq!(&text[span.body_range()], "/b");
+        let span = syntax.find_placeholder(text, 6).unwrap();
+        assert_eq!((span.start, span.body, span.end), (9, (10, 11), 12));
+        assert_eq!(syntax.find_placeholder(text, 12), None);
+        assert_eq!(syntax.find_placeholder("{unclosed", 0), None);
+    }
+
+    #[test]
+    fn test_find_placeholder_with_nested_delimiters() {
+        let syntax = Syntax::default();
+        let text = r#"{/a|{"b":{"c":1}}} and {/d}"#;
+        let span = syntax.find_placeholder(text, 0).unwrap();
+        assert_eq!(&text[span.body_range()], r#"/a|{"b":{"c":1}}"#);
+        let span = syntax.find_placeholder(text, span.end).unwrap();
+        assert_eq!(&text[span.body_range()], "/d");
+
+        // Unbalanced delimiters end at the first closing one
+        let span = syntax.find_placeholder("{a{b}", 0).unwrap();
+        assert_eq!(span.body, (1, 4));
+    }
+
+    #[test]
+    fn test_contains_placeholder() {
+        let syntax = Syntax::default();
+        assert!(syntax.contains_placeholder("Hello {name}"));
+        assert!(syntax.contains_placeholder("{} and {/x}"));
+        assert!(!syntax.contains_placeholder("Empty {} braces"));
+        assert!(!syntax.contains_placeholder("No } placeholders {"));
+    }
+
+    #[test]
+    fn test_custom_delimiters_with_escape() {
+        let options = ResolveOptions {
+            open_delim: "${".to_string(),
+            close_delim: "}".to_string(),
+            escape_delim: Some("$".to_string()),
+            ..Default::default()
+        };
+        let syntax = Syntax::new(&options);
+
+        let text = "$${x} and ${y} {z}";
+        assert_eq!(syntax.next_token(text, 0), Some(Token::Escaped(0, 1)));
+        let span = syntax.find_placeholder(text, 0).unwrap();
+        assert_eq!(&text[span.body_range()], "y");
+        assert_eq!(&text[span.range()], "${y}");
+        assert_eq!(syntax.find_placeholder(text, span.end), None);
+        assert!(!syntax.contains_placeholder("only $${escaped}"));
+        assert_eq!(syntax.wrap("/x"), "${/x}");
+
+        let options = ResolveOptions {
+            escape_delim: Some("\\".to_string()),
+            ..Default::default()
+        };
+        let syntax = Syntax::new(&options);
+        let text = r"\{1\} {y} \}";
+        assert_eq!(syntax.next_token(text, 0), Some(Token::Escaped(0, 1)));
+        assert_eq!(syntax.next_token(text, 2), Some(Token::Escaped(3, 4)));
+        let span = syntax.find_placeholder(text, 0).unwrap();
+        assert_eq!(&text[span.range()], "{y}");
+        assert_eq!(
+            syntax.next_token(text, span.end),
+            Some(Token::Escaped(10, 11))
+        );
+        assert_eq!(syntax.find_placeholder(text, span.end), None);
+    }
+
+    #[test]
+    fn test_max_reference_len() {
+        let options = ResolveOptions {
+            max_reference_len: 4,
+            ..Default::default()
+        };
+        let syntax = Syntax::new(&options);
+
+        let text = "{/too/long} {/ok}";
+        assert!(matches!(
+            syntax.next_token(text, 0),
+            Some(Token::TooLong(_))
+        ));
+        let span = syntax.find_placeholder(text, 0).unwrap();
+        assert_eq!(&text[span.body_range()], "/ok");
+        assert!(!syntax.contains_placeholder("{/too/long}"));
+    }
+
+    #[test]
+    fn test_split_namespace() {
+        assert_eq!(split_namespace("@db:/host"), Some(("db", "/host")));
+        assert_eq!(split_namespace("@db:/"), Some(("db", "/")));
+        assert_eq!(split_namespace("@db/host"), None);
+        assert_eq!(split_namespace("@:/host"), None);
+        assert_eq!(split_namespace("/host"), None);
+
+        assert_eq!(split_fallback_namespace("db@/host"), Some(("db", "/host")));
+        assert_eq!(split_fallback_namespace("db@host"), None);
+        assert_eq!(split_fallback_namespace("../a@/b"), None);
+        assert_eq!(split_fallback_namespace("@db:/host"), None);
+
+        let reference = Reference::parse("@db:/port:int");
+        assert_eq!(reference.target, Target::Path("@db:/port"));
+        assert_eq!(reference.cast, Some(Cast::Int));
+    }
+
+    #[test]
+    fn test_parse_reference_with_cast() {
+        let reference = Reference::parse("../port:int");
+        assert_eq!(reference.target, Target::Path("../port"));
+        assert_eq!(reference.cast, Some(Cast::Int));
+        assert_eq!(
+            reference.rewrite_paths(|_| Some("/port".to_string())),
+            "/port:int"
+        );
+    }
+
+    #[test]
+    fn test_parse_reference_with_unknown_suffix() {
+        let reference = Reference::parse("/time:12:30");
+        assert_eq!(reference.target, Target::Path("/time:12:30"));
+        assert_eq!(reference.cast, None);
+    }
+
+    #[test]
+    fn test_parse_reference_with_filters() {
+        let reference = Reference::parse("bio:int|truncate:20");
+        assert_eq!(reference.target, Target::Path("bio"));
+        assert_eq!(reference.cast, Some(Cast::Int));
+        assert_eq!(reference.filters, vec![Filter::Truncate(20)]);
+        assert_eq!(
+            reference.rewrite_paths(|_| Some("/bio".to_string())),
+            "/bio:int|truncate:20"
+        );
+
+        // Unknown filters make the rest of the text a default
+        let reference = Reference::parse("/a|b");
+        assert_eq!(reference.target, Target::Path("/a"));
+        assert!(reference.filters.is_empty());
+        assert_eq!(reference.default, Some(Value::String("b".to_string())));
+
+        let reference = Reference::parse(r#"/a|{"b": [1, 2]}"#);
+        assert_eq!(reference.default, Some(serde_json::json!({ "b": [1, 2] })));
+        assert_eq!(reference.default_text(), Some(r#"{"b": [1, 2]}"#));
+        assert_eq!(Reference::parse("/a|").default_text(), Some(""));
+        assert_eq!(Reference::parse("/a|upper").default_text(), None);
+
+        // Bars in quoted segments are part of the path
+        let reference = Reference::parse(r#"/["a|b"]"#);
+        assert_eq!(reference.target, Target::Path(r#"/["a|b"]"#));
+        assert_eq!(reference.default, None);
+    }
+
+    #[test]
+    fn test_parse_required() {
+        let reference = Reference::parse("name!");
+        assert_eq!(reference.target, Target::Path("name"));
+        assert!(reference.required);
+
+        let reference = Reference::parse("/port:int!|truncate:2");
+        assert_eq!(reference.target, Target::Path("/port"));
+        assert_eq!(reference.cast, Some(Cast::Int));
+        assert!(reference.required);
+        assert_eq!(
+            reference.rewrite_paths(|_| Some("/env/port".to_string())),
+            "/env/port:int!|truncate:2"
+        );
+
+        assert!(!Reference::parse("/name").required);
+    }
+
+    #[test]
+    fn test_parse_slice() {
+        let reference = Reference::parse("../items/1:3|upper");
+        assert_eq!(reference.target, Target::Path("../items"));
+        assert_eq!(reference.filters, vec![Filter::Slice(1, 3), Filter::Upper]);
+        assert_eq!(
+            reference.rewrite_paths(|_| Some("/items".to_string())),
+            "/items/1:3|upper"
+        );
+
+        let reference = Reference::parse("/0:2");
+        assert_eq!(reference.target, Target::Path("/"));
+        assert_eq!(reference.filters, vec![Filter::Slice(0, 2)]);
+
+        // Only numeric bounds make a slice
+        let reference = Reference::parse("/time/12:3x");
+        assert_eq!(reference.target, Target::Path("/time/12:3x"));
+        assert!(reference.filters.is_empty());
+    }
+
+    #[test]
+    fn test_parse_concat() {
+        let reference = Reference::parse(r#"concat:/first, " ",last,", \"x\"""#);
+        assert_eq!(
+            reference.target,
+            Target::Concat(vec![
+                ConcatPart::Path("/first"),
+                ConcatPart::Literal(" ".to_string()),
+                ConcatPart::Path("last"),
+                ConcatPart::Literal(", \"x\"".to_string()),
+            ])
+        );
+        assert_eq!(reference.paths(), vec!["/first", "last"]);
+        assert_eq!(
+            reference.rewrite_paths(|path| (path == "last").then(|| "/user/last".to_string())),
+            r#"concat:/first, " ",/user/last,", \"x\"""#
+        );
+    }
+
+    #[test]
+    fn test_parse_invalid_concat() {
+        // Empty parts and unterminated literals make it a plain path
+        let reference = Reference::parse("concat:/a,,/b");
+        assert_eq!(reference.target, Target::Path("concat:/a,,/b"));
+        let reference = Reference::parse(r#"concat:/a,"open"#);
+        assert_eq!(reference.target, Target::Path(r#"concat:/a,"open"#));
+    }
+
+    #[test]
+    fn test_parse_each() {
+        let reference = Reference::parse("#each  ../items ");
+        assert_eq!(reference.target, Target::Each("../items"));
+        assert_eq!(reference.paths(), vec!["../items"]);
+        assert_eq!(
+            Reference::parse("#eachitems").target,
+            Target::Path("#eachitems")
+        );
+    }
+
+    #[test]
+    fn test_parse_spread() {
+        let reference = Reference::parse("spread:../defaults");
+        assert_eq!(reference.target, Target::Spread("../defaults"));
+        assert_eq!(reference.paths(), vec!["../defaults"]);
+        assert_eq!(
+            Reference::parse("spread_kv:/q").target,
+            Target::SpreadKv("/q")
+        );
+    }
+
+    #[test]
+    fn test_parse_concat_arrays() {
+        let reference = Reference::parse("concat_arrays:/a, ../b,/c");
+        assert_eq!(
+            reference.target,
+            Target::ConcatArrays(vec!["/a", "../b", "/c"])
+        );
+        assert_eq!(
+            reference.rewrite_paths(|path| (path == "../b").then(|| "/x/b".to_string())),
+            "concat_arrays:/a, /x/b,/c"
+        );
+
+        let reference = Reference::parse("concat_arrays:/a,,/b");
+        assert_eq!(reference.target, Target::Path("concat_arrays:/a,,/b"));
+    }
+
+    #[test]
+    fn test_parse_descendants() {
+        let reference = Reference::parse("$..age:int");
+        assert_eq!(reference.target, Target::Descendants("age", None));
+        assert_eq!(reference.cast, Some(Cast::Int));
+        assert!(reference.paths().is_empty());
+
+        let reference = Reference::parse("$..url[1]|upper");
+        assert_eq!(reference.target, Target::Descendants("url", Some(1)));
+        assert_eq!(reference.filters, vec![Filter::Upper]);
+        // Anything but digits in the brackets is part of the field name
+        let reference = Reference::parse("$..url[x]");
+        assert_eq!(reference.target, Target::Descendants("url[x]", None));
+
+        // Only a single field name is supported
+        let reference = Reference::parse("$..a/b");
+        assert_eq!(reference.target, Target::Path("$..a/b"));
+
+        // Unclosed brackets are not a selector
+        let reference = Reference::parse("$..x[");
+        assert_eq!(reference.target, Target::Path("$..x["));
+        let reference = Reference::parse("$..x]1[");
+        assert_eq!(reference.target, Target::Path("$..x]1["));
+    }
+
+    #[test]
+    fn test_parse_key() {
+        let reference = Reference::parse("$key|upper");
+        assert_eq!(reference.target, Target::Key);
+        assert_eq!(reference.filters, vec![Filter::Upper]);
+        assert!(reference.paths().is_empty());
+        assert_eq!(Reference::parse("$keys").target, Target::Path("$keys"));
+    }
+}
@@ -0,0 +1,80 @@
+use super::path::AbsolutePath;
+
+/// Paths further away than this many edits are not suggested
+pub(crate) const MAX_DISTANCE: usize = 2;
+
+/// The candidate closest to `target`, if it's at most `max_distance` edits away.
+/// Ties are broken alphabetically, so the suggestion doesn't depend on the candidate order.
+pub(crate) fn closest_path<'a, I>(
+    target: &AbsolutePath,
+    candidates: I,
+    max_distance: usize,
+) -> Option<&'a AbsolutePath>
+where
+    I: IntoIterator<Item = &'a AbsolutePath>,
+{
+    candidates
+        .into_iter()
+        .map(|candidate| (levenshtein(target.as_str(), candidate.as_str()), candidate))
+        .filter(|(distance, _)| (1..=max_distance).contains(distance))
+        .min_by(|(a, a_path), (b, b_path)| {
+            a.cmp(b).then_with(|| a_path.as_str().cmp(b_path.as_str()))
+        })
+        .map(|(_, candidate)| candidate)
+}
+
+/// Number of single character insertions, deletions and substitutions turning `a` into `b`
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != *b_char);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_levenshtein() {
+        assert_eq!(levenshtein("", "abc"), 3);
+        assert_eq!(levenshtein("kitten", "sitting"), 3);
+        assert_eq!(levenshtein("/user/name", "/user/name"), 0);
+        assert_eq!(levenshtein("/user/nmae", "/user/name"), 2);
+    }
+
+    #[test]
+    fn test_closest_path() {
+        let candidates = [
+            AbsolutePath::new("/user/name"),
+            AbsolutePath::new("/user/game"),
+            AbsolutePath::new("/settings"),
+        ];
+        let closest = closest_path(&AbsolutePath::new("/user/nam"), &candidates, MAX_DISTANCE);
+        assert_eq!(closest, Some(&AbsolutePath::new("/user/name")));
+
+        // Equally close candidates are picked alphabetically
+        let closest = closest_path(&AbsolutePath::new("/user/xame"), &candidates, MAX_DISTANCE);
+        assert_eq!(closest, Some(&AbsolutePath::new("/user/game")));
+
+        assert_eq!(
+            closest_path(&AbsolutePath::new("/other"), &candidates, MAX_DISTANCE),
+            None
+        );
+        assert_eq!(
+            closest_path(&AbsolutePath::new("/settings"), &candidates, MAX_DISTANCE),
+            None
+        );
+        assert_eq!(
+            closest_path(&AbsolutePath::new("/user/nmae"), &candidates, 1),
+            None
+        );
+    }
+}
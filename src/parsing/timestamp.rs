@@ -0,0 +1,35 @@
+use chrono::{DateTime, FixedOffset, format::StrftimeItems};
+use serde_json::Value;
+
+/// Checks the part after `now:`, e.g. `%Y-%m-%d`.
+/// Returns None for formats chrono can't render.
+pub(crate) fn parse_format(text: &str) -> Option<&str> {
+    StrftimeItems::new(text).parse().ok().map(|_| text)
+}
+
+/// The time rendered with a format accepted by [`parse_format`]
+pub(crate) fn format(now: &DateTime<FixedOffset>, format: &str) -> Value {
+    Value::String(now.format(format).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_format() {
+        assert_eq!(parse_format("%Y-%m-%d"), Some("%Y-%m-%d"));
+        assert_eq!(parse_format("built at %H:%M"), Some("built at %H:%M"));
+        assert_eq!(parse_format("%Q"), None);
+    }
+
+    #[test]
+    fn test_format() {
+        let now = DateTime::parse_from_rfc3339("2024-03-05T14:07:09+02:00").unwrap();
+        assert_eq!(format(&now, "%Y-%m-%d"), Value::from("2024-03-05"));
+        assert_eq!(
+            format(&now, "%d.%m.%y %H:%M:%S %z"),
+            Value::from("05.03.24 14:07:09 +0200")
+        );
+    }
+}
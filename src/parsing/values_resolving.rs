@@ -1,117 +1,1094 @@
-use super::path::AbsolutePath;
+use super::index::WILDCARD;
+use super::path::{AbsolutePath, RelativePath};
+use super::reference::{
+    ConcatPart, DESCENDANTS_PREFIX, EACH_END, EACH_PREFIX, Reference, SPREAD_PREFIX, Syntax,
+    Target, Token, split_fallback_namespace, split_namespace,
+};
+use super::suggest::closest_path;
+use crate::{
+    error::ResolveError,
+    kind::ValueKind,
+    options::{Missing, ResolveOptions},
+    report::{Problems, Warning},
+    source::Source,
+};
 use serde_json::Value;
-use std::collections::HashMap;
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+};
 
-pub(crate) fn resolve_values(json: &Value, context: &HashMap<AbsolutePath, Value>) -> Value {
-    match json {
-        Value::Object(map) => {
-            let mut resolved_map = serde_json::Map::new();
-            for (key, value) in map {
-                let resolved_value = resolve_values(value, context);
-                resolved_map.insert(key.clone(), resolved_value);
-            }
-            Value::Object(resolved_map)
-        }
-        Value::Array(arr) => Value::Array(arr.iter().map(|v| resolve_values(v, context)).collect()),
-        Value::String(text) => {
-            // Check if the string is a single dependency like "{/absolute_path}"
-            if let Some(dependency_path) = extract_dependency(&text) {
-                // If it's a dependency, directly replace the value and type
-                if let Some(resolved_value) = context.get(&dependency_path) {
-                    return resolved_value.clone(); // Resolve to the dependency value and type
+/// Replaces placeholders with values from a source.
+///
+/// Placeholders that can't be resolved are left unchanged, and the problems are
+/// collected so that strict callers can report them.
+pub(crate) struct ValueResolver<'a> {
+    source: &'a dyn Source,
+    options: &'a ResolveOptions,
+    syntax: Syntax<'a>,
+    /// Paths whose absence is not a problem, see [`super::optional_paths`]
+    optional_paths: HashSet<AbsolutePath>,
+    /// Generator of `{random:...}` values, which stay unresolved without one
+    #[cfg(feature = "random")]
+    rng: Option<fastrand::Rng>,
+    /// Time of `{now:...}` values, which stay unresolved without one
+    #[cfg(feature = "chrono")]
+    now: Option<chrono::DateTime<chrono::FixedOffset>>,
+    /// Maximum size of the serialized output, see [`ValueResolver::with_size_limit`]
+    size_limit: Option<usize>,
+    /// Size of the serialized output so far, counting unresolved parts as they are
+    size_used: usize,
+    /// Size of the strings still to be resolved along with their keys, the most their
+    /// placeholders could take off the output
+    size_removable: usize,
+    /// The string whose resolution last took the output over the size limit
+    size_crossed: Option<AbsolutePath>,
+    /// Source paths whose values are being resolved, when pulled values are resolved too
+    pulling: Option<Vec<AbsolutePath>>,
+    /// Where absolute paths of the document start in the source, see [`ValueResolver::with_source_root`]
+    source_root: Option<AbsolutePath>,
+    /// Paths and values misspelled targets are matched against, when fuzzy matching is enabled
+    fuzzy_source: Option<&'a HashMap<AbsolutePath, Value>>,
+    /// Whether the value being resolved is an array element, which `{$key}` turns into its index
+    in_array: bool,
+    /// Fields of the document whose placeholders lead back to them, with the chains they are
+    /// part of, each starting with the field, see [`ValueResolver::with_cycles`]
+    cycles: HashMap<AbsolutePath, Vec<Vec<AbsolutePath>>>,
+    /// The array element `{.field}` placeholders read, inside an `{#each /items}` loop
+    each_element: Option<Value>,
+    /// The objects and arrays of the document in resolution order, when static ones are
+    /// copied whole, and the position of the next one
+    branches: Option<(Vec<Branch>, usize)>,
+    /// Whether problems are reported with the paths of placeholders made absolute,
+    /// see [`ValueResolver::with_absolute_reports`]
+    absolute_reports: bool,
+    problems: Problems,
+}
+
+impl<'a> ValueResolver<'a> {
+    pub(crate) fn new(source: &'a dyn Source, options: &'a ResolveOptions) -> Self {
+        ValueResolver {
+            source,
+            options,
+            syntax: Syntax::new(options),
+            optional_paths: HashSet::new(),
+            #[cfg(feature = "random")]
+            rng: None,
+            #[cfg(feature = "chrono")]
+            now: None,
+            size_limit: None,
+            size_used: 0,
+            size_removable: 0,
+            size_crossed: None,
+            pulling: None,
+            source_root: None,
+            fuzzy_source: None,
+            in_array: false,
+            cycles: HashMap::new(),
+            each_element: None,
+            branches: None,
+            absolute_reports: false,
+            problems: Problems::default(),
+        }
+    }
+
+    /// Resolves placeholders in values pulled from the source against the same source,
+    /// before inserting them. Values referring back to themselves are inserted unresolved.
+    pub(crate) fn with_recursive_sources(mut self) -> Self {
+        self.pulling = Some(Vec::new());
+        self
+    }
+
+    /// Copies objects and arrays of the `document` about to be resolved whole when nothing
+    /// beneath them needs resolving, instead of rebuilding them value by value
+    /// Values pulled from the source aren't part of the document, so this can't be combined
+    /// with [`ValueResolver::with_recursive_sources`].
+    pub(crate) fn with_static_branches(mut self, document: &Value) -> Self {
+        let mut branches = Vec::new();
+        collect_branches(document, &self.syntax, &mut branches);
+        self.branches = Some((branches, 0));
+        self
+    }
+
+    /// Stops resolving once the serialized output would exceed `limit` bytes even if all
+    /// remaining strings were removed, reporting [`ResolveError::OutputTooLarge`]. Sizes are
+    /// counted from the `document` about to be resolved, growing or shrinking with every
+    /// replaced string. The final size is checked with [`ValueResolver::size_overflow`].
+    pub(crate) fn with_size_limit(mut self, limit: usize, document: &Value) -> Self {
+        self.size_limit = Some(limit);
+        self.size_used = super::serialized_len(document);
+        self.size_removable = removable_len(document, None);
+        self
+    }
+
+    /// The [`ResolveError::OutputTooLarge`] for a `resolved` document over the size limit,
+    /// at the string that took it over
+    pub(crate) fn size_overflow(&self, resolved: &Value) -> Option<ResolveError> {
+        let limit = self.size_limit?;
+        (super::serialized_len(resolved) > limit).then(|| ResolveError::OutputTooLarge {
+            at: self.size_crossed.clone().unwrap_or_default(),
+            limit,
+        })
+    }
+
+    /// Generates `{random:...}` values from the seed, the same ones on every run
+    #[cfg(feature = "random")]
+    pub(crate) fn with_rng(mut self, seed: u64) -> Self {
+        self.rng = Some(fastrand::Rng::with_seed(seed));
+        self
+    }
+
+    /// Renders `{now:...}` values from this time, the same one for the whole document
+    #[cfg(feature = "chrono")]
+    pub(crate) fn with_now(mut self, now: chrono::DateTime<chrono::FixedOffset>) -> Self {
+        self.now = Some(now);
+        self
+    }
+
+    /// Looks up absolute paths beneath `root` in the source, so `{/db/host}` reads `/app/db/host`
+    /// with `/app`. Relative paths are still relative to the placeholder.
+    pub(crate) fn with_source_root(mut self, root: &AbsolutePath) -> Self {
+        self.source_root = (root.as_str() != "/").then(|| root.clone());
+        self
+    }
+
+    /// Falls back to the path of the source one edit away from a missing target,
+    /// if [`ResolveOptions::fuzzy`] is set. Every substitution is reported as a warning.
+    pub(crate) fn with_fuzzy_source(mut self, source: &'a HashMap<AbsolutePath, Value>) -> Self {
+        if self.options.fuzzy {
+            self.fuzzy_source = Some(source);
+        }
+        self
+    }
+
+    /// Reports the placeholders of errors with their paths made absolute, as they are written
+    /// in the normalized document, e.g. `{/user/nmae}` for `{nmae}` at `/user/greeting`
+    pub(crate) fn with_absolute_reports(mut self) -> Self {
+        self.absolute_reports = true;
+        self
+    }
+
+    /// Missing targets at these paths stay unresolved without being reported
+    pub(crate) fn with_optional_paths(mut self, optional_paths: HashSet<AbsolutePath>) -> Self {
+        self.optional_paths = optional_paths;
+        self
+    }
+
+    /// Placeholders leading to a field of one of these chains from a field of the same chain
+    /// stay unresolved and are reported as [`ResolveError::Cycle`]
+    pub(crate) fn with_cycles(mut self, chains: &[Vec<AbsolutePath>]) -> Self {
+        for chain in chains {
+            for (i, field) in chain.iter().enumerate() {
+                let rotated = chain[i..].iter().chain(&chain[..i]).cloned().collect();
+                self.cycles.entry(field.clone()).or_default().push(rotated);
+            }
+        }
+        self
+    }
+
+    /// The cycle closed by a placeholder of the field at `current_path` referring to `path`,
+    /// starting with the field. The placeholder may skip fields of a longer chain.
+    fn cycle_through(
+        &self,
+        current_path: &AbsolutePath,
+        path: &AbsolutePath,
+    ) -> Option<Vec<AbsolutePath>> {
+        self.cycles.get(current_path)?.iter().find_map(|chain| {
+            match chain.iter().position(|field| path.contains(field))? {
+                0 => Some(vec![chain[0].clone()]),
+                next => Some([&chain[..1], &chain[next..]].concat()),
+            }
+        })
+    }
+
+    /// Problems found so far, in document order
+    pub(crate) fn into_problems(self) -> Problems {
+        self.problems
+    }
+
+    /// Resolves a value found at `current_path` of a larger document
+    pub(crate) fn resolve_within(
+        &mut self,
+        json: &Value,
+        current_path: &AbsolutePath,
+        in_array: bool,
+    ) -> Value {
+        self.in_array = in_array;
+        self.resolve(json, current_path)
+    }
+
+    pub(crate) fn resolve(&mut self, json: &Value, current_path: &AbsolutePath) -> Value {
+        match json {
+            Value::Object(_) | Value::Array(_) if self.skip_static_branch() => json.clone(),
+            Value::Object(map) => {
+                let mut resolved_map = serde_json::Map::with_capacity(map.len());
+                // Entries of spliced objects, added last so that explicit keys win
+                let mut spliced = serde_json::Map::new();
+                for (key, value) in map {
+                    self.in_array = false;
+                    let errors = self.problems.errors.len();
+                    let resolved_value = self.resolve(value, &current_path.append(key));
+                    match key.strip_suffix(CONDITIONAL_SUFFIX) {
+                        // A conditional key without a value is dropped along with its problems
+                        Some(_) if is_absent(value, &resolved_value, &self.syntax) => {
+                            self.problems.errors.truncate(errors);
+                        }
+                        _ if resolved_value.is_object()
+                            && (self.options.splice_object_refs && value.is_string()
+                                || self.is_spread(value)) =>
+                        {
+                            if let Value::Object(entries) = resolved_value {
+                                spliced.extend(entries);
+                            }
+                        }
+                        Some(key) => {
+                            resolved_map.insert(key.to_string(), resolved_value);
+                        }
+                        None => {
+                            resolved_map.insert(key.clone(), resolved_value);
+                        }
+                    }
                 }
+                for (key, value) in spliced {
+                    resolved_map.entry(key).or_insert(value);
+                }
+                Value::Object(resolved_map)
+            }
+            Value::Array(arr) => Value::Array(
+                arr.iter()
+                    .enumerate()
+                    .map(|(i, v)| {
+                        self.in_array = true;
+                        self.resolve(v, &current_path.append_index(i))
+                    })
+                    .collect(),
+            ),
+            Value::String(_) if self.over_size_limit() => json.clone(),
+            Value::String(text) => {
+                let key = self.entry_key(current_path);
+                let resolved = self.resolve_string(text, json, current_path);
+                self.count_size(json, key.as_deref(), &resolved, current_path);
+                resolved
+            }
+            _ => json.clone(), // Leave other types of JSON values untouched
+        }
+    }
+
+    /// Same as [`ValueResolver::resolve`], replacing the placeholders of `json` where they are.
+    /// Only strings are replaced, and objects only lose or gain the keys of conditional and
+    /// spliced entries, so nothing else of the document is copied.
+    pub(crate) fn resolve_in_place(&mut self, json: &mut Value, current_path: &AbsolutePath) {
+        if let Value::String(text) = &*json {
+            if !self.over_size_limit() {
+                let key = self.entry_key(current_path);
+                let resolved = self.resolve_string(text, json, current_path);
+                self.count_size(json, key.as_deref(), &resolved, current_path);
+                *json = resolved;
             }
+            return;
+        }
 
-            // Handle embedded dependencies (e.g., "Hello {path}")
-            resolve_embedded_refs(text, context)
+        match json {
+            Value::Object(_) | Value::Array(_) if self.skip_static_branch() => {}
+            Value::Object(map) => {
+                let mut dropped = Vec::new();
+                let mut renamed = Vec::new();
+                let mut spliced = serde_json::Map::new();
+                for (key, value) in map.iter_mut() {
+                    self.in_array = false;
+                    let errors = self.problems.errors.len();
+                    // Conditional and spliced entries depend on the value as written
+                    let original = value.is_string().then(|| value.clone());
+                    self.resolve_in_place(value, &current_path.append(key));
+                    let original = original.as_ref().unwrap_or(&Value::Null);
+                    match key.strip_suffix(CONDITIONAL_SUFFIX) {
+                        Some(_) if is_absent(original, value, &self.syntax) => {
+                            self.problems.errors.truncate(errors);
+                            dropped.push(key.clone());
+                        }
+                        _ if value.is_object()
+                            && (self.options.splice_object_refs && original.is_string()
+                                || self.is_spread(original)) =>
+                        {
+                            if let Value::Object(entries) = value.take() {
+                                spliced.extend(entries);
+                            }
+                            dropped.push(key.clone());
+                        }
+                        Some(name) => renamed.push((key.clone(), name.to_string())),
+                        None => {}
+                    }
+                }
+                for key in dropped {
+                    map.remove(&key);
+                }
+                for (key, name) in renamed {
+                    if let Some(value) = map.remove(&key) {
+                        map.insert(name, value);
+                    }
+                }
+                for (key, value) in spliced {
+                    map.entry(key).or_insert(value);
+                }
+            }
+            Value::Array(arr) => {
+                for (i, value) in arr.iter_mut().enumerate() {
+                    self.in_array = true;
+                    self.resolve_in_place(value, &current_path.append_index(i));
+                }
+            }
+            _ => {}
         }
-        _ => json.clone(), // Leave other types of JSON values untouched
     }
-}
 
-/// Extracts a dependency path from a string in the format "{path}".
-/// Returns an AbsolutePath if the string is a valid single dependency, otherwise None.
-fn extract_dependency(text: &str) -> Option<AbsolutePath> {
-    // Check if the entire string is in the format "{dependency_path}"
-    if text.starts_with('{') && text.ends_with('}') && text.len() > 2 {
-        let path = &text[1..text.len() - 1]; // Extract the path between '{' and '}'
-        return Some(AbsolutePath::new(path));
+    fn resolve_string(&mut self, text: &str, json: &Value, current_path: &AbsolutePath) -> Value {
+        // Check if the string is a single dependency like "{/absolute_path}"
+        let candidate = match self.options.trim_then_typed {
+            true => text.trim(),
+            false => text,
+        };
+        if let Some(body) = self
+            .standalone_reference(candidate)
+            .filter(|body| !body.starts_with(EACH_PREFIX))
+        {
+            // If it's a dependency, directly replace the value and type
+            let reference = Reference::parse(body);
+            return match self.resolve_standalone(&reference, current_path) {
+                Ok(value) => value,
+                Err(Failure::Missing) => Value::String(self.missing_text(&reference)),
+                Err(Failure::Invalid) => json.clone(),
+            };
+        }
+
+        // Handle embedded dependencies (e.g., "Hello {path}")
+        match self.resolve_embedded(text, current_path) {
+            Value::String(resolved) if self.options.collapse_whitespace && resolved != text => {
+                Value::String(collapse_whitespace(&resolved))
+            }
+            resolved => resolved,
+        }
     }
-    None
-}
 
-/// Resolves embedded references in a string, such as "Hello {path}".
-/// This keeps the input as a string and replaces any "{dependency_path}" references within it.
-fn resolve_embedded_refs(text: &str, source_map: &HashMap<AbsolutePath, Value>) -> Value {
-    let mut resolved_text = text.to_string();
-    let mut start_pos = 0;
-
-    while let Some(start) = resolved_text[start_pos..].find('{') {
-        let absolute_start = start_pos + start;
-        if let Some(end) = resolved_text[absolute_start..].find('}') {
-            let absolute_end = absolute_start + end;
-            let reference = &resolved_text[absolute_start + 1..absolute_end];
-
-            // Check if it's an absolute path
-            if reference.starts_with('/') {
-                let absolute_path = AbsolutePath::new(reference);
-                if let Some(resolved_value) = source_map.get(&absolute_path) {
-                    if let Value::String(resolved_string) = resolved_value {
-                        // Replace the dependency with the resolved string slice
-                        resolved_text
-                            .replace_range(absolute_start..=absolute_end, resolved_string.as_str());
-                        start_pos = absolute_start + resolved_string.len();
-                        continue;
+    /// Moves past the object or array about to be resolved, and past everything beneath it
+    /// if it's static. Returns whether it was static.
+    fn skip_static_branch(&mut self) -> bool {
+        let Some((branches, next)) = &mut self.branches else {
+            return false;
+        };
+        let branch = branches[*next];
+        match branch.dynamic {
+            true => *next += 1,
+            false => *next += 1 + branch.nested,
+        }
+        !branch.dynamic
+    }
+
+    /// Whether the output is over the size limit whatever the remaining strings resolve to
+    fn over_size_limit(&self) -> bool {
+        self.size_limit
+            .is_some_and(|limit| self.size_used.saturating_sub(self.size_removable) > limit)
+    }
+
+    /// The key of the object entry at the path, None for array elements and the root
+    fn entry_key(&self, current_path: &AbsolutePath) -> Option<String> {
+        if self.size_limit.is_none() || self.in_array {
+            return None;
+        }
+        current_path.segments().last().map(Cow::into_owned)
+    }
+
+    /// Accounts for the size change of a resolved string, reporting when the output can no
+    /// longer get back under the limit
+    fn count_size(
+        &mut self,
+        original: &Value,
+        key: Option<&str>,
+        resolved: &Value,
+        current_path: &AbsolutePath,
+    ) {
+        let Some(limit) = self.size_limit else {
+            return;
+        };
+        let within = self.size_used <= limit;
+        self.size_used = (self.size_used + super::serialized_len(resolved))
+            .saturating_sub(super::serialized_len(original));
+        self.size_removable = self
+            .size_removable
+            .saturating_sub(removable_len(original, key));
+        match self.size_used <= limit {
+            true => self.size_crossed = None,
+            false if within => self.size_crossed = Some(current_path.clone()),
+            false => {}
+        }
+        if self.over_size_limit() {
+            self.problems.errors.push(ResolveError::OutputTooLarge {
+                at: current_path.clone(),
+                limit,
+            });
+        }
+    }
+
+    /// Resolves a placeholder occupying the whole string, keeping the type of the target value.
+    fn resolve_standalone(
+        &mut self,
+        reference: &Reference,
+        current_path: &AbsolutePath,
+    ) -> Result<Value, Failure> {
+        let value = self.resolve_target(reference, current_path)?;
+
+        let value = match reference.cast {
+            Some(cast) => {
+                let cast_value = cast.apply(&value);
+                if cast_value.is_none() {
+                    self.problems.errors.push(ResolveError::InvalidCast {
+                        at: current_path.clone(),
+                        reference: self.reported(reference, current_path),
+                    });
+                }
+                cast_value.ok_or(Failure::Invalid)?
+            }
+            None => value,
+        };
+
+        self.apply_filters(reference, value, current_path)
+            .ok_or(Failure::Invalid)
+    }
+
+    /// Resolves embedded references in a string, such as "Hello {path}".
+    /// This keeps the input as a string and replaces any "{dependency_path}" references within it.
+    fn resolve_embedded(&mut self, text: &str, current_path: &AbsolutePath) -> Value {
+        let mut resolved_text = text.to_string();
+        let mut start_pos = 0;
+
+        while let Some(token) = self.syntax.next_token(&resolved_text, start_pos) {
+            let span = match token {
+                Token::Placeholder(span) => span,
+                Token::Escaped(escape_start, escape_end) => {
+                    // Drop the escape, keeping the delimiter as text
+                    resolved_text.replace_range(escape_start..escape_end, "");
+                    start_pos = self.syntax.skip_escaped(&resolved_text, escape_start);
+                    continue;
+                }
+                Token::TooLong(span) => {
+                    self.problems.errors.push(ResolveError::ReferenceTooLong {
+                        at: current_path.clone(),
+                        limit: self.options.max_reference_len,
+                    });
+                    start_pos = span.end;
+                    continue;
+                }
+            };
+            let mut reference = Reference::parse(&resolved_text[span.body_range()]);
+            if let Some(default) = reference.default_text() {
+                // Defaults go into the text as written, even those reading as other JSON values
+                reference.default = Some(Value::String(default.to_string()));
+            }
+            if let Target::Each(_) = reference.target {
+                // The whole loop, up to its closing placeholder, is replaced at once
+                let closing = self.syntax.wrap(EACH_END);
+                let Some(offset) = resolved_text[span.end..].find(&closing) else {
+                    self.report_unresolved(&reference, current_path);
+                    start_pos = span.end;
+                    continue;
+                };
+                let loop_end = span.end + offset + closing.len();
+                let body = resolved_text[span.end..span.end + offset].to_string();
+                let opening = resolved_text[span.body_range()].to_string();
+                match self.expand_each(&opening, &body, current_path) {
+                    Some(expanded) => {
+                        resolved_text.replace_range(span.start..loop_end, &expanded);
+                        start_pos = span.start + expanded.len();
                     }
+                    None => start_pos = loop_end,
+                }
+                continue;
+            }
+
+            let resolved_value = self
+                .resolve_target(&reference, current_path)
+                .and_then(|value| {
+                    self.apply_filters(&reference, value, current_path)
+                        .ok_or(Failure::Invalid)
+                })
+                .map(|value| self.embeddable(value));
+
+            match resolved_value {
+                Ok(resolved_string) => {
+                    // Replace the dependency with the resolved string slice
+                    resolved_text.replace_range(span.range(), &resolved_string);
+                    start_pos = span.start + resolved_string.len();
                 }
+                Err(Failure::Missing) if !reference.text.is_empty() => {
+                    let missing_text = self.missing_text(&reference);
+                    resolved_text.replace_range(span.range(), &missing_text);
+                    start_pos = span.start + missing_text.len();
+                }
+                Err(_) => start_pos = span.end,
             }
-            start_pos = absolute_end + 1;
-        } else {
-            break; // No closing brace found
         }
+
+        Value::String(resolved_text)
     }
 
-    Value::String(resolved_text)
-}
+    /// Repeats the body of an `{#each /items}` loop for every element of the array,
+    /// with `{.field}` placeholders in it reading the element
+    fn expand_each(
+        &mut self,
+        opening: &str,
+        body: &str,
+        current_path: &AbsolutePath,
+    ) -> Option<String> {
+        let reference = Reference::parse(opening);
+        let items = match self.resolve_target(&reference, current_path).ok()? {
+            Value::Array(items) => items,
+            other => {
+                self.problems.errors.push(ResolveError::KindMismatch {
+                    at: current_path.clone(),
+                    expected: ValueKind::Array,
+                    found: Some(ValueKind::of(&other)),
+                });
+                return None;
+            }
+        };
 
-// Recursive function for resolving the template
-pub(crate) fn resolve_recursive(
-    template: &Value,
-    source_map: &HashMap<AbsolutePath, Value>,
-) -> Value {
-    match template {
-        Value::Object(template_map) => {
-            // Traverse the template map
-            let mut resolved_map = serde_json::Map::new();
-            for (key, value) in template_map {
-                resolved_map.insert(key.clone(), resolve_recursive(value, source_map));
-            }
-            Value::Object(resolved_map)
-        }
-        Value::Array(template_array) => {
-            // Traverse the template array
-            Value::Array(
-                template_array
-                    .iter()
-                    .map(|value| resolve_recursive(value, source_map))
-                    .collect(),
-            )
+        let outer = self.each_element.take();
+        let mut expanded = String::new();
+        for item in items {
+            self.each_element = Some(item);
+            if let Value::String(text) = self.resolve_embedded(body, current_path) {
+                expanded.push_str(&text);
+            }
+        }
+        self.each_element = outer;
+        Some(expanded)
+    }
+
+    /// Resolves what the placeholder selects, before any cast or filter is applied
+    fn resolve_target(
+        &mut self,
+        reference: &Reference,
+        current_path: &AbsolutePath,
+    ) -> Result<Value, Failure> {
+        let value = match (
+            self.select_target(reference, current_path),
+            &reference.default,
+        ) {
+            (Err(Failure::Missing), Some(default)) => default.clone(),
+            (value, _) => value?,
+        };
+        if reference.required && is_empty(&value) {
+            self.report_required_empty(reference, current_path);
+        }
+        Ok(value)
+    }
+
+    fn select_target(
+        &mut self,
+        reference: &Reference,
+        current_path: &AbsolutePath,
+    ) -> Result<Value, Failure> {
+        match &reference.target {
+            Target::Path(path) | Target::Each(path) => {
+                if let Some(field) = path.strip_prefix('.')
+                    && let Some(element) = &self.each_element
+                {
+                    let value = super::value_at(element, &AbsolutePath::new(field)).cloned();
+                    return value.ok_or_else(|| {
+                        self.report_unresolved(reference, current_path);
+                        Failure::Missing
+                    });
+                }
+                let path = self.source_path(path, current_path);
+                self.lookup(reference, path, current_path)
+                    .map(Cow::into_owned)
+                    .ok_or(Failure::Missing)
+            }
+            Target::Concat(parts) => {
+                let mut joined = String::new();
+                for part in parts {
+                    match part {
+                        ConcatPart::Literal(text) => joined.push_str(text),
+                        ConcatPart::Path(path) => {
+                            // Missing parts are left empty
+                            let path = self.source_path(path, current_path);
+                            if let Some(value) = self.lookup(reference, path, current_path) {
+                                joined.push_str(&text_of(&value));
+                            }
+                        }
+                    }
+                }
+                Ok(Value::String(joined))
+            }
+            Target::ConcatArrays(paths) => {
+                let mut joined = Vec::new();
+                for path in paths {
+                    // Missing parts are left out, like parts that aren't arrays
+                    let path = self.source_path(path, current_path);
+                    match self.lookup(reference, path, current_path) {
+                        Some(Cow::Borrowed(Value::Array(items))) => joined.extend_from_slice(items),
+                        Some(Cow::Owned(Value::Array(items))) => joined.extend(items),
+                        Some(other) => self.problems.errors.push(ResolveError::KindMismatch {
+                            at: current_path.clone(),
+                            expected: ValueKind::Array,
+                            found: Some(ValueKind::of(&other)),
+                        }),
+                        None => {}
+                    }
+                }
+                Ok(Value::Array(joined))
+            }
+            Target::Spread(path) => {
+                let path = self.source_path(path, current_path);
+                let value = self
+                    .lookup(reference, path, current_path)
+                    .ok_or(Failure::Missing)?;
+                match value.is_object() {
+                    true => Ok(value.into_owned()),
+                    false => {
+                        self.problems.errors.push(ResolveError::KindMismatch {
+                            at: current_path.clone(),
+                            expected: ValueKind::Object,
+                            found: Some(ValueKind::of(&value)),
+                        });
+                        Err(Failure::Invalid)
+                    }
+                }
+            }
+            Target::SpreadKv(path) => {
+                let path = self.source_path(path, current_path);
+                match self
+                    .lookup(reference, path, current_path)
+                    .ok_or(Failure::Missing)?
+                {
+                    Cow::Borrowed(Value::Object(map)) => Ok(self.spread_kv(map)),
+                    Cow::Owned(Value::Object(map)) => Ok(self.spread_kv(&map)),
+                    _ => {
+                        self.report_unresolved(reference, current_path);
+                        Err(Failure::Invalid)
+                    }
+                }
+            }
+            Target::Key => match current_path.segments().last() {
+                Some(index) if self.in_array => {
+                    Ok(index.parse::<usize>().map_or(Value::Null, Value::from))
+                }
+                Some(key) => Ok(Value::String(key.to_string())),
+                None => {
+                    self.report_unresolved(reference, current_path);
+                    Err(Failure::Missing)
+                }
+            },
+            Target::Descendants(field, nth) => match (self.source.get_descendants(field), nth) {
+                (Some(values), None) => Ok(Value::Array(values)),
+                (Some(mut values), Some(nth)) if *nth < values.len() => {
+                    Ok(values.swap_remove(*nth))
+                }
+                _ => {
+                    self.report_unresolved(reference, current_path);
+                    Err(Failure::Missing)
+                }
+            },
+            #[cfg(feature = "random")]
+            Target::Random(generator) => match &mut self.rng {
+                Some(rng) => Ok(generator.generate(rng)),
+                None => {
+                    self.report_unresolved(reference, current_path);
+                    Err(Failure::Missing)
+                }
+            },
+            #[cfg(feature = "chrono")]
+            Target::Now(format) => match &self.now {
+                Some(now) => Ok(super::timestamp::format(now, format)),
+                None => {
+                    self.report_unresolved(reference, current_path);
+                    Err(Failure::Missing)
+                }
+            },
+        }
+    }
+
+    /// The text a value is inserted into a string as: strings as they are, arrays joined with
+    /// [`ResolveOptions::embed_array_join`] if set, and everything else as JSON
+    fn embeddable(&self, value: Value) -> String {
+        match (value, &self.options.embed_array_join) {
+            (Value::Array(arr), Some(separator)) => {
+                let parts: Vec<String> = arr.into_iter().map(|element| text_of(&element)).collect();
+                parts.join(separator)
+            }
+            (value, _) => text_of(&value),
+        }
+    }
+
+    /// Joins the entries of an object as `key=value` pairs. Strings are inserted as-is,
+    /// other values, nested objects and arrays included, are serialized as JSON.
+    fn spread_kv(&self, map: &serde_json::Map<String, Value>) -> Value {
+        let pairs: Vec<String> = map
+            .iter()
+            .map(|(key, value)| format!("{key}={}", text_of(value)))
+            .collect();
+        Value::String(pairs.join(&self.options.spread_kv_separator))
+    }
+
+    /// Extracts the body of a placeholder occupying the whole string, like "{path}".
+    /// Returns None if the string contains anything besides the placeholder.
+    fn standalone_reference<'t>(&self, text: &'t str) -> Option<&'t str> {
+        match self.syntax.find_placeholder(text, 0) {
+            Some(span)
+                if span.start == 0 && span.end == text.len() && !span.body_range().is_empty() =>
+            {
+                Some(&text[span.body_range()])
+            }
+            _ => None,
+        }
+    }
+
+    /// Whether the value is a standalone `{spread:...}` placeholder
+    fn is_spread(&self, value: &Value) -> bool {
+        value
+            .as_str()
+            .and_then(|text| self.standalone_reference(text))
+            .is_some_and(|body| body.starts_with(SPREAD_PREFIX))
+    }
+
+    /// The source path a path written in a placeholder points to
+    fn source_path(&self, path: &str, current_path: &AbsolutePath) -> AbsolutePath {
+        let path = self.syntax.slash_indices(path);
+        // Values pulled from the source refer to source paths already
+        let pulled = self
+            .pulling
+            .as_ref()
+            .is_some_and(|pulling| !pulling.is_empty());
+        match &self.source_root {
+            Some(root) if path.starts_with('/') && !pulled => match AbsolutePath::new(&path) {
+                path if path.as_str() == "/" => root.clone(),
+                path => root.join(&path),
+            },
+            _ => absolute_path(&path, current_path, self.source),
+        }
+    }
+
+    /// What a placeholder with a missing target turns into
+    fn missing_text(&self, reference: &Reference) -> String {
+        match &self.options.on_missing {
+            Missing::Keep => self.syntax.wrap(reference.text),
+            Missing::Annotate => self
+                .syntax
+                .wrap(&format!("{} /* missing */", reference.text)),
+            Missing::Comment(prefix) => format!("{prefix}{}", reference.text),
+        }
+    }
+
+    /// Finds the target of a placeholder in the source map
+    fn lookup(
+        &mut self,
+        reference: &Reference,
+        path: AbsolutePath,
+        current_path: &AbsolutePath,
+    ) -> Option<Cow<'a, Value>> {
+        if let Some(chain) = self.cycle_through(current_path, &path) {
+            self.problems.errors.push(ResolveError::Cycle {
+                at: current_path.clone(),
+                reference: self.reported(reference, current_path),
+                chain,
+            });
+            return None;
+        }
+
+        let value = match path.segments().any(|segment| segment == WILDCARD) {
+            true => self
+                .source
+                .get_matching(&path)
+                .map(|values| Cow::Owned(Value::Array(values))),
+            false => self
+                .source
+                .get(&path)
+                .or_else(|| self.fuzzy_lookup(reference, &path, current_path)),
+        };
+        let Some(value) = value else {
+            if reference.required {
+                self.report_required_empty(reference, current_path);
+            } else if reference.default.is_none() && !self.optional_paths.contains(&path) {
+                self.report_unresolved(reference, current_path);
+            }
+            return None;
+        };
+
+        let Some(pulling) = &mut self.pulling else {
+            self.check_orphaned_references(&value, current_path);
+            return Some(value);
+        };
+        if let Some(first) = pulling.iter().position(|pulled| *pulled == path) {
+            let chain = pulling[first..].to_vec();
+            self.problems.errors.push(ResolveError::Cycle {
+                at: current_path.clone(),
+                reference: self.reported(reference, current_path),
+                chain,
+            });
+            return Some(value);
+        }
+
+        // Placeholders in the pulled value are relative to its place in the source
+        pulling.push(path.clone());
+        let in_array = self.in_array;
+        let resolved = self.resolve(&value, &path);
+        self.in_array = in_array;
+        if let Some(pulling) = &mut self.pulling {
+            pulling.pop();
+        }
+        Some(Cow::Owned(resolved))
+    }
+
+    /// The value at the only path a single typo away from the missing `path`
+    fn fuzzy_lookup(
+        &mut self,
+        reference: &Reference,
+        path: &AbsolutePath,
+        current_path: &AbsolutePath,
+    ) -> Option<Cow<'a, Value>> {
+        let source = self.fuzzy_source?;
+        let used = closest_path(path, source.keys(), 1)?;
+        self.problems.warnings.push(Warning::FuzzyMatch {
+            at: current_path.clone(),
+            reference: reference.text.to_string(),
+            used: used.clone(),
+        });
+        source.get(used).map(Cow::Borrowed)
+    }
+
+    /// Applies the filters of the reference in order.
+    /// Returns None if one of them doesn't accept its input.
+    ///
+    /// Only resolved targets are filtered. A missing one goes to the missing handler as is,
+    /// so `{/items|count}` never counts nothing as zero.
+    fn apply_filters(
+        &mut self,
+        reference: &Reference,
+        mut value: Value,
+        current_path: &AbsolutePath,
+    ) -> Option<Value> {
+        for filter in &reference.filters {
+            match filter.apply(value) {
+                Some(filtered) => value = filtered,
+                None => {
+                    self.problems.errors.push(ResolveError::InvalidFilter {
+                        at: current_path.clone(),
+                        reference: self.reported(reference, current_path),
+                    });
+                    return None;
+                }
+            }
         }
-        Value::String(text) => {
-            // Handle standalone dependency (e.g., "{/absolute/path}")
-            if let Some(absolute_path) = extract_dependency(text) {
-                if let Some(resolved_value) = source_map.get(&absolute_path) {
-                    return resolved_value.clone(); // Fully resolve the standalone dependency
+        Some(value)
+    }
+
+    /// Warns about relative placeholders inside a value pulled from the source
+    fn check_orphaned_references(&mut self, value: &Value, current_path: &AbsolutePath) {
+        match value {
+            Value::Object(map) => map
+                .values()
+                .for_each(|value| self.check_orphaned_references(value, current_path)),
+            Value::Array(arr) => arr
+                .iter()
+                .for_each(|value| self.check_orphaned_references(value, current_path)),
+            Value::String(text) => {
+                let mut start_pos = 0;
+                while let Some(span) = self.syntax.find_placeholder(text, start_pos) {
+                    let reference = Reference::parse(&text[span.body_range()]);
+                    let is_relative = reference.paths().iter().any(|path| {
+                        !path.starts_with('/')
+                            && split_namespace(path).is_none()
+                            && split_fallback_namespace(path).is_none()
+                    });
+                    if !reference.text.is_empty() && is_relative {
+                        self.problems
+                            .warnings
+                            .push(Warning::OrphanedRelativeReference {
+                                at: current_path.clone(),
+                                reference: reference.text.to_string(),
+                            });
+                    }
+                    start_pos = span.end;
                 }
             }
+            _ => {}
+        }
+    }
+
+    /// The placeholder body as reported, see [`ValueResolver::with_absolute_reports`]
+    fn reported(&self, reference: &Reference, current_path: &AbsolutePath) -> String {
+        if !self.absolute_reports {
+            return reference.text.to_string();
+        }
+        // The same paths as those `make_deps_path_map` rewrites, and malformed `$..` selectors
+        // are kept as written
+        reference.rewrite_paths(|path| {
+            let element_field = path.starts_with('.') && self.each_element.is_some();
+            let selector = path.starts_with(DESCENDANTS_PREFIX);
+            let outside =
+                split_namespace(path).is_some() || split_fallback_namespace(path).is_some();
+            (!path.is_empty() && !element_field && !outside && !selector).then(|| {
+                self.syntax
+                    .write_path(&self.source_path(path, current_path))
+            })
+        })
+    }
+
+    fn report_required_empty(&mut self, reference: &Reference, current_path: &AbsolutePath) {
+        self.problems.errors.push(ResolveError::RequiredEmpty {
+            at: current_path.clone(),
+            reference: self.reported(reference, current_path),
+        });
+    }
+
+    fn report_unresolved(&mut self, reference: &Reference, current_path: &AbsolutePath) {
+        if !reference.text.is_empty() {
+            self.problems.errors.push(ResolveError::Unresolved {
+                at: current_path.clone(),
+                reference: self.reported(reference, current_path),
+            });
+        }
+    }
+}
+
+/// Why a placeholder couldn't be resolved
+enum Failure {
+    /// The target doesn't exist
+    Missing,
+    /// The target exists, but can't be cast or filtered as requested
+    Invalid,
+}
+
+/// Makes a placeholder path absolute, relative paths start from the field holding the placeholder.
+/// Any number of leading slashes anchors the path at the root, so `//x` is the same as `/x`.
+/// Namespaced paths like `@db:/host` point into the source registered under that namespace,
+/// and `db@/host` too if that source has the path, or to `/host` otherwise.
+fn absolute_path(path: &str, current_path: &AbsolutePath, source: &dyn Source) -> AbsolutePath {
+    if let Some((namespace, path)) = split_namespace(path) {
+        AbsolutePath::namespaced(namespace, path)
+    } else if let Some((namespace, path)) = split_fallback_namespace(path) {
+        let namespaced = AbsolutePath::namespaced(namespace, path);
+        match source.get(&namespaced) {
+            Some(_) => namespaced,
+            None => AbsolutePath::new(path),
+        }
+    } else if path.starts_with('/') {
+        AbsolutePath::new(path)
+    } else {
+        current_path.resolve_with(&RelativePath::new(path))
+    }
+}
+
+/// An object or array of the document, see [`ValueResolver::with_static_branches`]
+#[derive(Debug, Clone, Copy)]
+struct Branch {
+    /// Whether it holds placeholders or conditional keys, at any depth
+    dynamic: bool,
+    /// Number of objects and arrays beneath it
+    nested: usize,
+}
+
+/// Lists the objects and arrays of the value in resolution order, parents before children.
+/// Returns whether the value needs resolving.
+fn collect_branches(json: &Value, syntax: &Syntax, branches: &mut Vec<Branch>) -> bool {
+    let index = branches.len();
+    let dynamic = match json {
+        Value::Object(map) => {
+            branches.push(Branch {
+                dynamic: false,
+                nested: 0,
+            });
+            map.iter().fold(false, |dynamic, (key, value)| {
+                collect_branches(value, syntax, branches)
+                    | key.ends_with(CONDITIONAL_SUFFIX)
+                    | dynamic
+            })
+        }
+        Value::Array(arr) => {
+            branches.push(Branch {
+                dynamic: false,
+                nested: 0,
+            });
+            arr.iter().fold(false, |dynamic, value| {
+                collect_branches(value, syntax, branches) | dynamic
+            })
+        }
+        Value::String(text) => return syntax.contains_placeholder(text),
+        _ => return false,
+    };
+    branches[index] = Branch {
+        dynamic,
+        nested: branches.len() - index - 1,
+    };
+    dynamic
+}
+
+/// Suffix of object keys that are dropped when their value doesn't resolve, as in `"port?"`
+pub(crate) const CONDITIONAL_SUFFIX: char = '?';
 
-            // Handle embedded dependencies
-            resolve_embedded_refs(text, source_map)
+/// Whether a resolved value counts as absent for a conditional key: null, empty,
+/// or a string whose placeholders were left unresolved
+/// Size of the strings of the value along with the keys holding them, as `"key":"text",`,
+/// which is the most resolving them could take off the serialized output
+fn removable_len(json: &Value, key: Option<&str>) -> usize {
+    match json {
+        Value::Object(map) => map
+            .iter()
+            .map(|(key, value)| removable_len(value, Some(key)))
+            .sum(),
+        Value::Array(arr) => arr.iter().map(|value| removable_len(value, None)).sum(),
+        // The separator after the entry, and the key with its colon
+        Value::String(_) => {
+            super::serialized_len(json)
+                + 1
+                + key.map_or(0, |key| super::serialized_len(&Value::from(key)) + 1)
         }
-        _ => template.clone(), // Return all other values unchanged
+        _ => 0,
+    }
+}
+
+fn is_absent(original: &Value, resolved: &Value, syntax: &Syntax) -> bool {
+    let unresolved = match original {
+        Value::String(text) => resolved == original && syntax.contains_placeholder(text),
+        _ => false,
+    };
+    unresolved || is_empty(resolved)
+}
+
+/// Whether a value fails the `!` requirement: null, or an empty string, array or object
+fn is_empty(value: &Value) -> bool {
+    match value {
+        Value::Null => true,
+        Value::String(text) => text.is_empty(),
+        Value::Array(arr) => arr.is_empty(),
+        Value::Object(map) => map.is_empty(),
+        _ => false,
     }
 }
 
+/// Replaces runs of whitespace with single spaces, dropping it at both ends
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Text inserted into a string for the value: strings as they are, anything else as JSON
+fn text_of(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+pub(crate) fn resolve_values(json: &Value, context: &dyn Source) -> Value {
+    ValueResolver::new(context, &ResolveOptions::default()).resolve(json, &AbsolutePath::default())
+}
+
+#[cfg(test)]
+fn resolve_embedded_refs(text: &str, source_map: &dyn Source) -> Value {
+    ValueResolver::new(source_map, &ResolveOptions::default())
+        .resolve_embedded(text, &AbsolutePath::default())
+}
+
 #[cfg(test)]
 mod tests {
     use super::AbsolutePath;
@@ -150,6 +1127,52 @@ mod tests {
         assert_eq!(result, expected);
     }
 
+    #[test]
+    fn test_resolve_embedded_escapes() {
+        let source_map: HashMap<AbsolutePath, Value> = HashMap::from([
+            (AbsolutePath::new("/name"), serde_json::json!("Bob")),
+            (AbsolutePath::new("/price"), serde_json::json!(100)),
+        ]);
+
+        // Escaped braces around and between placeholders don't shift the ones after them
+        let input = r"\{name\} costs {/price}, \{/price\} {/name}\}{/name}";
+        assert_eq!(
+            resolve_embedded_refs(input, &source_map),
+            Value::String("{name} costs 100, {/price} Bob}Bob".to_string())
+        );
+    }
+
+    #[test]
+    fn test_resolve_embedded_scalars() {
+        let source_map: HashMap<AbsolutePath, Value> = HashMap::from([
+            (AbsolutePath::new("/count"), serde_json::json!(5)),
+            (AbsolutePath::new("/ratio"), serde_json::json!(0.25)),
+            (AbsolutePath::new("/enabled"), serde_json::json!(true)),
+            (AbsolutePath::new("/owner"), Value::Null),
+            (
+                AbsolutePath::new("/limits"),
+                serde_json::json!({ "max": 3 }),
+            ),
+        ]);
+
+        let cases = [
+            ("You have {/count} items", "You have 5 items"),
+            ("Ratio: {/ratio}!", "Ratio: 0.25!"),
+            ("enabled={/enabled}", "enabled=true"),
+            ("owner: {/owner}.", "owner: null."),
+            ("limits {/limits}", r#"limits {"max":3}"#),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(resolve_embedded_refs(text, &source_map), expected);
+        }
+
+        // A standalone placeholder keeps the type
+        assert_eq!(
+            resolve_values(&serde_json::json!("{/count}"), &source_map),
+            serde_json::json!(5)
+        );
+    }
+
     #[test]
     fn test_resolve_embedded_refs_with_invalid_paths() {
         // Sample source_map with correct absolute paths
@@ -394,4 +1417,1087 @@ mod tests {
 
         assert_eq!(resolved_json, expected_resolved);
     }
+
+    #[cfg(feature = "hash")]
+    #[test]
+    fn test_resolve_hash() {
+        let input = serde_json::json!({
+            "content": "hello",
+            "bundle": "app.{hash:/content}.js",
+            "key": "{/content|hash}",
+            "missing": "{hash:/nothing}"
+        });
+        let resolved = crate::resolve_json(&input);
+
+        assert_eq!(resolved["bundle"], "app.2cf24dba.js");
+        assert_eq!(resolved["key"], "2cf24dba");
+        assert_eq!(resolved["missing"], "{hash:/nothing}");
+        assert_eq!(crate::resolve_json(&input), resolved);
+    }
+
+    #[test]
+    fn test_resolve_group() {
+        let input = serde_json::json!({
+            "amount": 1234567,
+            "name": "Bob",
+            "total": "Total: {/amount|group} EUR",
+            "dotted": "{/amount|group:.}",
+            "text": "{/name|group}"
+        });
+        let resolved = crate::resolve_json(&input);
+
+        assert_eq!(resolved["total"], "Total: 1,234,567 EUR");
+        assert_eq!(resolved["dotted"], "1.234.567");
+        // Only numbers are grouped
+        assert_eq!(resolved["text"], "{/name|group}");
+    }
+
+    #[test]
+    fn test_resolve_bytes() {
+        let input = serde_json::json!({
+            "firmware": { "key": "DEADbeef", "bad": "xyz0" },
+            "key": "{bytes:/firmware/key}",
+            "piped": "{/firmware/key|bytes|count}",
+            "invalid": "{bytes:/firmware/bad}"
+        });
+        let resolved = crate::resolve_json(&input);
+
+        assert_eq!(resolved["key"], serde_json::json!([222, 173, 190, 239]));
+        assert_eq!(resolved["piped"], serde_json::json!(4));
+        // Invalid hex stays unresolved and is reported
+        assert_eq!(resolved["invalid"], "{bytes:/firmware/bad}");
+        assert_eq!(
+            crate::validate(&input),
+            vec![ResolveError::InvalidFilter {
+                at: AbsolutePath::new("/invalid"),
+                reference: "bytes:/firmware/bad".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_resolve_spread_kv() {
+        let input = serde_json::json!({
+            "params": { "page": 2, "q": "rust" },
+            "env": { "HOME": "/root", "limits": { "cpu": 2 } },
+            "query": "https://example.com/?{spread_kv:/params}",
+            "block": "{spread_kv:env}",
+            "scalar": "x={spread_kv:/params/q}"
+        });
+        let resolved = crate::resolve_json(&input);
+
+        assert_eq!(resolved["query"], "https://example.com/?page=2&q=rust");
+        // Nested objects are serialized as JSON
+        assert_eq!(resolved["block"], r#"HOME=/root&limits={"cpu":2}"#);
+        assert_eq!(resolved["scalar"], "x={spread_kv:/params/q}");
+
+        let options = ResolveOptions {
+            spread_kv_separator: "\n".to_string(),
+            ..Default::default()
+        };
+        let resolved = crate::resolve_json_with_options(&input, &options).unwrap();
+        assert_eq!(resolved["block"], "HOME=/root\nlimits={\"cpu\":2}");
+    }
+
+    #[test]
+    fn test_resolve_standalone_casts() {
+        let json = serde_json::json!({
+            "port": "{/env/port:int}",
+            "debug": "{/env/debug:bool}",
+            "embedded": "Port {/env/port:int}"
+        });
+
+        let context = HashMap::from([
+            (
+                AbsolutePath::new("/env/port"),
+                Value::String("8080".to_string()),
+            ),
+            (
+                AbsolutePath::new("/env/debug"),
+                Value::String("true".to_string()),
+            ),
+        ]);
+
+        let options = ResolveOptions::default();
+        let mut resolver = ValueResolver::new(&context, &options);
+        let resolved_json = resolver.resolve(&json, &AbsolutePath::default());
+
+        // Casts only apply to standalone placeholders
+        let expected_resolved = serde_json::json!({
+            "port": 8080,
+            "debug": true,
+            "embedded": "Port 8080"
+        });
+
+        assert_eq!(resolved_json, expected_resolved);
+        assert!(resolver.into_problems().errors.is_empty());
+    }
+
+    #[test]
+    fn test_resolve_standalone_invalid_cast() {
+        let json = serde_json::json!({ "port": "{/env/port:int}" });
+
+        let context = HashMap::from([(
+            AbsolutePath::new("/env/port"),
+            Value::String("abc".to_string()),
+        )]);
+
+        let options = ResolveOptions::default();
+        let mut resolver = ValueResolver::new(&context, &options);
+        let resolved_json = resolver.resolve(&json, &AbsolutePath::default());
+
+        // The placeholder stays literal and the failure is reported
+        assert_eq!(resolved_json, json);
+        assert_eq!(
+            resolver.into_problems().errors,
+            vec![ResolveError::InvalidCast {
+                at: AbsolutePath::new("/port"),
+                reference: "/env/port:int".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_pulled_relative_reference_is_reported() {
+        let json = serde_json::json!({
+            "standalone": "{/source/link}",
+            "embedded": "See {/source/text}"
+        });
+
+        let context = HashMap::from([
+            (
+                AbsolutePath::new("/source/link"),
+                serde_json::json!({ "href": "{../base}/index.html" }),
+            ),
+            (
+                AbsolutePath::new("/source/text"),
+                Value::String("{sibling}".to_string()),
+            ),
+        ]);
+
+        let options = ResolveOptions::default();
+        let mut resolver = ValueResolver::new(&context, &options);
+        let resolved_json = resolver.resolve(&json, &AbsolutePath::default());
+
+        // Relative placeholders are copied as they are
+        let expected_resolved = serde_json::json!({
+            "standalone": { "href": "{../base}/index.html" },
+            "embedded": "See {sibling}"
+        });
+        assert_eq!(resolved_json, expected_resolved);
+
+        assert_eq!(
+            resolver.into_problems().warnings,
+            vec![
+                Warning::OrphanedRelativeReference {
+                    at: AbsolutePath::new("/embedded"),
+                    reference: "sibling".to_string(),
+                },
+                Warning::OrphanedRelativeReference {
+                    at: AbsolutePath::new("/standalone"),
+                    reference: "../base".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_resolve_truncate_filter() {
+        let json = serde_json::json!({
+            "summary": "{/bio|truncate:8}",
+            "embedded": "Bio: {/bio|truncate:8}",
+            "number": "{/age|truncate:1}"
+        });
+
+        let context = HashMap::from([
+            (
+                AbsolutePath::new("/bio"),
+                Value::String("Software Engineer".to_string()),
+            ),
+            (AbsolutePath::new("/age"), Value::Number(30.into())),
+        ]);
+
+        let options = ResolveOptions::default();
+        let mut resolver = ValueResolver::new(&context, &options);
+        let resolved_json = resolver.resolve(&json, &AbsolutePath::default());
+
+        // Non-string targets are left unresolved
+        let expected_resolved = serde_json::json!({
+            "summary": "Software…",
+            "embedded": "Bio: Software…",
+            "number": "{/age|truncate:1}"
+        });
+        assert_eq!(resolved_json, expected_resolved);
+
+        assert_eq!(
+            resolver.into_problems().errors,
+            vec![ResolveError::InvalidFilter {
+                at: AbsolutePath::new("/number"),
+                reference: "/age|truncate:1".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_resolve_embedded_array_join() {
+        let json = serde_json::json!({
+            "tags": "Tags: {/tags}",
+            "mixed": "Mixed: {/mixed}"
+        });
+
+        let context = HashMap::from([
+            (
+                AbsolutePath::new("/tags"),
+                serde_json::json!(["a", "b", "c"]),
+            ),
+            (
+                AbsolutePath::new("/mixed"),
+                serde_json::json!(["a", 1, true, null, { "k": "v" }]),
+            ),
+        ]);
+
+        // Without a separator arrays are inserted as JSON
+        let default_options = ResolveOptions::default();
+        let mut resolver = ValueResolver::new(&context, &default_options);
+        assert_eq!(
+            resolver.resolve(&json, &AbsolutePath::default()),
+            serde_json::json!({
+                "tags": r#"Tags: ["a","b","c"]"#,
+                "mixed": r#"Mixed: ["a",1,true,null,{"k":"v"}]"#
+            })
+        );
+
+        let options = ResolveOptions {
+            embed_array_join: Some(", ".to_string()),
+            ..Default::default()
+        };
+        let mut resolver = ValueResolver::new(&context, &options);
+        let resolved_json = resolver.resolve(&json, &AbsolutePath::default());
+
+        let expected_resolved = serde_json::json!({
+            "tags": "Tags: a, b, c",
+            "mixed": r#"Mixed: a, 1, true, null, {"k":"v"}"#
+        });
+        assert_eq!(resolved_json, expected_resolved);
+    }
+
+    #[test]
+    fn test_same_reference_with_different_filters() {
+        let input = "{/n|upper} and {/n|lower}, then {/n}";
+
+        let source_map: HashMap<AbsolutePath, Value> = HashMap::from([(
+            AbsolutePath::new("/n"),
+            Value::String("MixedCase".to_string()),
+        )]);
+
+        // Every occurrence is resolved with its own filters
+        let result = resolve_embedded_refs(input, &source_map);
+        let expected = Value::String("MIXEDCASE and mixedcase, then MixedCase".to_string());
+
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_resolve_concat() {
+        let json = serde_json::json!({
+            "full_name": r#"{concat:/first," ",/last}"#,
+            "greeting": r#"Hello, {concat:/first,"-",/id}!"#,
+            "partial": r#"{concat:/first," ",/middle," ",/last}"#
+        });
+
+        let context = HashMap::from([
+            (
+                AbsolutePath::new("/first"),
+                Value::String("Ada".to_string()),
+            ),
+            (
+                AbsolutePath::new("/last"),
+                Value::String("Lovelace".to_string()),
+            ),
+            (AbsolutePath::new("/id"), Value::Number(7.into())),
+        ]);
+
+        let options = ResolveOptions::default();
+        let mut resolver = ValueResolver::new(&context, &options);
+        let resolved_json = resolver.resolve(&json, &AbsolutePath::default());
+
+        // Missing parts are left empty and reported
+        let expected_resolved = serde_json::json!({
+            "full_name": "Ada Lovelace",
+            "greeting": "Hello, Ada-7!",
+            "partial": "Ada  Lovelace"
+        });
+        assert_eq!(resolved_json, expected_resolved);
+
+        assert_eq!(
+            resolver.into_problems().errors,
+            vec![ResolveError::Unresolved {
+                at: AbsolutePath::new("/partial"),
+                reference: r#"concat:/first," ",/middle," ",/last"#.to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_resolve_relative_references_in_arrays() {
+        let template = serde_json::json!({
+            "items": [
+                { "label": "{name}", "text": "Item {name} costs {../1/price}" },
+                "{0/name}",
+                ["{../../title}"]
+            ]
+        });
+
+        let context = HashMap::from([
+            (
+                AbsolutePath::new("/items/0/name"),
+                Value::String("Apple".to_string()),
+            ),
+            (
+                AbsolutePath::new("/items/1/price"),
+                Value::String("$2".to_string()),
+            ),
+            (
+                AbsolutePath::new("/title"),
+                Value::String("Fruits".to_string()),
+            ),
+        ]);
+
+        // The base of a relative reference includes array indices
+        let resolved_json = resolve_values(&template, &context);
+        let expected_resolved = serde_json::json!({
+            "items": [
+                { "label": "Apple", "text": "Item Apple costs $2" },
+                "Apple",
+                ["Fruits"]
+            ]
+        });
+
+        assert_eq!(resolved_json, expected_resolved);
+    }
+
+    #[test]
+    fn test_resolve_keeps_big_integer_precision() {
+        let json = serde_json::json!({
+            "id": "{/source/id}",
+            "id_from_text": "{/source/id_text:int}",
+            "embedded": "Id: {/source/id_text}"
+        });
+
+        let digits = "12345678901234567890";
+        let big_integer: Value = serde_json::from_str(digits).unwrap();
+        let context = HashMap::from([
+            (AbsolutePath::new("/source/id"), big_integer.clone()),
+            (
+                AbsolutePath::new("/source/id_text"),
+                Value::String(digits.to_string()),
+            ),
+        ]);
+
+        let resolved_json = resolve_values(&json, &context);
+
+        assert_eq!(resolved_json["id"], big_integer);
+        assert_eq!(resolved_json["id_from_text"], big_integer);
+        assert_eq!(
+            resolved_json.to_string(),
+            format!(r#"{{"embedded":"Id: {digits}","id":{digits},"id_from_text":{digits}}}"#)
+        );
+    }
+
+    #[test]
+    fn test_double_slash_matches_single_slash() {
+        let template = serde_json::json!({
+            "nested": { "single": "{/x}", "double": "{//x}", "embedded": "({//x})" }
+        });
+
+        let context = HashMap::from([(AbsolutePath::new("/x"), Value::String("X".to_string()))]);
+
+        let resolved_json = resolve_values(&template, &context);
+        let expected_resolved = serde_json::json!({
+            "nested": { "single": "X", "double": "X", "embedded": "(X)" }
+        });
+
+        assert_eq!(resolved_json, expected_resolved);
+    }
+
+    #[test]
+    fn test_annotate_missing_targets() {
+        let json = serde_json::json!({
+            "standalone": "{/x}",
+            "cast": "{/x:int}",
+            "embedded": "Hello {/name}, {/x}!",
+            "invalid": "{/name:int}",
+            "empty": "{}"
+        });
+        let context = HashMap::from([(AbsolutePath::new("/name"), serde_json::json!("Bob"))]);
+
+        let options = ResolveOptions {
+            on_missing: Missing::Annotate,
+            ..Default::default()
+        };
+        let mut resolver = ValueResolver::new(&context, &options);
+        let resolved_json = resolver.resolve(&json, &AbsolutePath::default());
+
+        // Only missing targets are annotated, invalid casts stay unchanged
+        let expected_resolved = serde_json::json!({
+            "standalone": "{/x /* missing */}",
+            "cast": "{/x:int /* missing */}",
+            "embedded": "Hello Bob, {/x /* missing */}!",
+            "invalid": "{/name:int}",
+            "empty": "{}"
+        });
+        assert_eq!(resolved_json, expected_resolved);
+    }
+
+    #[test]
+    fn test_missing_filtered_references() {
+        let json = serde_json::json!({
+            "standalone": "{/missing|upper}",
+            "count": "{/missing|count}",
+            "embedded": "Hi {/missing|truncate:2}, {/name|upper}"
+        });
+        let context = HashMap::from([(AbsolutePath::new("/name"), serde_json::json!("Bob"))]);
+        let unresolved = |reference: &str| ResolveError::Unresolved {
+            at: AbsolutePath::new("/standalone"),
+            reference: reference.to_string(),
+        };
+
+        // Missing targets are reported as such, the filters never see them
+        let options = ResolveOptions::default();
+        let mut resolver = ValueResolver::new(&context, &options);
+        let resolved_json = resolver.resolve(&json, &AbsolutePath::default());
+        assert_eq!(
+            resolved_json,
+            serde_json::json!({
+                "standalone": "{/missing|upper}",
+                "count": "{/missing|count}",
+                "embedded": "Hi {/missing|truncate:2}, BOB"
+            })
+        );
+        let errors = resolver.into_problems().errors;
+        assert_eq!(errors.len(), 3);
+        assert!(
+            errors
+                .iter()
+                .all(|error| matches!(error, ResolveError::Unresolved { .. }))
+        );
+
+        let options = ResolveOptions {
+            on_missing: Missing::Annotate,
+            ..Default::default()
+        };
+        let mut resolver = ValueResolver::new(&context, &options);
+        let resolved_json = resolver.resolve(&json["standalone"], &AbsolutePath::default());
+        assert_eq!(
+            resolved_json,
+            serde_json::json!("{/missing|upper /* missing */}")
+        );
+
+        let options = ResolveOptions {
+            strict: true,
+            ..Default::default()
+        };
+        let input = serde_json::json!({ "standalone": "{/missing|upper}" });
+        assert_eq!(
+            crate::resolve_json_with_options(&input, &options),
+            Err(unresolved("/missing|upper"))
+        );
+    }
+
+    #[test]
+    fn test_collapse_whitespace() {
+        let json = serde_json::json!({
+            "title": "{/first} {/middle} {/last} ",
+            "static": "kept  as   is "
+        });
+        let context = HashMap::from([
+            (AbsolutePath::new("/first"), serde_json::json!("Ada")),
+            (AbsolutePath::new("/middle"), serde_json::json!("")),
+            (AbsolutePath::new("/last"), serde_json::json!("Lovelace")),
+        ]);
+
+        let resolved_json = resolve_values(&json, &context);
+        assert_eq!(resolved_json["title"], serde_json::json!("Ada  Lovelace "));
+
+        // Only strings changed by resolution are collapsed
+        let options = ResolveOptions {
+            collapse_whitespace: true,
+            ..Default::default()
+        };
+        let mut resolver = ValueResolver::new(&context, &options);
+        let resolved_json = resolver.resolve(&json, &AbsolutePath::default());
+        assert_eq!(
+            resolved_json,
+            serde_json::json!({ "title": "Ada Lovelace", "static": "kept  as   is " })
+        );
+    }
+
+    #[test]
+    fn test_splice_object_refs() {
+        let json = serde_json::json!({
+            "service": {
+                "defaults": "{/defaults}",
+                "overrides": "{/overrides}",
+                "port": 9090,
+                "name": "{/name}"
+            }
+        });
+        let context = HashMap::from([
+            (
+                AbsolutePath::new("/defaults"),
+                serde_json::json!({ "port": 80, "host": "localhost", "tls": false }),
+            ),
+            (
+                AbsolutePath::new("/overrides"),
+                serde_json::json!({ "tls": true }),
+            ),
+            (
+                AbsolutePath::new("/name"),
+                serde_json::json!({ "first": "Ada" }),
+            ),
+        ]);
+
+        // Without the option the objects are nested as usual
+        let resolved_json = resolve_values(&json, &context);
+        assert_eq!(
+            resolved_json["service"]["defaults"]["port"],
+            serde_json::json!(80)
+        );
+
+        // Explicit keys win, and later splices override earlier ones
+        let options = ResolveOptions {
+            splice_object_refs: true,
+            ..Default::default()
+        };
+        let mut resolver = ValueResolver::new(&context, &options);
+        let resolved_json = resolver.resolve(&json, &AbsolutePath::default());
+        assert_eq!(
+            resolved_json,
+            serde_json::json!({
+                "service": {
+                    "port": 9090,
+                    "host": "localhost",
+                    "tls": true,
+                    "first": "Ada"
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn test_spread_objects() {
+        let json = serde_json::json!({
+            "a_base": "{spread:/defaults}",
+            "b_env": "{spread:/production}",
+            "port": 9090,
+            "invalid": "{spread:/name}"
+        });
+        let context = HashMap::from([
+            (
+                AbsolutePath::new("/defaults"),
+                serde_json::json!({ "port": 80, "host": "localhost", "tls": false }),
+            ),
+            (
+                AbsolutePath::new("/production"),
+                serde_json::json!({ "host": "example.com", "tls": true }),
+            ),
+            (AbsolutePath::new("/name"), serde_json::json!("Bob")),
+        ]);
+
+        let options = ResolveOptions::default();
+        let mut resolver = ValueResolver::new(&context, &options);
+        let resolved_json = resolver.resolve(&json, &AbsolutePath::default());
+
+        // Explicit keys override spread ones, and later spreads override earlier ones
+        assert_eq!(
+            resolved_json,
+            serde_json::json!({
+                "port": 9090,
+                "host": "example.com",
+                "tls": true,
+                "invalid": "{spread:/name}"
+            })
+        );
+        assert_eq!(
+            resolver.into_problems().errors,
+            vec![ResolveError::KindMismatch {
+                at: AbsolutePath::new("/invalid"),
+                expected: ValueKind::Object,
+                found: Some(ValueKind::String),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_fuzzy_lookup() {
+        let json = serde_json::json!({
+            "typo": "{/user/nmae}",
+            "missing_letter": "Hi {/user/nme}",
+            "far": "{/user/title}",
+            "exact": "{/user/name}"
+        });
+        let context = HashMap::from([
+            (AbsolutePath::new("/user/name"), serde_json::json!("Bob")),
+            (AbsolutePath::new("/user/age"), serde_json::json!(42)),
+        ]);
+        let options = ResolveOptions {
+            fuzzy: true,
+            ..Default::default()
+        };
+        let mut resolver = ValueResolver::new(&context, &options).with_fuzzy_source(&context);
+        let resolved_json = resolver.resolve(&json, &AbsolutePath::default());
+
+        // A swap of two letters takes two edits
+        assert_eq!(
+            resolved_json,
+            serde_json::json!({
+                "typo": "{/user/nmae}",
+                "missing_letter": "Hi Bob",
+                "far": "{/user/title}",
+                "exact": "Bob"
+            })
+        );
+        let problems = resolver.into_problems();
+        assert_eq!(
+            problems.warnings,
+            vec![Warning::FuzzyMatch {
+                at: AbsolutePath::new("/missing_letter"),
+                reference: "/user/nme".to_string(),
+                used: AbsolutePath::new("/user/name"),
+            }]
+        );
+        assert_eq!(problems.errors.len(), 2);
+
+        // Without the option the source isn't used
+        let options = ResolveOptions::default();
+        let mut resolver = ValueResolver::new(&context, &options).with_fuzzy_source(&context);
+        let resolved_json = resolver.resolve(&json, &AbsolutePath::default());
+        assert_eq!(resolved_json["missing_letter"], "Hi {/user/nme}");
+    }
+
+    #[test]
+    fn test_fuzzy_resolve_json() {
+        let input = serde_json::json!({
+            "db": { "host": "db.local", "port": 5432 },
+            "url": "postgres://{/db/hst}/",
+            "port": "{/db/prt}",
+            "far": "{/database/host}"
+        });
+        let options = ResolveOptions {
+            fuzzy: true,
+            ..Default::default()
+        };
+        let resolved = crate::resolve_json_with_options(&input, &options).unwrap();
+        assert_eq!(resolved["url"], "postgres://db.local/");
+        assert_eq!(resolved["port"], 5432);
+        assert_eq!(resolved["far"], "{/database/host}");
+
+        let template = serde_json::json!({ "host": "{/db/hos}" });
+        assert_eq!(
+            crate::resolve_template_with_options(&template, &input, &options),
+            Ok(serde_json::json!({ "host": "db.local" }))
+        );
+    }
+
+    #[test]
+    fn test_required_references() {
+        let context = HashMap::from([
+            (AbsolutePath::new("/name"), serde_json::json!("Bob")),
+            (AbsolutePath::new("/nickname"), serde_json::json!("")),
+            (AbsolutePath::new("/avatar"), Value::Null),
+        ]);
+        let options = ResolveOptions::default();
+        let resolve = |json: Value| {
+            let mut resolver = ValueResolver::new(&context, &options);
+            let resolved = resolver.resolve(&json, &AbsolutePath::new("/field"));
+            (resolved, resolver.into_problems().errors)
+        };
+
+        let (resolved, errors) = resolve(serde_json::json!("Hi {/name!}"));
+        assert_eq!(resolved, serde_json::json!("Hi Bob"));
+        assert!(errors.is_empty());
+
+        // Outside of strict mode the values resolve as usual
+        let required_empty = |reference: &str| ResolveError::RequiredEmpty {
+            at: AbsolutePath::new("/field"),
+            reference: reference.to_string(),
+        };
+        let (resolved, errors) = resolve(serde_json::json!("{/missing!}"));
+        assert_eq!(resolved, serde_json::json!("{/missing!}"));
+        assert_eq!(errors, vec![required_empty("/missing!")]);
+
+        let (resolved, errors) = resolve(serde_json::json!("{/nickname!}"));
+        assert_eq!(resolved, serde_json::json!(""));
+        assert_eq!(errors, vec![required_empty("/nickname!")]);
+
+        let (resolved, errors) = resolve(serde_json::json!("{/avatar!}"));
+        assert_eq!(resolved, Value::Null);
+        assert_eq!(errors, vec![required_empty("/avatar!")]);
+    }
+
+    #[test]
+    fn test_comment_missing_targets() {
+        let json = serde_json::json!({
+            "script": "export HOST={/host}\n{/extra}\necho done",
+            "standalone": "{/extra}"
+        });
+        let context = HashMap::from([(AbsolutePath::new("/host"), serde_json::json!("localhost"))]);
+
+        let options = ResolveOptions {
+            on_missing: Missing::Comment("# ".to_string()),
+            ..Default::default()
+        };
+        let mut resolver = ValueResolver::new(&context, &options);
+        let resolved_json = resolver.resolve(&json, &AbsolutePath::default());
+
+        let expected_resolved = serde_json::json!({
+            "script": "export HOST=localhost\n# /extra\necho done",
+            "standalone": "# /extra"
+        });
+        assert_eq!(resolved_json, expected_resolved);
+    }
+
+    #[test]
+    fn test_resolve_slices() {
+        let json = serde_json::json!({
+            "slice": "{/items/1:3}",
+            "clamped": "{/items/2:10}",
+            "empty": "{/items/3:1}",
+            "embedded": "Items: {/items/0:2}",
+            "not_array": "{/name/0:1}"
+        });
+        let context = HashMap::from([
+            (
+                AbsolutePath::new("/items"),
+                serde_json::json!(["a", "b", "c", "d"]),
+            ),
+            (AbsolutePath::new("/name"), serde_json::json!("Bob")),
+        ]);
+
+        let resolved_json = resolve_values(&json, &context);
+
+        // Embedded slices are refused like any other array
+        let expected_resolved = serde_json::json!({
+            "slice": ["b", "c"],
+            "clamped": ["c", "d"],
+            "empty": [],
+            "embedded": r#"Items: ["a","b"]"#,
+            "not_array": "{/name/0:1}"
+        });
+        assert_eq!(resolved_json, expected_resolved);
+    }
+
+    #[test]
+    fn test_resolve_concat_arrays() {
+        let json = serde_json::json!({
+            "all": "{concat_arrays:/base,/extra}",
+            "mixed": "{concat_arrays:/base,/name,/missing}"
+        });
+        let context = HashMap::from([
+            (AbsolutePath::new("/base"), serde_json::json!([1, 2])),
+            (AbsolutePath::new("/extra"), serde_json::json!([{ "x": 3 }])),
+            (AbsolutePath::new("/name"), serde_json::json!("Bob")),
+        ]);
+
+        let options = ResolveOptions::default();
+        let mut resolver = ValueResolver::new(&context, &options);
+        let resolved_json = resolver.resolve(&json, &AbsolutePath::default());
+
+        // Parts that are missing or not arrays are left out and reported
+        assert_eq!(
+            resolved_json,
+            serde_json::json!({ "all": [1, 2, { "x": 3 }], "mixed": [1, 2] })
+        );
+        assert_eq!(
+            resolver.into_problems().errors,
+            vec![
+                ResolveError::KindMismatch {
+                    at: AbsolutePath::new("/mixed"),
+                    expected: ValueKind::Array,
+                    found: Some(ValueKind::String),
+                },
+                ResolveError::Unresolved {
+                    at: AbsolutePath::new("/mixed"),
+                    reference: "concat_arrays:/base,/name,/missing".to_string(),
+                },
+            ]
+        );
+
+        let options = ResolveOptions {
+            strict: true,
+            ..Default::default()
+        };
+        let input =
+            serde_json::json!({ "name": "Bob", "list": [1], "all": "{concat_arrays:/list,/name}" });
+        assert!(matches!(
+            crate::resolve_json_with_options(&input, &options),
+            Err(ResolveError::KindMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn test_resolve_descendants() {
+        let json = serde_json::json!({
+            "names": "{$..name}",
+            "emails": "{$..email}",
+            "embedded": "Names: {$..name}"
+        });
+        let mut context = HashMap::new();
+        crate::parsing::collect_all_absolute_paths(
+            &serde_json::json!({
+                "name": "root",
+                "users": [
+                    { "name": "Alice", "pets": [{ "name": "Rex" }] },
+                    { "name": "Bob" }, {}, {}, {}, {}, {}, {}, {},
+                    { "name": "Judy" }
+                ]
+            }),
+            &AbsolutePath::new("/"),
+            &mut context,
+        );
+
+        let resolved_json = resolve_values(&json, &context);
+
+        // Indices are ordered numerically, so Judy at index 10 comes last
+        let expected_resolved = serde_json::json!({
+            "names": ["root", "Alice", "Rex", "Bob", "Judy"],
+            "emails": [],
+            "embedded": r#"Names: ["root","Alice","Rex","Bob","Judy"]"#
+        });
+        assert_eq!(resolved_json, expected_resolved);
+    }
+
+    #[test]
+    fn test_resolve_nth_descendant() {
+        let json = serde_json::json!({
+            "first": "{$..url[0]}",
+            "second": "{$..url[1]}",
+            "missing": "{$..url[3]}",
+            "malformed": "{$..url[}",
+            "embedded": "Mirror: {$..url[2]}"
+        });
+        let mut context = HashMap::new();
+        crate::parsing::collect_all_absolute_paths(
+            &serde_json::json!({
+                "main": { "url": "https://a.example" },
+                "mirrors": [{ "url": "https://b.example" }, { "name": "none" }],
+                "spare": { "url": "https://c.example" }
+            }),
+            &AbsolutePath::new("/"),
+            &mut context,
+        );
+
+        let resolved_json = resolve_values(&json, &context);
+
+        // Fields are searched in document order, and out of range selects nothing
+        let expected_resolved = serde_json::json!({
+            "first": "https://a.example",
+            "second": "https://b.example",
+            "missing": "{$..url[3]}",
+            "malformed": "{$..url[}",
+            "embedded": "Mirror: https://c.example"
+        });
+        assert_eq!(resolved_json, expected_resolved);
+
+        // A selector that doesn't parse is reported like any missing target
+        let options = ResolveOptions {
+            strict: true,
+            ..Default::default()
+        };
+        let input = serde_json::json!({ "url": "https://a.example", "all": "{$..url[}" });
+        assert!(matches!(
+            crate::resolve_json_with_options(&input, &options),
+            Err(ResolveError::Unresolved { reference, .. }) if reference == "$..url["
+        ));
+    }
+
+    #[test]
+    fn test_trim_then_typed() {
+        let json = serde_json::json!({
+            "padded": "  {/count}  ",
+            "newline": "{/count}\n",
+            "prefixed": "x {/count}",
+            "text": " {/name} "
+        });
+        let context = HashMap::from([
+            (AbsolutePath::new("/count"), serde_json::json!(3)),
+            (AbsolutePath::new("/name"), serde_json::json!("Bob")),
+        ]);
+        let options = ResolveOptions {
+            trim_then_typed: true,
+            ..Default::default()
+        };
+
+        let resolved_json =
+            ValueResolver::new(&context, &options).resolve(&json, &AbsolutePath::default());
+
+        // Only whitespace is dropped, other surrounding text keeps the reference embedded
+        let expected_resolved = serde_json::json!({
+            "padded": 3,
+            "newline": 3,
+            "prefixed": "x 3",
+            "text": "Bob"
+        });
+        assert_eq!(resolved_json, expected_resolved);
+
+        // Without the option whitespace makes the reference embedded, so numbers become text
+        let resolved_json = resolve_values(&json, &context);
+        assert_eq!(resolved_json["padded"], serde_json::json!("  3  "));
+        assert_eq!(resolved_json["text"], serde_json::json!(" Bob "));
+    }
+
+    #[test]
+    fn test_resolve_recursive_sources() {
+        let json = serde_json::json!({
+            "url": "{/service/url}",
+            "config": "{/service}",
+            "loop": "{/a}"
+        });
+        let mut context = HashMap::new();
+        crate::parsing::collect_all_absolute_paths(
+            &serde_json::json!({
+                "host": "example.com",
+                "service": { "port": "8080", "url": "http://{/host}:{port}/" },
+                "a": "{/b}",
+                "b": "{/a}"
+            }),
+            &AbsolutePath::new("/"),
+            &mut context,
+        );
+
+        let options = ResolveOptions::default();
+        let mut resolver = ValueResolver::new(&context, &options).with_recursive_sources();
+        let resolved_json = resolver.resolve(&json, &AbsolutePath::default());
+
+        // The cycle is cut where `/a` is pulled for the second time, inserting its raw value
+        let expected_resolved = serde_json::json!({
+            "url": "http://example.com:8080/",
+            "config": { "port": "8080", "url": "http://example.com:8080/" },
+            "loop": "{/b}"
+        });
+        assert_eq!(resolved_json, expected_resolved);
+        assert_eq!(
+            resolver.into_problems().errors,
+            vec![ResolveError::Cycle {
+                at: AbsolutePath::new("/b"),
+                reference: "/a".to_string(),
+                chain: vec![AbsolutePath::new("/a"), AbsolutePath::new("/b")],
+            }]
+        );
+    }
+
+    #[test]
+    fn test_resolve_key() {
+        let json = serde_json::json!({
+            "services": {
+                "web": { "name": "{$key}", "label": "service {$key|upper}" },
+                "db": { "name": "{$key}" }
+            },
+            "steps": ["{$key}", { "title": "{$key}" }, "step {$key}"],
+        });
+
+        let resolved_json = resolve_values(&json, &HashMap::new());
+
+        // Indices are numbers, embedded into strings as digits
+        let expected_resolved = serde_json::json!({
+            "services": {
+                "web": { "name": "name", "label": "service LABEL" },
+                "db": { "name": "name" }
+            },
+            "steps": [0, { "title": "title" }, "step 2"],
+        });
+        assert_eq!(resolved_json, expected_resolved);
+
+        // The root has no key
+        assert_eq!(
+            resolve_values(&serde_json::json!("{$key}"), &HashMap::new()),
+            serde_json::json!("{$key}")
+        );
+    }
+
+    #[test]
+    fn test_resolve_each() {
+        let mut context = HashMap::new();
+        crate::parsing::collect_all_absolute_paths(
+            &serde_json::json!({
+                "users": [{ "name": "Ann", "role": "admin" }, { "name": "Bob" }],
+                "sep": "; ",
+                "count": 2
+            }),
+            &AbsolutePath::new("/"),
+            &mut context,
+        );
+        let resolve = |text: &str| {
+            let options = ResolveOptions::default();
+            let mut resolver = ValueResolver::new(&context, &options);
+            let resolved = resolver.resolve_embedded(text, &AbsolutePath::default());
+            (resolved, resolver.into_problems().errors)
+        };
+
+        let (resolved, errors) = resolve("Users: {#each /users}{.name|upper}{/sep}{/each}end");
+        assert_eq!(resolved, serde_json::json!("Users: ANN; BOB; end"));
+        assert_eq!(errors, vec![]);
+
+        // Missing fields of an element are reported, other elements still expand
+        let (resolved, errors) = resolve("{#each /users}{.name}={.role}, {/each}");
+        assert_eq!(resolved, serde_json::json!("Ann=admin, Bob={.role}, "));
+        assert_eq!(
+            errors,
+            vec![ResolveError::Unresolved {
+                at: AbsolutePath::default(),
+                reference: ".role".to_string(),
+            }]
+        );
+
+        // Loops over anything but an array, or without an end, are left as they are
+        let (resolved, errors) = resolve("{#each /count}{.name}{/each} {#each /users}");
+        assert_eq!(
+            resolved,
+            serde_json::json!("{#each /count}{.name}{/each} {#each /users}")
+        );
+        assert_eq!(errors.len(), 2);
+
+        // A loop on its own still makes a string
+        assert_eq!(
+            resolve_values(&serde_json::json!("{#each /users}{.name}{/each}"), &context),
+            serde_json::json!("AnnBob")
+        );
+
+        let document = serde_json::json!({
+            "tags": ["a", "b"],
+            "line": "{#each /tags}[{.}]{/each}"
+        });
+        assert_eq!(crate::resolve_json(&document)["line"], "[a][b]");
+    }
+
+    #[test]
+    fn test_resolve_defaults() {
+        let json = serde_json::json!({
+            "config": r#"{/config|{"timeout":30}}"#,
+            "hosts": r#"{/hosts|["a","b"]}"#,
+            "name": "{/name|anonymous}",
+            "greeting": "Hello {/name|stranger}!",
+            "present": "{/port|8080}",
+            "quoted": r#"{/mode|"fast"}"#,
+            "flag": "{/debug|true}",
+            "empty": "{/suffix|}",
+            "embedded": r#"timeout={/timeout|30}, debug={/debug|false}, retry={/retry|{"max": 3}}{/suffix|}, port={/port|1}"#
+        });
+        let context = HashMap::from([(AbsolutePath::new("/port"), serde_json::json!(443))]);
+
+        let resolved_json = resolve_values(&json, &context);
+
+        let expected_resolved = serde_json::json!({
+            "config": { "timeout": 30 },
+            "hosts": ["a", "b"],
+            "name": "anonymous",
+            "greeting": "Hello stranger!",
+            "present": 443,
+            "quoted": "fast",
+            "flag": true,
+            "empty": "",
+            "embedded": r#"timeout=30, debug=false, retry={"max": 3}, port=443"#
+        });
+        assert_eq!(resolved_json, expected_resolved);
+    }
 }
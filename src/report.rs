@@ -0,0 +1,472 @@
+use crate::{
+    error::ResolveError,
+    options::ResolveOptions,
+    parsing::{path::AbsolutePath, stringify_scalars},
+};
+use serde_json::{Value, json};
+use std::{fmt, io::Write, time::Duration};
+
+/// A suspicious but non-fatal finding made while resolving placeholders.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Warning {
+    /// A value pulled from the source contains a relative placeholder,
+    /// which has no meaning outside of the source document
+    OrphanedRelativeReference {
+        /// Path of the field that received the value
+        at: AbsolutePath,
+        /// Body of the relative placeholder, without braces
+        reference: String,
+    },
+    /// A relative placeholder points to a path that doesn't exist, but a similar one does
+    SimilarPath {
+        /// Path of the field containing the placeholder
+        at: AbsolutePath,
+        /// The relative path
+        reference: String,
+        /// The existing path it was likely meant to be
+        suggestion: AbsolutePath,
+    },
+    /// A placeholder with a missing target was resolved with a path one typo away,
+    /// see [`crate::ResolveOptions::fuzzy`]
+    FuzzyMatch {
+        /// Path of the field containing the placeholder
+        at: AbsolutePath,
+        /// Placeholder body, without braces
+        reference: String,
+        /// The existing path used instead
+        used: AbsolutePath,
+    },
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Warning::OrphanedRelativeReference { at, reference } => {
+                write!(
+                    f,
+                    "value pulled into {at} contains relative reference {{{reference}}}"
+                )
+            }
+            Warning::SimilarPath {
+                at,
+                reference,
+                suggestion,
+            } => write!(
+                f,
+                "reference {{{reference}}} at {at} doesn't exist, did you mean {suggestion}?"
+            ),
+            Warning::FuzzyMatch {
+                at,
+                reference,
+                used,
+            } => {
+                write!(f, "reference {{{reference}}} at {at} resolved with {used}")
+            }
+        }
+    }
+}
+
+/// Time spent in each phase of [`crate::resolve_json_timed`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PhaseTimings {
+    /// Finding the placeholders and the paths they depend on
+    pub map_build: Duration,
+    /// Rewriting relative placeholders as absolute ones
+    pub expand: Duration,
+    /// Collecting the referenced values
+    pub extract: Duration,
+    /// Replacing the placeholders
+    pub resolve: Duration,
+}
+
+/// A placeholder left as written, because its target is missing, can't be converted
+/// or leads back to the field holding it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Unresolved {
+    /// Path of the field containing the placeholder
+    pub at: AbsolutePath,
+    /// Placeholder body, without braces
+    pub reference: String,
+    /// The chain of [`ResolveError::Cycle`] if the placeholder leads back to its own field
+    pub cycle: Option<Vec<AbsolutePath>>,
+}
+
+/// An unresolved placeholder as [`crate::resolve_json_checked`] reports it, the same as [`Unresolved`]
+pub type UnresolvedRef = Unresolved;
+
+/// Figures about a resolution run of [`crate::resolve_json_full`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ResolveStats {
+    /// Strings of the input holding placeholders
+    pub fields: usize,
+    /// Placeholders in those strings, counting every occurrence
+    pub placeholders: usize,
+    /// Time spent in each phase
+    pub timings: PhaseTimings,
+}
+
+/// Everything [`crate::resolve_json_full`] finds out about a document.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResolveOutcome {
+    /// The resolved document, shaped by the options as usual
+    pub value: Value,
+    /// Placeholders that stay unresolved, in document order
+    pub unresolved: Vec<Unresolved>,
+    /// Warnings of the resolution, followed by the typo suggestions
+    /// of [`crate::suggest_similar_paths`]
+    pub warnings: Vec<Warning>,
+    /// Figures about the run
+    pub stats: ResolveStats,
+}
+
+/// Output format of [`write_validation_report`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportFormat {
+    /// One line per problem, for people reading CI logs
+    Text,
+    /// A JSON array with one `{ "kind", "at", "reference", "message" }` object per problem
+    Json,
+}
+
+/// Writes every problem [`crate::validate`] finds in the document, in the given format,
+/// followed by the typo suggestions of [`crate::suggest_similar_paths`].
+///
+/// A document without problems produces no lines in the text format and `[]` in JSON.
+///
+/// ## Example:
+/// ```
+/// use serde_json::json;
+/// use json_deref::{write_validation_report, ReportFormat};
+///
+/// let mut report = Vec::new();
+/// write_validation_report(&json!({ "a": "{/missing}" }), &mut report, ReportFormat::Text).unwrap();
+///
+/// assert_eq!(String::from_utf8(report).unwrap(), "error: unresolved reference {/missing} at /a\n");
+/// ```
+pub fn write_validation_report<W: Write>(
+    input: &Value,
+    mut writer: W,
+    format: ReportFormat,
+) -> std::io::Result<()> {
+    let errors = crate::validate(input);
+    let warnings = crate::suggest_similar_paths(input);
+    match format {
+        ReportFormat::Text => {
+            for error in &errors {
+                writeln!(writer, "error: {error}")?;
+            }
+            for warning in &warnings {
+                writeln!(writer, "warning: {warning}")?;
+            }
+        }
+        ReportFormat::Json => {
+            let entries: Vec<Value> = errors
+                .iter()
+                .map(report_entry)
+                .chain(warnings.iter().map(warning_entry))
+                .collect();
+            serde_json::to_writer_pretty(&mut writer, &entries)?;
+            writeln!(writer)?;
+        }
+    }
+    Ok(())
+}
+
+/// Machine-readable form of a problem
+fn report_entry(error: &ResolveError) -> Value {
+    let (kind, at, reference) = match error {
+        ResolveError::Unresolved { at, reference } => ("unresolved", at, Some(reference)),
+        ResolveError::InvalidCast { at, reference } => ("invalid_cast", at, Some(reference)),
+        ResolveError::InvalidFilter { at, reference } => ("invalid_filter", at, Some(reference)),
+        ResolveError::EscapesRoot { at, reference } => ("escapes_root", at, Some(reference)),
+        ResolveError::RequiredEmpty { at, reference } => ("required_empty", at, Some(reference)),
+        ResolveError::KindMismatch { at, .. } => ("kind_mismatch", at, None),
+        ResolveError::Cycle { at, reference, .. } => ("cycle", at, Some(reference)),
+        ResolveError::OutputTooLarge { at, .. } => ("output_too_large", at, None),
+        ResolveError::ReferenceTooLong { at, .. } => ("reference_too_long", at, None),
+    };
+    json!({
+        "kind": kind,
+        "at": at.as_str(),
+        "reference": reference,
+        "message": error.to_string(),
+    })
+}
+
+/// Machine-readable form of a warning
+fn warning_entry(warning: &Warning) -> Value {
+    let mut entry = match warning {
+        Warning::OrphanedRelativeReference { at, reference } => json!({
+            "kind": "orphaned_relative_reference",
+            "at": at.as_str(),
+            "reference": reference,
+        }),
+        Warning::SimilarPath {
+            at,
+            reference,
+            suggestion,
+        } => json!({
+            "kind": "similar_path",
+            "at": at.as_str(),
+            "reference": reference,
+            "suggestion": suggestion.as_str(),
+        }),
+        Warning::FuzzyMatch {
+            at,
+            reference,
+            used,
+        } => json!({
+            "kind": "fuzzy_match",
+            "at": at.as_str(),
+            "reference": reference,
+            "used": used.as_str(),
+        }),
+    };
+    entry["message"] = json!(warning.to_string());
+    entry
+}
+
+/// Everything reported by a single resolution run
+#[derive(Debug, Default)]
+pub(crate) struct Problems {
+    pub(crate) errors: Vec<ResolveError>,
+    pub(crate) warnings: Vec<Warning>,
+}
+
+impl Problems {
+    /// Placeholders the errors leave unresolved
+    pub(crate) fn unresolved(&self) -> Vec<Unresolved> {
+        self.errors
+            .iter()
+            .filter_map(|error| match error {
+                ResolveError::Unresolved { at, reference }
+                | ResolveError::InvalidCast { at, reference }
+                | ResolveError::InvalidFilter { at, reference } => Some(Unresolved {
+                    at: at.clone(),
+                    reference: reference.clone(),
+                    cycle: None,
+                }),
+                ResolveError::Cycle {
+                    at,
+                    reference,
+                    chain,
+                } => Some(Unresolved {
+                    at: at.clone(),
+                    reference: reference.clone(),
+                    cycle: Some(chain.clone()),
+                }),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Turns the collected problems into an error if the options ask for strictness.
+    /// Otherwise applies the options shaping the final output.
+    pub(crate) fn into_result(
+        self,
+        resolved: Value,
+        options: &ResolveOptions,
+    ) -> Result<Value, ResolveError> {
+        match self.errors.into_iter().next() {
+            Some(error) if options.strict => Err(error),
+            _ if options.stringify_scalars => Ok(stringify_scalars(resolved)),
+            _ => Ok(resolved),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timings_are_populated() {
+        let input = json!({ "a": { "b": "value" }, "c": "{a/b}", "d": "Embedded {/a/b}" });
+        let (resolved, timings) = crate::resolve_json_timed(&input);
+
+        assert_eq!(resolved, crate::resolve_json(&input));
+        let total = timings.map_build + timings.expand + timings.extract + timings.resolve;
+        assert!(total > std::time::Duration::ZERO);
+    }
+
+    #[test]
+    fn test_resolve_json_checked() {
+        let input = json!({
+            "db": { "host": "localhost" },
+            "url": "{/db/host}:{/db/port}/{/db/name}",
+            "user": "{/db/user}",
+            "port": "{/db/host:int}"
+        });
+        assert_eq!(
+            crate::resolve_json_checked(&input),
+            Err(vec![
+                UnresolvedRef {
+                    at: AbsolutePath::new("/port"),
+                    reference: "/db/host:int".to_string(),
+                    cycle: None,
+                },
+                UnresolvedRef {
+                    at: AbsolutePath::new("/url"),
+                    reference: "/db/port".to_string(),
+                    cycle: None,
+                },
+                UnresolvedRef {
+                    at: AbsolutePath::new("/url"),
+                    reference: "/db/name".to_string(),
+                    cycle: None,
+                },
+                UnresolvedRef {
+                    at: AbsolutePath::new("/user"),
+                    reference: "/db/user".to_string(),
+                    cycle: None,
+                },
+            ])
+        );
+
+        // Fields referring to each other can't be resolved, whatever the order
+        let input = json!({ "a": "{/b}", "b": "{/a}", "c": "{/c}" });
+        assert_eq!(
+            crate::resolve_json_checked(&input),
+            Err(vec![
+                UnresolvedRef {
+                    at: AbsolutePath::new("/a"),
+                    reference: "/b".to_string(),
+                    cycle: Some(vec![AbsolutePath::new("/a"), AbsolutePath::new("/b")]),
+                },
+                UnresolvedRef {
+                    at: AbsolutePath::new("/b"),
+                    reference: "/a".to_string(),
+                    cycle: Some(vec![AbsolutePath::new("/b"), AbsolutePath::new("/a")]),
+                },
+                UnresolvedRef {
+                    at: AbsolutePath::new("/c"),
+                    reference: "/c".to_string(),
+                    cycle: Some(vec![AbsolutePath::new("/c")]),
+                },
+            ])
+        );
+
+        // Empty braces aren't placeholders
+        let input = json!({ "empty": "{}", "copy": "{/empty}" });
+        assert_eq!(
+            crate::resolve_json_checked(&input),
+            Ok(json!({ "empty": "{}", "copy": "{}" }))
+        );
+    }
+
+    #[test]
+    fn test_resolve_json_full() {
+        let input = json!({
+            "user": { "name": "Bob", "greeting": "Hi {nmae}, meet {/user/name}" },
+            "port": "{/user/name:int}",
+            "title": "{/user/name}"
+        });
+        let outcome = crate::resolve_json_full(&input, &Default::default());
+
+        assert_eq!(
+            outcome.value,
+            json!({
+                "user": { "name": "Bob", "greeting": "Hi {nmae}, meet Bob" },
+                "port": "{/user/name:int}",
+                "title": "Bob"
+            })
+        );
+        assert_eq!(
+            outcome.unresolved,
+            vec![
+                Unresolved {
+                    at: AbsolutePath::new("/port"),
+                    reference: "/user/name:int".to_string(),
+                    cycle: None,
+                },
+                Unresolved {
+                    at: AbsolutePath::new("/user/greeting"),
+                    reference: "/user/nmae".to_string(),
+                    cycle: None,
+                },
+            ]
+        );
+        assert_eq!(
+            outcome.warnings,
+            vec![Warning::SimilarPath {
+                at: AbsolutePath::new("/user/greeting"),
+                reference: "nmae".to_string(),
+                suggestion: AbsolutePath::new("/user/name"),
+            }]
+        );
+        assert_eq!(outcome.stats.fields, 3);
+        assert_eq!(outcome.stats.placeholders, 4);
+        assert!(outcome.stats.timings.resolve > Duration::ZERO);
+
+        // Strictness doesn't turn the problems into an error
+        let strict = ResolveOptions {
+            strict: true,
+            stringify_scalars: true,
+            ..Default::default()
+        };
+        let outcome = crate::resolve_json_full(&json!({ "a": 1, "b": "{/a}" }), &strict);
+        assert_eq!(outcome.value, json!({ "a": "1", "b": "1" }));
+        assert!(outcome.unresolved.is_empty());
+    }
+
+    #[test]
+    fn test_validation_report_formats() {
+        let input = json!({
+            "name": "abc",
+            "a": { "b": "{../../../x}" },
+            "port": "{/name:int}",
+            "url": "http://{/host}/"
+        });
+
+        let mut text = Vec::new();
+        write_validation_report(&input, &mut text, ReportFormat::Text).unwrap();
+        assert_eq!(
+            String::from_utf8(text).unwrap(),
+            "error: reference {../../../x} at /a/b climbs above the root\n\
+             error: unresolved reference {/x} at /a/b\n\
+             error: can't cast {/name:int} at /port\n\
+             error: unresolved reference {/host} at /url\n"
+        );
+
+        let mut report = Vec::new();
+        write_validation_report(&input, &mut report, ReportFormat::Json).unwrap();
+        let entries: Value = serde_json::from_slice(&report).unwrap();
+        assert_eq!(entries.as_array().unwrap().len(), 4);
+        assert_eq!(
+            entries[2],
+            json!({
+                "kind": "invalid_cast",
+                "at": "/port",
+                "reference": "/name:int",
+                "message": "can't cast {/name:int} at /port"
+            })
+        );
+        assert_eq!(entries[0]["kind"], json!("escapes_root"));
+
+        let mut empty = Vec::new();
+        write_validation_report(&json!({ "a": 1 }), &mut empty, ReportFormat::Json).unwrap();
+        assert_eq!(String::from_utf8(empty).unwrap(), "[]\n");
+    }
+
+    #[test]
+    fn test_validation_report_suggests_similar_paths() {
+        let input = json!({
+            "user": { "name": "Bob", "greeting": "Hi {nmae}" },
+            "title": "{user/name}"
+        });
+
+        let mut text = Vec::new();
+        write_validation_report(&input, &mut text, ReportFormat::Text).unwrap();
+        assert_eq!(
+            String::from_utf8(text).unwrap(),
+            "error: unresolved reference {/user/nmae} at /user/greeting\n\
+             warning: reference {nmae} at /user/greeting doesn't exist, did you mean /user/name?\n"
+        );
+
+        let mut report = Vec::new();
+        write_validation_report(&input, &mut report, ReportFormat::Json).unwrap();
+        let entries: Value = serde_json::from_slice(&report).unwrap();
+        assert_eq!(entries[1]["kind"], json!("similar_path"));
+        assert_eq!(entries[1]["suggestion"], json!("/user/name"));
+    }
+}
@@ -0,0 +1,468 @@
+use crate::{
+    ResolveError, ResolveOptions, Source,
+    parsing::{
+        collect_all_absolute_paths, index::PathIndex, optional_paths, path::AbsolutePath,
+        values_resolving::ValueResolver,
+    },
+    source::compare_segments,
+};
+use serde_json::Value;
+use std::{borrow::Cow, collections::HashMap, sync::Arc};
+
+/// Assembles a source from several pieces before resolving templates against it.
+///
+/// Every entry is addressed by its absolute path. Entries are independent of each other:
+/// inserting `/db/port` doesn't change the value found at `/db`, and a later entry
+/// with the same path replaces the earlier one.
+///
+/// ## Example:
+/// ```
+/// use serde_json::json;
+/// use json_deref::SourceBuilder;
+///
+/// let resolver = SourceBuilder::new()
+///     .insert("/env/name", json!("prod"))
+///     .merge(&json!({ "db": { "host": "localhost" } }))
+///     .build();
+///
+/// let resolved = resolver.resolve(&json!({ "url": "{/env/name}.{/db/host}" }));
+/// assert_eq!(resolved, json!({ "url": "prod.localhost" }));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct SourceBuilder {
+    source_map: PathIndex,
+}
+
+impl SourceBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `value` at the absolute `path`, along with everything nested in it
+    pub fn insert(mut self, path: &str, value: Value) -> Self {
+        let path = AbsolutePath::new(path);
+        self.source_map.insert(&path, value.clone());
+        collect_all_absolute_paths(&value, &path, &mut self.source_map);
+        self
+    }
+
+    /// Adds every value of a whole document, addressed from its root
+    pub fn merge(mut self, document: &Value) -> Self {
+        collect_all_absolute_paths(document, &AbsolutePath::new("/"), &mut self.source_map);
+        self
+    }
+
+    pub fn build(self) -> Resolver {
+        Resolver {
+            source_map: self.source_map,
+        }
+    }
+}
+
+/// A source ready to resolve any number of templates, see [`SourceBuilder`].
+///
+/// The source is indexed by path segments, which keeps lookups fast for large sources
+/// and allows wildcards: `{/users/*/name}` resolves to an array of all matching values,
+/// in the order they were added. `{$..name}` collects the values of all `name` fields
+/// at any depth, parents before their children.
+#[derive(Debug, Clone, Default)]
+pub struct Resolver {
+    source_map: PathIndex,
+}
+
+impl Resolver {
+    /// Resolves the template, leaving the placeholders it can't resolve unchanged
+    pub fn resolve(&self, template: &Value) -> Value {
+        ValueResolver::new(&self.source_map, &Default::default())
+            .resolve(template, &Default::default())
+    }
+
+    /// Resolves the template with the given options, see [`crate::resolve_template_with_options`]
+    pub fn resolve_with_options(
+        &self,
+        template: &Value,
+        options: &ResolveOptions,
+    ) -> Result<Value, ResolveError> {
+        let mut resolver = ValueResolver::new(&self.source_map, options)
+            .with_optional_paths(optional_paths(template))
+            .with_source_root(&options.source_root);
+        let resolved = resolver.resolve(template, &Default::default());
+        resolver.into_problems().into_result(resolved, options)
+    }
+}
+
+/// A source shared by any number of threads, each resolving its own templates.
+///
+/// Cloning the resolver only clones a pointer, so every request handler can hold one.
+/// Source values are kept behind their own [`Arc`] and are read in place while resolving:
+/// a value is copied once, when it's inserted into the resolved template.
+///
+/// ## Example:
+/// ```
+/// use serde_json::json;
+/// use json_deref::SharedResolver;
+///
+/// let resolver = SharedResolver::new(&json!({ "site": { "name": "demo" } }));
+///
+/// let handler = resolver.clone();
+/// let page = std::thread::spawn(move || handler.resolve(&json!({ "title": "{/site/name}" })))
+///     .join()
+///     .unwrap();
+/// assert_eq!(page, json!({ "title": "demo" }));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct SharedResolver {
+    source_map: Arc<SharedSource>,
+}
+
+/// Source values behind their own pointers, lent out without copying them
+#[derive(Debug, Default)]
+struct SharedSource(HashMap<AbsolutePath, Arc<Value>>);
+
+impl Source for SharedSource {
+    fn get(&self, path: &AbsolutePath) -> Option<Cow<'_, Value>> {
+        self.0.get(path).map(|value| Cow::Borrowed(value.as_ref()))
+    }
+
+    fn get_descendants(&self, field: &str) -> Option<Vec<Value>> {
+        let mut found: Vec<(Vec<Cow<str>>, &Value)> = self
+            .0
+            .iter()
+            .filter(|(path, _)| path.segments().last().as_deref() == Some(field))
+            .map(|(path, value)| (path.segments().collect(), value.as_ref()))
+            .collect();
+        found.sort_by(|(a, _), (b, _)| compare_segments(a, b));
+        Some(found.into_iter().map(|(_, value)| value.clone()).collect())
+    }
+}
+
+impl SharedResolver {
+    /// Indexes every value of `document`, addressed from its root
+    pub fn new(document: &Value) -> Self {
+        let mut source_map = HashMap::new();
+        collect_all_absolute_paths(document, &AbsolutePath::new("/"), &mut source_map);
+        SharedResolver {
+            source_map: Arc::new(SharedSource(
+                source_map
+                    .into_iter()
+                    .map(|(path, value)| (path, Arc::new(value)))
+                    .collect(),
+            )),
+        }
+    }
+
+    /// Resolves the template, leaving the placeholders it can't resolve unchanged
+    pub fn resolve(&self, template: &Value) -> Value {
+        ValueResolver::new(self.source_map.as_ref(), &Default::default())
+            .resolve(template, &Default::default())
+    }
+
+    /// Resolves the template with the given options, see [`crate::resolve_template_with_options`]
+    pub fn resolve_with_options(
+        &self,
+        template: &Value,
+        options: &ResolveOptions,
+    ) -> Result<Value, ResolveError> {
+        let mut resolver = ValueResolver::new(self.source_map.as_ref(), options)
+            .with_optional_paths(optional_paths(template))
+            .with_source_root(&options.source_root);
+        let resolved = resolver.resolve(template, &Default::default());
+        resolver.into_problems().into_result(resolved, options)
+    }
+}
+
+/// Resolves templates against several sources, each registered under its own namespace.
+///
+/// A placeholder selects the source with an `@namespace:` prefix, like `{@db:/host}`.
+/// Casts and filters work as usual, e.g. `{@db:/port:int}`. Placeholders with an unknown
+/// namespace or path stay unchanged, or are reported in strict mode.
+///
+/// Plain paths like `{/host}` read the default source, added with
+/// [`MultiSourceResolver::with_default_source`]. `{db@/host}` reads the `db` source
+/// if it has the path, and the default source otherwise.
+///
+/// ## Example:
+/// ```
+/// use serde_json::json;
+/// use json_deref::MultiSourceResolver;
+///
+/// let resolver = MultiSourceResolver::new()
+///     .with_source("db", &json!({ "host": "localhost" }))
+///     .with_source("app", &json!({ "name": "demo" }));
+///
+/// let resolved = resolver.resolve(&json!({ "title": "{@app:/name} on {@db:/host}" }));
+/// assert_eq!(resolved, json!({ "title": "demo on localhost" }));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct MultiSourceResolver {
+    resolver: Resolver,
+}
+
+impl MultiSourceResolver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers `document` under `namespace`, replacing the source previously registered there
+    pub fn with_source(mut self, namespace: &str, document: &Value) -> Self {
+        let root = AbsolutePath::namespaced(namespace, "/");
+        let source_map = &mut self.resolver.source_map;
+        source_map.remove(&root);
+        source_map.insert(&root, document.clone());
+        collect_all_absolute_paths(document, &root, source_map);
+        self
+    }
+
+    /// Adds every value of `document` to the default source, read by placeholders without
+    /// a namespace and as the fallback of `{namespace@/path}`
+    pub fn with_default_source(mut self, document: &Value) -> Self {
+        collect_all_absolute_paths(
+            document,
+            &AbsolutePath::new("/"),
+            &mut self.resolver.source_map,
+        );
+        self
+    }
+
+    /// Resolves the template, leaving the placeholders it can't resolve unchanged
+    pub fn resolve(&self, template: &Value) -> Value {
+        self.resolver.resolve(template)
+    }
+
+    /// Resolves the template with the given options, see [`crate::resolve_template_with_options`]
+    pub fn resolve_with_options(
+        &self,
+        template: &Value,
+        options: &ResolveOptions,
+    ) -> Result<Value, ResolveError> {
+        self.resolver.resolve_with_options(template, options)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_build_from_inserts_and_merge() {
+        let resolver = SourceBuilder::new()
+            .insert("/db/host", json!("localhost"))
+            .insert("/db/port", json!(5432))
+            .insert("/features", json!({ "search": true }))
+            .merge(&json!({ "app": { "name": "demo" }, "db": { "port": 6543 } }))
+            .build();
+
+        let template = json!({
+            "host": "{/db/host}",
+            "port": "{/db/port}",
+            "search": "{/features/search}",
+            "title": "{/app/name}",
+            "missing": "{/db/user}"
+        });
+
+        assert_eq!(
+            resolver.resolve(&template),
+            json!({
+                "host": "localhost",
+                "port": 6543,
+                "search": true,
+                "title": "demo",
+                "missing": "{/db/user}"
+            })
+        );
+
+        let strict = ResolveOptions {
+            strict: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            resolver.resolve_with_options(&template, &strict),
+            Err(ResolveError::Unresolved {
+                at: AbsolutePath::new("/missing"),
+                reference: "/db/user".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn test_resolve_from_namespaced_sources() {
+        let resolver = MultiSourceResolver::new()
+            .with_source("db", &json!({ "host": "localhost", "port": "5432" }))
+            .with_source("app", &json!({ "name": "old" }))
+            .with_source("app", &json!({ "name": "demo" }));
+
+        let template = json!({
+            "url": "{@app:/name}@{@db:/host}",
+            "port": "{@db:/port:int}",
+            "db": "{@db:/}",
+            "unknown_path": "{@db:/user}",
+            "unknown_namespace": "{@cache:/host}"
+        });
+
+        assert_eq!(
+            resolver.resolve(&template),
+            json!({
+                "url": "demo@localhost",
+                "port": 5432,
+                "db": { "host": "localhost", "port": "5432" },
+                "unknown_path": "{@db:/user}",
+                "unknown_namespace": "{@cache:/host}"
+            })
+        );
+
+        let strict = ResolveOptions {
+            strict: true,
+            ..Default::default()
+        };
+        let template = json!({ "host": "{@cache:/host}" });
+        assert_eq!(
+            resolver.resolve_with_options(&template, &strict),
+            Err(ResolveError::Unresolved {
+                at: AbsolutePath::new("/host"),
+                reference: "@cache:/host".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn test_resolve_with_fallback_namespace() {
+        let resolver = MultiSourceResolver::new()
+            .with_source("db", &json!({ "host": "db.internal" }))
+            .with_default_source(&json!({ "host": "localhost", "port": 5432 }));
+
+        let template = json!({
+            "host": "{db@/host}",
+            "port": "{db@/port}",
+            "url": "{db@/host}:{cache@/host}",
+            "user": "{db@/user}"
+        });
+        assert_eq!(
+            resolver.resolve(&template),
+            json!({
+                "host": "db.internal",
+                "port": 5432,
+                "url": "db.internal:localhost",
+                "user": "{db@/user}"
+            })
+        );
+    }
+
+    #[test]
+    fn test_shared_resolver_across_threads() {
+        let resolver = SharedResolver::new(&json!({
+            "db": { "host": "localhost", "port": "5432" },
+            "users": [{ "name": "Alice" }, { "name": "Bob" }]
+        }));
+
+        let pages: Vec<Value> = std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..4)
+                .map(|i| {
+                    let resolver = resolver.clone();
+                    scope.spawn(move || {
+                        let template = json!({
+                            "user": format!("{{/users/{}/name}}", i % 2),
+                            "db": "{/db}",
+                            "port": "{/db/port:int}",
+                            "names": "{$..name}"
+                        });
+                        resolver.resolve(&template)
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap())
+                .collect()
+        });
+
+        for (i, page) in pages.iter().enumerate() {
+            assert_eq!(
+                page,
+                &json!({
+                    "user": if i % 2 == 0 { "Alice" } else { "Bob" },
+                    "db": { "host": "localhost", "port": "5432" },
+                    "port": 5432,
+                    "names": ["Alice", "Bob"]
+                })
+            );
+        }
+
+        let strict = ResolveOptions {
+            strict: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            resolver.resolve_with_options(&json!({ "user": "{/db/user}" }), &strict),
+            Err(ResolveError::Unresolved {
+                at: AbsolutePath::new("/user"),
+                reference: "/db/user".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn test_resolve_wildcards() {
+        let resolver = SourceBuilder::new()
+            .merge(&json!({
+                "users": [
+                    { "name": "Alice", "role": "admin" },
+                    { "name": "Bob" }
+                ]
+            }))
+            .insert("/teams/core/lead", json!("Alice"))
+            .insert("/teams/web/lead", json!("Carol"))
+            .build();
+
+        let template = json!({
+            "names": "{/users/*/name}",
+            "roles": "{/users/*/role}",
+            "leads": "{/teams/*/lead}",
+            "none": "{/groups/*}"
+        });
+        assert_eq!(
+            resolver.resolve(&template),
+            json!({
+                "names": ["Alice", "Bob"],
+                "roles": ["admin"],
+                "leads": ["Alice", "Carol"],
+                "none": []
+            })
+        );
+    }
+
+    #[test]
+    fn test_count_wildcard_matches() {
+        let resolver = SourceBuilder::new()
+            .merge(&json!({
+                "users": [{ "id": 1 }, { "id": 2 }, { "name": "Carol" }, { "id": 4 }]
+            }))
+            .build();
+
+        let template = json!({
+            "total_users": "{/users/*/id|count}",
+            "total_groups": "{/groups/*/id|count}"
+        });
+        assert_eq!(
+            resolver.resolve(&template),
+            json!({ "total_users": 3, "total_groups": 0 })
+        );
+    }
+
+    #[test]
+    fn test_resolve_descendants() {
+        let resolver = SourceBuilder::new()
+            .merge(&json!({
+                "org": { "name": "Acme", "teams": [{ "name": "core", "lead": { "name": "Alice" } }] }
+            }))
+            .insert("/extra/name", json!("Bob"))
+            .build();
+
+        let resolved = resolver.resolve(&json!({ "names": "{$..name}" }));
+        // Keys of merged objects are walked in sorted order, so `lead` comes before `name`
+        assert_eq!(
+            resolved,
+            json!({ "names": ["Acme", "Alice", "core", "Bob"] })
+        );
+    }
+}
@@ -0,0 +1,142 @@
+use crate::parsing::{index::PathIndex, path::AbsolutePath};
+use serde_json::Value;
+use std::{borrow::Cow, cmp::Ordering, collections::HashMap};
+
+/// Anything placeholders can be resolved against.
+///
+/// Lookups are made with absolute paths, one per referenced path and occurrence.
+pub trait Source {
+    /// The value at `path`, if there is one
+    fn get(&self, path: &AbsolutePath) -> Option<Cow<'_, Value>>;
+
+    /// The values at all paths matching `pattern`, in which `*` segments match any key or index.
+    /// Returns None if the source doesn't support wildcards, which is the default.
+    fn get_matching(&self, _pattern: &AbsolutePath) -> Option<Vec<Value>> {
+        None
+    }
+
+    /// The values of all fields named `field`, at any depth, in document order.
+    /// Returns None if the source can't be walked, which is the default.
+    fn get_descendants(&self, _field: &str) -> Option<Vec<Value>> {
+        None
+    }
+}
+
+impl Source for HashMap<AbsolutePath, Value> {
+    fn get(&self, path: &AbsolutePath) -> Option<Cow<'_, Value>> {
+        HashMap::get(self, path).map(Cow::Borrowed)
+    }
+
+    fn get_descendants(&self, field: &str) -> Option<Vec<Value>> {
+        let mut found: Vec<(Vec<Cow<str>>, &Value)> = self
+            .iter()
+            .filter(|(path, _)| path.segments().last().as_deref() == Some(field))
+            .map(|(path, value)| (path.segments().collect(), value))
+            .collect();
+        found.sort_by(|(a, _), (b, _)| compare_segments(a, b));
+        Some(found.into_iter().map(|(_, value)| value.clone()).collect())
+    }
+}
+
+impl Source for PathIndex {
+    fn get(&self, path: &AbsolutePath) -> Option<Cow<'_, Value>> {
+        PathIndex::get(self, path).map(Cow::Borrowed)
+    }
+
+    fn get_matching(&self, pattern: &AbsolutePath) -> Option<Vec<Value>> {
+        Some(self.matching(pattern).into_iter().cloned().collect())
+    }
+
+    fn get_descendants(&self, field: &str) -> Option<Vec<Value>> {
+        Some(self.descendants(field).into_iter().cloned().collect())
+    }
+}
+
+/// Orders paths as they appear in a document: array indices numerically, keys alphabetically
+pub(crate) fn compare_segments(a: &[Cow<str>], b: &[Cow<str>]) -> Ordering {
+    a.iter()
+        .zip(b)
+        .map(|(a, b)| match (a.parse::<usize>(), b.parse::<usize>()) {
+            (Ok(a), Ok(b)) => a.cmp(&b),
+            _ => a.cmp(b),
+        })
+        .find(|ordering| ordering.is_ne())
+        .unwrap_or_else(|| a.len().cmp(&b.len()))
+}
+
+/// A source computing values on demand, e.g. to decrypt secrets only when they're referenced.
+///
+/// The closure is called for every lookup, without caching the results.
+///
+/// ## Example:
+/// ```
+/// use serde_json::json;
+/// use json_deref::{resolve_template, FnSource};
+///
+/// let source = FnSource::new(|path| match path.as_str() {
+///     "/secrets/token" => Some(json!("decrypted")),
+///     _ => None,
+/// });
+///
+/// let resolved = resolve_template(&json!({ "token": "{/secrets/token}" }), &source);
+/// assert_eq!(resolved, json!({ "token": "decrypted" }));
+/// ```
+#[derive(Debug, Clone)]
+pub struct FnSource<F> {
+    f: F,
+}
+
+impl<F> FnSource<F>
+where
+    F: Fn(&AbsolutePath) -> Option<Value>,
+{
+    pub fn new(f: F) -> Self {
+        FnSource { f }
+    }
+}
+
+impl<F> Source for FnSource<F>
+where
+    F: Fn(&AbsolutePath) -> Option<Value>,
+{
+    fn get(&self, path: &AbsolutePath) -> Option<Cow<'_, Value>> {
+        (self.f)(path).map(Cow::Owned)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::cell::Cell;
+
+    #[test]
+    fn test_fn_source_computes_on_demand() {
+        let calls = Cell::new(0);
+        let source = FnSource::new(|path| {
+            calls.set(calls.get() + 1);
+            let name = path.as_str().strip_prefix("/secrets/")?;
+            Some(json!(name.to_uppercase()))
+        });
+
+        let template = json!({
+            "db": "{/secrets/db}",
+            "api": "key={/secrets/api}",
+            "again": "{/secrets/db}",
+            "missing": "{/other}",
+            "plain": "no placeholders"
+        });
+
+        assert_eq!(
+            crate::resolve_template(&template, &source),
+            json!({
+                "db": "DB",
+                "api": "key=API",
+                "again": "DB",
+                "missing": "{/other}",
+                "plain": "no placeholders"
+            })
+        );
+        assert_eq!(calls.get(), 4);
+    }
+}
@@ -0,0 +1,262 @@
+use crate::{
+    ResolveError, ResolveOptions,
+    parsing::{
+        FIELDS_KEY, optional_paths,
+        path::AbsolutePath,
+        reference::Syntax,
+        values_resolving::{CONDITIONAL_SUFFIX, ValueResolver},
+    },
+    report::Problems,
+    source::Source,
+};
+use serde_json::Value;
+use std::{borrow::Cow, collections::HashSet};
+
+/// A template prepared once to be applied to many sources.
+///
+/// [`crate::resolve_template_with_source`] walks the whole template and indexes the whole
+/// source on every call. A compiled template remembers where its placeholders are, and
+/// looks values up directly in each source document, which pays off when a server fills
+/// the same template for many requests. The results are the same.
+///
+/// ## Example:
+/// ```
+/// use serde_json::json;
+/// use json_deref::CompiledTemplate;
+///
+/// let template = CompiledTemplate::new(&json!({
+///     "greeting": "Hello, {/user/name}!",
+///     "id": "{/user/id}",
+///     "static": { "version": 2 }
+/// }));
+///
+/// let resolved = template.apply(&json!({ "user": { "name": "Bob", "id": 7 } }));
+/// assert_eq!(resolved, json!({ "greeting": "Hello, Bob!", "id": 7, "static": { "version": 2 } }));
+///
+/// let resolved = template.apply(&json!({ "user": { "name": "Alice", "id": 8 } }));
+/// assert_eq!(resolved["greeting"], json!("Hello, Alice!"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct CompiledTemplate {
+    template: Value,
+    /// The parts of the template holding placeholders, in document order
+    fields: Vec<Field>,
+    optional_paths: HashSet<AbsolutePath>,
+}
+
+/// A string holding placeholders, or an object with conditional keys, resolved as a whole
+#[derive(Debug, Clone)]
+struct Field {
+    at: AbsolutePath,
+    /// How to reach the field from the root of the template
+    steps: Vec<Step>,
+    in_array: bool,
+}
+
+#[derive(Debug, Clone)]
+enum Step {
+    Key(String),
+    Index(usize),
+}
+
+impl CompiledTemplate {
+    /// Finds the placeholders of the template
+    pub fn new(template: &Value) -> Self {
+        let mut fields = Vec::new();
+        collect_fields(
+            template,
+            &mut Vec::new(),
+            &Default::default(),
+            false,
+            &mut fields,
+        );
+        CompiledTemplate {
+            template: template.clone(),
+            fields,
+            optional_paths: optional_paths(template),
+        }
+    }
+
+    /// Fills the template from the source, leaving the placeholders it can't resolve unchanged
+    pub fn apply(&self, source: &Value) -> Value {
+        self.apply_collecting(source, &Default::default()).0
+    }
+
+    /// Fills the template from the source with the given options,
+    /// see [`crate::resolve_template_with_options`]
+    pub fn apply_with_options(
+        &self,
+        source: &Value,
+        options: &ResolveOptions,
+    ) -> Result<Value, ResolveError> {
+        let (resolved, problems) = self.apply_collecting(source, options);
+        problems.into_result(resolved, options)
+    }
+
+    fn apply_collecting(&self, source: &Value, options: &ResolveOptions) -> (Value, Problems) {
+        let source = DocumentSource(source);
+        let mut resolver = ValueResolver::new(&source, options)
+            .with_optional_paths(self.optional_paths.clone())
+            .with_source_root(&options.source_root);
+
+        let mut resolved = self.template.clone();
+        for field in &self.fields {
+            let slot = field
+                .steps
+                .iter()
+                .fold(&mut resolved, |value, step| match step {
+                    Step::Key(key) => &mut value[key.as_str()],
+                    Step::Index(index) => &mut value[*index],
+                });
+            *slot = resolver.resolve_within(slot, &field.at, field.in_array);
+        }
+        (resolved, resolver.into_problems())
+    }
+}
+
+fn collect_fields(
+    json: &Value,
+    steps: &mut Vec<Step>,
+    current_path: &AbsolutePath,
+    in_array: bool,
+    fields: &mut Vec<Field>,
+) {
+    let mut add_field = |steps: &[Step]| {
+        fields.push(Field {
+            at: current_path.clone(),
+            steps: steps.to_vec(),
+            in_array,
+        })
+    };
+    match json {
+        // Conditional keys are decided by the object holding them
+        Value::Object(map) if map.keys().any(|key| key.ends_with(CONDITIONAL_SUFFIX)) => {
+            add_field(steps)
+        }
+        Value::Object(map) => {
+            for (key, value) in map {
+                steps.push(Step::Key(key.clone()));
+                collect_fields(value, steps, &current_path.append(key), false, fields);
+                steps.pop();
+            }
+        }
+        Value::Array(arr) => {
+            for (i, value) in arr.iter().enumerate() {
+                steps.push(Step::Index(i));
+                collect_fields(value, steps, &current_path.append_index(i), true, fields);
+                steps.pop();
+            }
+        }
+        Value::String(text) if Syntax::default().contains_placeholder(text) => add_field(steps),
+        _ => {}
+    }
+}
+
+/// A source document read in place, without indexing its paths first
+struct DocumentSource<'a>(&'a Value);
+
+impl Source for DocumentSource<'_> {
+    fn get(&self, path: &AbsolutePath) -> Option<Cow<'_, Value>> {
+        // Array elements may also be named by the `$fields` of the enclosing object
+        let mut names: &[Value] = &[];
+        path.segments()
+            .try_fold(self.0, |value, segment| match value {
+                Value::Object(map) => {
+                    names = map
+                        .get(FIELDS_KEY)
+                        .and_then(Value::as_array)
+                        .map_or(&[], Vec::as_slice);
+                    map.get(segment.as_ref())
+                }
+                Value::Array(arr) => {
+                    let index = segment
+                        .parse::<usize>()
+                        .ok()
+                        .or_else(|| names.iter().position(|name| *name == *segment))?;
+                    names = &[];
+                    arr.get(index)
+                }
+                _ => None,
+            })
+            .map(Cow::Borrowed)
+    }
+
+    fn get_descendants(&self, field: &str) -> Option<Vec<Value>> {
+        let mut found = Vec::new();
+        collect_descendants(self.0, field, &mut found);
+        Some(found)
+    }
+}
+
+/// Collects the values of all fields named `field`, parents before their children
+fn collect_descendants(json: &Value, field: &str, found: &mut Vec<Value>) {
+    match json {
+        Value::Object(map) => {
+            for (key, value) in map {
+                if key == field {
+                    found.push(value.clone());
+                }
+                collect_descendants(value, field, found);
+            }
+        }
+        Value::Array(arr) => arr
+            .iter()
+            .for_each(|value| collect_descendants(value, field, found)),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_compiled_template_matches_resolve_template() {
+        let template = json!({
+            "$optional": ["/cache"],
+            "title": "{/app/name} v{/app/version}",
+            "port": "{/env/port:int}",
+            "lat": "{/place/point/lat}",
+            "names": "{$..name}",
+            "items": ["{$key}", { "key": "{$key}", "first": "{../../app/name|upper}" }],
+            "server": { "host?": "{/env/host}", "cache?": "{/cache}", "fixed": 1 },
+            "cache": "{/cache}",
+            "missing": "{/nope}"
+        });
+        let sources = [
+            json!({
+                "app": { "name": "demo", "version": "1.2" },
+                "env": { "port": "8080", "host": "localhost" },
+                "place": { "$fields": ["lat", "lng"], "point": [52.5, 13.4] }
+            }),
+            json!({ "app": { "name": "other" }, "env": { "port": "x" } }),
+            json!(null),
+        ];
+
+        let compiled = CompiledTemplate::new(&template);
+        for source in &sources {
+            assert_eq!(
+                compiled.apply(source),
+                crate::resolve_template_with_source(&template, source)
+            );
+        }
+        assert_eq!(compiled.apply(&sources[0])["lat"], json!(52.5));
+        assert_eq!(
+            compiled.apply(&sources[0])["server"],
+            json!({ "host": "localhost", "fixed": 1 })
+        );
+
+        let strict = ResolveOptions {
+            strict: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            compiled.apply_with_options(&sources[0], &strict),
+            Err(ResolveError::Unresolved {
+                at: AbsolutePath::new("/missing"),
+                reference: "/nope".to_string()
+            })
+        );
+    }
+}